@@ -1,33 +1,146 @@
+use std::io::Cursor;
+use std::ops::Range;
 use std::path::Path;
+use std::sync::{Mutex as StdMutex, OnceLock};
 
 use rubato::{FftFixedIn, Resampler};
-use symphonia::core::audio::{AudioBufferRef, Signal};
-use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
-use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::audio::{
+    AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec,
+};
+use symphonia::core::codecs::{
+    CodecDescriptor, CodecParameters, CodecRegistry, Decoder, DecoderOptions, FinalizeResult,
+    CODEC_TYPE_NULL, CODEC_TYPE_OPUS,
+};
+use symphonia::core::conv::IntoSample;
+use symphonia::core::errors::{
+    decode_error, unsupported_error, Error as SymphoniaError, Result as SymphoniaResult,
+};
 use symphonia::core::formats::FormatOptions;
+use symphonia::core::formats::{Packet, SeekMode, SeekTo};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
+use symphonia::core::sample::Sample;
+use symphonia::core::units::{Time, TimeBase, TimeStamp};
 
 pub const TARGET_SAMPLE_RATE: u32 = 16_000;
 
-pub fn load_and_resample(path: &Path) -> Result<Vec<f32>, String> {
-    let (samples, sample_rate) = load_audio(path)?;
+/// Opus always decodes at 48 kHz regardless of the rate the container reports.
+const OPUS_SAMPLE_RATE: u32 = 48_000;
+/// Longest Opus frame is 120 ms.
+const OPUS_MAX_FRAME_SAMPLES: usize = 5_760;
+/// Audio decoded after a seek needs 80 ms to converge (RFC 7845 §4.6).
+const OPUS_SEEK_PRE_ROLL: usize = 3_840;
+
+/// Symphonia's enabled codecs plus our libopus-backed Opus decoder, so that
+/// Ogg/Opus and WebM/Opus uploads (browser `MediaRecorder`) decode like any
+/// other container.
+fn codec_registry() -> &'static CodecRegistry {
+    static REGISTRY: OnceLock<CodecRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut registry = CodecRegistry::new();
+        symphonia::default::register_enabled_codecs(&mut registry);
+        registry.register_all::<OpusDecoder>();
+        registry
+    })
+}
+
+/// A section of a file to decode, in seconds from the start of the file.
+/// The default covers the whole file.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TimeRange {
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+/// Decodes an upload held in memory and resamples it to mono
+/// `TARGET_SAMPLE_RATE`. The file name and content type only serve as format
+/// hints; the bytes never touch disk.
+pub fn load_and_resample_bytes(
+    bytes: Vec<u8>,
+    file_name: Option<&str>,
+    content_type: Option<&str>,
+    range: TimeRange,
+    mode: ResampleMode,
+) -> Result<Vec<f32>, String> {
+    let (samples, sample_rate) = load_audio(bytes, format_hint(file_name, content_type), range)?;
     if sample_rate == TARGET_SAMPLE_RATE {
         return Ok(samples);
     }
-    resample(&samples, sample_rate, TARGET_SAMPLE_RATE)
+    resample(&samples, sample_rate, TARGET_SAMPLE_RATE, mode)
 }
 
-fn load_audio(path: &Path) -> Result<(Vec<f32>, u32), String> {
-    let file =
-        std::fs::File::open(path).map_err(|err| format!("Failed to open audio file: {err}"))?;
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+/// Like `load_and_resample_bytes`, but decodes every channel separately and
+/// resamples each one, for transcribing speakers recorded on separate
+/// channels.
+pub fn load_and_resample_channels_bytes(
+    bytes: Vec<u8>,
+    file_name: Option<&str>,
+    content_type: Option<&str>,
+    range: TimeRange,
+    mode: ResampleMode,
+) -> Result<Vec<Vec<f32>>, String> {
+    let (channels, sample_rate) =
+        load_audio_channels(bytes, format_hint(file_name, content_type), range)?;
+    channels
+        .iter()
+        .map(|samples| resample(samples, sample_rate, TARGET_SAMPLE_RATE, mode))
+        .collect()
+}
 
+fn format_hint(file_name: Option<&str>, content_type: Option<&str>) -> Hint {
     let mut hint = Hint::new();
-    if let Some(ext) = path.extension().and_then(|value| value.to_str()) {
+    if let Some(ext) = file_name
+        .and_then(|name| Path::new(name).extension())
+        .and_then(|value| value.to_str())
+    {
         hint.with_extension(ext);
     }
+    if let Some(mime) = content_type
+        .and_then(|value| value.split(';').next())
+        .map(str::trim)
+        .filter(|value| !value.is_empty() && *value != "application/octet-stream")
+    {
+        hint.mime_type(mime);
+    }
+    hint
+}
+
+fn load_audio(bytes: Vec<u8>, hint: Hint, range: TimeRange) -> Result<(Vec<f32>, u32), String> {
+    let mut all_samples = Vec::new();
+    let sample_rate = decode_audio(bytes, hint, range, |decoded, channels, frames| {
+        all_samples.extend_from_slice(&buffer_to_mono_f32(decoded, channels)[frames]);
+    })?;
+    Ok((all_samples, sample_rate))
+}
+
+fn load_audio_channels(
+    bytes: Vec<u8>,
+    hint: Hint,
+    range: TimeRange,
+) -> Result<(Vec<Vec<f32>>, u32), String> {
+    let mut all_channels: Vec<Vec<f32>> = Vec::new();
+    let sample_rate = decode_audio(bytes, hint, range, |decoded, channels, frames| {
+        if all_channels.len() < channels {
+            all_channels.resize_with(channels, Vec::new);
+        }
+        for (target, plane) in all_channels.iter_mut().zip(buffer_to_planes_f32(decoded)) {
+            target.extend_from_slice(&plane[frames.clone()]);
+        }
+    })?;
+    Ok((all_channels, sample_rate))
+}
+
+/// Decodes `range` of the first audio track, handing each decoded buffer,
+/// the track's channel count and the buffer's frames inside the range to
+/// `sink`. Returns the decoded sample rate.
+fn decode_audio(
+    bytes: Vec<u8>,
+    hint: Hint,
+    range: TimeRange,
+    mut sink: impl FnMut(&AudioBufferRef<'_>, usize, Range<usize>),
+) -> Result<u32, String> {
+    let mss = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
 
     let format_opts = FormatOptions::default();
     let metadata_opts = MetadataOptions::default();
@@ -43,18 +156,59 @@ fn load_audio(path: &Path) -> Result<(Vec<f32>, u32), String> {
         .ok_or_else(|| "No audio track found".to_string())?;
 
     let track_id = track.id;
-    let sample_rate = track
-        .codec_params
-        .sample_rate
-        .ok_or_else(|| "Unknown sample rate".to_string())?;
-    let channels = track.codec_params.channels.map(|c| c.count()).unwrap_or(1);
-
     let decoder_opts = DecoderOptions::default();
-    let mut decoder = symphonia::default::get_codecs()
+    let mut decoder = codec_registry()
         .make(&track.codec_params, &decoder_opts)
         .map_err(|err| format!("Failed to create decoder: {err:?}"))?;
 
-    let mut all_samples = Vec::new();
+    // Prefer the decoder's view: Opus output is 48 kHz even when a WebM
+    // track advertises the capture rate.
+    let sample_rate = decoder
+        .codec_params()
+        .sample_rate
+        .or(track.codec_params.sample_rate)
+        .ok_or_else(|| "Unknown sample rate".to_string())?;
+    let channels = decoder
+        .codec_params()
+        .channels
+        .or(track.codec_params.channels)
+        .map(|c| c.count())
+        .unwrap_or(1);
+    let time_base = track.codec_params.time_base;
+    // The Opus decoder drops its seek pre-roll after a reset, so seek back by
+    // that much to still start at the requested frame.
+    let seek_pre_roll = if track.codec_params.codec == CODEC_TYPE_OPUS {
+        OPUS_SEEK_PRE_ROLL as u64
+    } else {
+        0
+    };
+
+    let to_frame = |seconds: f64| (seconds.max(0.0) * sample_rate as f64).round() as u64;
+    let start_frame = to_frame(range.start);
+    let end_frame = range.end.map(to_frame);
+    // Frame index of the next decoded buffer, at the decoder's sample rate.
+    let mut position = 0u64;
+    // Closer to the start than the pre-roll, decoding from the top is as fast.
+    if start_frame > seek_pre_roll {
+        let seek_to = SeekTo::Time {
+            time: Time::from((start_frame - seek_pre_roll) as f64 / sample_rate as f64),
+            track_id: Some(track_id),
+        };
+        match format.seek(SeekMode::Accurate, seek_to) {
+            Ok(seeked) => {
+                decoder.reset();
+                position =
+                    timestamp_to_frame(seeked.actual_ts, time_base, sample_rate) + seek_pre_roll;
+            }
+            // Unseekable streams still work, just without the speed-up:
+            // `position` stays at 0 and the loop drops everything before
+            // the start.
+            Err(err) => {
+                eprintln!("[audio] seek failed, decoding and discarding up to the start: {err:?}")
+            }
+        }
+    }
+
     loop {
         let packet = match format.next_packet() {
             Ok(packet) => packet,
@@ -72,44 +226,78 @@ fn load_audio(path: &Path) -> Result<(Vec<f32>, u32), String> {
 
         let decoded = match decoder.decode(&packet) {
             Ok(decoded) => decoded,
-            Err(SymphoniaError::DecodeError(_)) => continue,
+            // Skip a corrupt packet, but count its frames so the ones after
+            // it stay at the right place in the range.
+            Err(SymphoniaError::DecodeError(_)) => {
+                position += timestamp_to_frame(packet.dur, time_base, sample_rate);
+                continue;
+            }
             Err(err) => return Err(format!("Decode error: {err:?}")),
         };
 
-        let samples = buffer_to_mono_f32(&decoded, channels);
-        all_samples.extend(samples);
+        let frames = decoded.frames() as u64;
+        let buffer_start = position;
+        position += frames;
+        if position <= start_frame {
+            continue;
+        }
+        if end_frame.is_some_and(|end| buffer_start >= end) {
+            break;
+        }
+        let from = start_frame.saturating_sub(buffer_start);
+        let to = end_frame.map_or(frames, |end| (end - buffer_start).min(frames));
+        sink(&decoded, channels, from as usize..to as usize);
     }
 
-    Ok((all_samples, sample_rate))
+    Ok(sample_rate)
 }
 
-fn buffer_to_mono_f32(buffer: &AudioBufferRef, channels: usize) -> Vec<f32> {
-    match buffer {
-        AudioBufferRef::F32(buf) => mix_to_mono_f32(buf.planes().planes(), buf.frames(), channels),
-        AudioBufferRef::S16(buf) => {
-            let planes: Vec<Vec<f32>> = buf
-                .planes()
-                .planes()
-                .iter()
-                .map(|plane| plane.iter().map(|&s| s as f32 / 32768.0).collect())
-                .collect();
-            let plane_refs: Vec<&[f32]> = planes.iter().map(|p| p.as_slice()).collect();
-            mix_to_mono_f32(&plane_refs, buf.frames(), channels)
-        }
-        AudioBufferRef::S32(buf) => {
-            let planes: Vec<Vec<f32>> = buf
-                .planes()
-                .planes()
-                .iter()
-                .map(|plane| plane.iter().map(|&s| s as f32 / 2147483648.0).collect())
-                .collect();
-            let plane_refs: Vec<&[f32]> = planes.iter().map(|p| p.as_slice()).collect();
-            mix_to_mono_f32(&plane_refs, buf.frames(), channels)
+fn timestamp_to_frame(ts: TimeStamp, time_base: Option<TimeBase>, sample_rate: u32) -> u64 {
+    match time_base {
+        Some(time_base) => {
+            let time = time_base.calc_time(ts);
+            ((time.seconds as f64 + time.frac) * sample_rate as f64).round() as u64
         }
-        _ => Vec::new(),
+        None => ts,
+    }
+}
+
+/// Copies every channel of a decoded buffer out as `f32`, whatever sample
+/// format the codec produced.
+fn buffer_to_planes_f32(buffer: &AudioBufferRef) -> Vec<Vec<f32>> {
+    fn planes<S: Sample + IntoSample<f32>>(buf: &AudioBuffer<S>) -> Vec<Vec<f32>> {
+        buf.planes()
+            .planes()
+            .iter()
+            .map(|plane| {
+                plane[..buf.frames()]
+                    .iter()
+                    .map(|&s| s.into_sample())
+                    .collect()
+            })
+            .collect()
+    }
+
+    match buffer {
+        AudioBufferRef::U8(buf) => planes(buf),
+        AudioBufferRef::U16(buf) => planes(buf),
+        AudioBufferRef::U24(buf) => planes(buf),
+        AudioBufferRef::U32(buf) => planes(buf),
+        AudioBufferRef::S8(buf) => planes(buf),
+        AudioBufferRef::S16(buf) => planes(buf),
+        AudioBufferRef::S24(buf) => planes(buf),
+        AudioBufferRef::S32(buf) => planes(buf),
+        AudioBufferRef::F32(buf) => planes(buf),
+        AudioBufferRef::F64(buf) => planes(buf),
     }
 }
 
+fn buffer_to_mono_f32(buffer: &AudioBufferRef, channels: usize) -> Vec<f32> {
+    let planes = buffer_to_planes_f32(buffer);
+    let plane_refs: Vec<&[f32]> = planes.iter().map(Vec::as_slice).collect();
+    mix_to_mono_f32(&plane_refs, buffer.frames(), channels)
+}
+
 fn mix_to_mono_f32(planes: &[&[f32]], frames: usize, channels: usize) -> Vec<f32> {
     if channels == 1 || planes.len() == 1 {
         return planes
@@ -128,37 +316,1151 @@ fn mix_to_mono_f32(planes: &[&[f32]], frames: usize, channels: usize) -> Vec<f32
     mono
 }
 
-fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Result<Vec<f32>, String> {
-    if from_rate == to_rate {
+/// A PCM sample type as delivered by an input device.
+pub trait PcmSample: Copy {
+    /// Maps the sample onto `-1.0..=1.0`, with silence at `0.0`.
+    fn to_f32(self) -> f32;
+}
+
+// Signed integers are scaled by the magnitude of their minimum, so the full
+// range maps onto [-1.0, 1.0) like symphonia's S16/S32 conversions above.
+macro_rules! impl_signed_pcm_sample {
+    ($($ty:ty),*) => {$(
+        impl PcmSample for $ty {
+            fn to_f32(self) -> f32 {
+                (self as f64 / -(<$ty>::MIN as f64)) as f32
+            }
+        }
+    )*};
+}
+
+// Unsigned integers have their silence at the midpoint of the range.
+macro_rules! impl_unsigned_pcm_sample {
+    ($($ty:ty),*) => {$(
+        impl PcmSample for $ty {
+            fn to_f32(self) -> f32 {
+                let mid = (<$ty>::MAX as f64 + 1.0) / 2.0;
+                ((self as f64 - mid) / mid) as f32
+            }
+        }
+    )*};
+}
+
+impl_signed_pcm_sample!(i8, i16, i32, i64);
+impl_unsigned_pcm_sample!(u8, u16, u32, u64);
+
+impl PcmSample for f32 {
+    fn to_f32(self) -> f32 {
+        self
+    }
+}
+
+impl PcmSample for f64 {
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+}
+
+/// Averages interleaved samples of any `PcmSample` type down to mono. A
+/// frame split across two calls is completed on the second one.
+pub struct MonoMixer {
+    channels: usize,
+    frame: Vec<f32>,
+}
+
+impl MonoMixer {
+    pub fn new(channels: usize) -> Self {
+        let channels = channels.max(1);
+        Self {
+            channels,
+            frame: Vec::with_capacity(channels),
+        }
+    }
+
+    pub fn mix<T: PcmSample>(&mut self, interleaved: &[T], mono: &mut Vec<f32>) {
+        for &sample in interleaved {
+            self.frame.push(sample.to_f32());
+            if self.frame.len() == self.channels {
+                mono.push(self.frame.iter().sum::<f32>() / self.channels as f32);
+                self.frame.clear();
+            }
+        }
+    }
+}
+
+/// How `resample` treats the resampler's latency and the final partial chunk.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResampleMode {
+    /// Emit whole output chunks: the tail is zero-padded and the FFT filter
+    /// delay is kept, so the output is shifted late and its length depends
+    /// on the chunk size rather than the input length.
+    Padded,
+    /// Drop the leading output delay and trim to `len * to / from`, keeping
+    /// timestamps aligned with the source.
+    #[default]
+    Exact,
+}
+
+/// Number of samples `len` source samples map to at the target rate.
+pub fn resampled_len(len: usize, from_rate: u32, to_rate: u32) -> usize {
+    (len as f64 * to_rate as f64 / from_rate as f64).round() as usize
+}
+
+pub fn resample(
+    samples: &[f32],
+    from_rate: u32,
+    to_rate: u32,
+    mode: ResampleMode,
+) -> Result<Vec<f32>, String> {
+    if from_rate == to_rate || samples.is_empty() {
         return Ok(samples.to_vec());
     }
 
     let mut resampler = FftFixedIn::<f32>::new(from_rate as usize, to_rate as usize, 1024, 2, 1)
         .map_err(|err| format!("Failed to create resampler: {err:?}"))?;
     let chunk_size = resampler.input_frames_max();
-    let mut output =
-        Vec::with_capacity((samples.len() as f64 * to_rate as f64 / from_rate as f64) as usize);
-
-    let mut pos = 0;
-    while pos < samples.len() {
-        let end = (pos + chunk_size).min(samples.len());
-        let chunk = &samples[pos..end];
-        let input = if chunk.len() < chunk_size {
-            let mut padded = chunk.to_vec();
-            padded.resize(chunk_size, 0.0);
-            vec![padded]
-        } else {
-            vec![chunk.to_vec()]
-        };
+    let expected = resampled_len(samples.len(), from_rate, to_rate);
+    let delay = match mode {
+        ResampleMode::Padded => 0,
+        ResampleMode::Exact => resampler.output_delay(),
+    };
+    let mut output = Vec::with_capacity(expected + delay + resampler.output_frames_max());
 
+    let mut chunks = samples.chunks_exact(chunk_size);
+    for chunk in &mut chunks {
         let resampled = resampler
-            .process(&input, None)
+            .process(&[chunk], None)
             .map_err(|err| format!("Resample failed: {err:?}"))?;
-        if let Some(channel) = resampled.get(0) {
-            output.extend(channel);
+        output.extend_from_slice(&resampled[0]);
+    }
+
+    let tail = chunks.remainder();
+    if mode == ResampleMode::Padded {
+        if !tail.is_empty() {
+            let mut padded = tail.to_vec();
+            padded.resize(chunk_size, 0.0);
+            let resampled = resampler
+                .process(&[padded], None)
+                .map_err(|err| format!("Resample failed: {err:?}"))?;
+            output.extend_from_slice(&resampled[0]);
         }
-        pos += chunk_size;
+        return Ok(output);
     }
 
+    if !tail.is_empty() {
+        let resampled = resampler
+            .process_partial(Some(&[tail]), None)
+            .map_err(|err| format!("Resample failed: {err:?}"))?;
+        output.extend_from_slice(&resampled[0]);
+    }
+
+    // Flush the filter until the delayed signal has fully come out.
+    while output.len() < delay + expected {
+        let resampled = resampler
+            .process_partial(None::<&[&[f32]]>, None)
+            .map_err(|err| format!("Resample failed: {err:?}"))?;
+        output.extend_from_slice(&resampled[0]);
+    }
+    output.drain(..delay);
+    output.truncate(expected);
     Ok(output)
 }
+
+/// Input block of the live resampler, about 20 ms at 48 kHz.
+const STREAM_RESAMPLE_BLOCK: usize = 1024;
+
+/// Resamples live 16-bit audio chunk by chunk, for realtime providers that
+/// do not accept the device rate. Input is held until a full block is
+/// available, and the filter delay is dropped like in `ResampleMode::Exact`.
+pub struct StreamResampler {
+    /// `None` when the rates already match.
+    resampler: Option<FftFixedIn<f32>>,
+    input: Vec<f32>,
+    from_rate: u32,
+    to_rate: u32,
+    /// Output samples still to discard for the filter delay.
+    skip: usize,
+    consumed: usize,
+    produced: usize,
+}
+
+impl StreamResampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Result<Self, String> {
+        let resampler = if from_rate == to_rate {
+            None
+        } else {
+            Some(
+                FftFixedIn::<f32>::new(
+                    from_rate as usize,
+                    to_rate as usize,
+                    STREAM_RESAMPLE_BLOCK,
+                    2,
+                    1,
+                )
+                .map_err(|err| format!("Failed to create resampler: {err:?}"))?,
+            )
+        };
+        let skip = resampler.as_ref().map_or(0, |r| r.output_delay());
+        Ok(Self {
+            resampler,
+            input: Vec::with_capacity(STREAM_RESAMPLE_BLOCK),
+            from_rate,
+            to_rate,
+            skip,
+            consumed: 0,
+            produced: 0,
+        })
+    }
+
+    pub fn process(&mut self, samples: &[i16]) -> Result<Vec<i16>, String> {
+        let Some(resampler) = self.resampler.as_mut() else {
+            return Ok(samples.to_vec());
+        };
+        self.consumed += samples.len();
+        self.input.extend(samples.iter().map(|&s| s.to_f32()));
+        let mut output = Vec::new();
+        let mut blocks = self.input.chunks_exact(STREAM_RESAMPLE_BLOCK);
+        for block in &mut blocks {
+            let resampled = resampler
+                .process(&[block], None)
+                .map_err(|err| format!("Resample failed: {err:?}"))?;
+            output.extend_from_slice(&resampled[0]);
+        }
+        let used = self.input.len() - blocks.remainder().len();
+        self.input.drain(..used);
+        Ok(self.emit(output))
+    }
+
+    /// Drains the buffered input at the end of the stream, so the total
+    /// output matches `resampled_len` of everything passed to `process`.
+    pub fn flush(&mut self) -> Result<Vec<i16>, String> {
+        let Some(resampler) = self.resampler.as_mut() else {
+            return Ok(Vec::new());
+        };
+        let expected = resampled_len(self.consumed, self.from_rate, self.to_rate);
+        let mut output = Vec::new();
+        if !self.input.is_empty() {
+            let resampled = resampler
+                .process_partial(Some(&[self.input.as_slice()]), None)
+                .map_err(|err| format!("Resample failed: {err:?}"))?;
+            output.extend_from_slice(&resampled[0]);
+            self.input.clear();
+        }
+        while self.produced + output.len().saturating_sub(self.skip) < expected {
+            let resampled = resampler
+                .process_partial(None::<&[&[f32]]>, None)
+                .map_err(|err| format!("Resample failed: {err:?}"))?;
+            output.extend_from_slice(&resampled[0]);
+        }
+        let before = self.produced;
+        let mut output = self.emit(output);
+        output.truncate(expected.saturating_sub(before));
+        Ok(output)
+    }
+
+    fn emit(&mut self, mut output: Vec<f32>) -> Vec<i16> {
+        let skipped = self.skip.min(output.len());
+        self.skip -= skipped;
+        output.drain(..skipped);
+        self.produced += output.len();
+        output.into_iter().map(f32_to_i16).collect()
+    }
+}
+
+/// Converts a float sample to 16-bit PCM, clamping out-of-range values.
+pub fn f32_to_i16(sample: f32) -> i16 {
+    let clamped = sample.clamp(-1.0, 1.0);
+    if clamped < 0.0 {
+        (clamped * 0x8000 as f32) as i16
+    } else {
+        (clamped * 0x7FFF as f32) as i16
+    }
+}
+
+/// Energy is measured over 30 ms frames.
+const VAD_FRAME_MS: usize = 30;
+/// Frames this far above the noise floor count as speech.
+const VAD_MARGIN_DB: f32 = 12.0;
+/// Never treat anything quieter than this as speech.
+const VAD_MIN_THRESHOLD_DB: f32 = -55.0;
+/// Keep this much audio around each speech region so word edges survive.
+const VAD_PAD_MS: usize = 200;
+/// Pauses shorter than this stay inside the surrounding region.
+const VAD_MERGE_GAP_MS: usize = 300;
+/// Regions shorter than this are clicks, not speech.
+const VAD_MIN_SPEECH_MS: usize = 90;
+/// Silence left between regions after cutting, so whisper still sees a pause.
+const VAD_JOIN_GAP_MS: usize = 100;
+
+/// Finds speech in mono samples with an adaptive energy threshold. Returns
+/// sample ranges in ascending order, padded and merged.
+pub fn speech_regions(samples: &[f32], sample_rate: u32) -> Vec<Range<usize>> {
+    let ms = |value: usize| value * sample_rate as usize / 1000;
+    let frame = ms(VAD_FRAME_MS).max(1);
+    if samples.len() < frame {
+        return std::iter::once(0..samples.len()).collect();
+    }
+
+    let energies: Vec<f32> = samples
+        .chunks(frame)
+        .map(|chunk| {
+            let power = chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32;
+            10.0 * (power + 1e-10).log10()
+        })
+        .collect();
+    let mut sorted = energies.clone();
+    sorted.sort_by(f32::total_cmp);
+    let floor = sorted[sorted.len() / 10];
+    let peak = sorted[sorted.len() - 1];
+    // Mostly-speech recordings have a high floor; stay below the loud parts.
+    let threshold = (floor + VAD_MARGIN_DB)
+        .min(peak - VAD_MARGIN_DB)
+        .max(VAD_MIN_THRESHOLD_DB);
+
+    let mut regions: Vec<Range<usize>> = Vec::new();
+    for (index, energy) in energies.iter().enumerate() {
+        if *energy < threshold {
+            continue;
+        }
+        let start = index * frame;
+        let end = (start + frame).min(samples.len());
+        match regions.last_mut() {
+            Some(last) if start - last.end < ms(VAD_MERGE_GAP_MS) => last.end = end,
+            _ => regions.push(start..end),
+        }
+    }
+
+    let pad = ms(VAD_PAD_MS);
+    let mut padded: Vec<Range<usize>> = Vec::new();
+    for region in regions {
+        if region.len() < ms(VAD_MIN_SPEECH_MS) {
+            continue;
+        }
+        let start = region.start.saturating_sub(pad);
+        let end = (region.end + pad).min(samples.len());
+        match padded.last_mut() {
+            Some(last) if start <= last.end => last.end = end,
+            _ => padded.push(start..end),
+        }
+    }
+    padded
+}
+
+/// How fast the live noise floor may rise, in dB per second. Pauses between
+/// words pull it back down, so it follows the room rather than the voice.
+const LIVE_VAD_FLOOR_RISE_DB: f32 = 3.0;
+
+/// The energy rule of `speech_regions` applied to live input, one level
+/// window at a time. Tracks how long the input has been silent.
+pub struct LiveVad {
+    floor_db: f32,
+    silent_secs: f32,
+}
+
+impl Default for LiveVad {
+    fn default() -> Self {
+        Self {
+            // Start as a quiet room; the floor rises to the real one.
+            floor_db: VAD_MIN_THRESHOLD_DB - VAD_MARGIN_DB,
+            silent_secs: 0.0,
+        }
+    }
+}
+
+impl LiveVad {
+    /// Feeds the RMS of `secs` of audio and returns the trailing silence
+    /// in seconds.
+    pub fn push(&mut self, rms: f32, secs: f32) -> f32 {
+        let level_db = 20.0 * (rms + 1e-5).log10();
+        self.floor_db = level_db.min(self.floor_db + LIVE_VAD_FLOOR_RISE_DB * secs);
+        let threshold = (self.floor_db + VAD_MARGIN_DB).max(VAD_MIN_THRESHOLD_DB);
+        if level_db >= threshold {
+            self.silent_secs = 0.0;
+        } else {
+            self.silent_secs += secs;
+        }
+        self.silent_secs
+    }
+}
+
+/// Where a run of kept samples sits in the original and in the cut audio.
+#[derive(Clone, Copy, Debug)]
+struct SpeechSpan {
+    source_start: usize,
+    compact_start: usize,
+    len: usize,
+}
+
+/// Maps positions in audio produced by `strip_silence` back to the original.
+#[derive(Debug)]
+pub struct SpeechMap {
+    spans: Vec<SpeechSpan>,
+    sample_rate: u32,
+}
+
+impl SpeechMap {
+    /// Converts a time in the cut audio to the matching time in the
+    /// original. Times inside an inserted gap map to the end of the span
+    /// before it.
+    pub fn source_seconds(&self, compact_seconds: f64) -> f64 {
+        let rate = self.sample_rate as f64;
+        let position = (compact_seconds.max(0.0) * rate).round() as usize;
+        let span = self
+            .spans
+            .iter()
+            .rev()
+            .find(|span| span.compact_start <= position)
+            .or(self.spans.first());
+        match span {
+            Some(span) => {
+                let offset = position.saturating_sub(span.compact_start).min(span.len);
+                (span.source_start + offset) as f64 / rate
+            }
+            None => compact_seconds,
+        }
+    }
+
+    /// Total length of the kept audio, without the inserted gaps.
+    pub fn speech_samples(&self) -> usize {
+        self.spans.iter().map(|span| span.len).sum()
+    }
+}
+
+/// Keeps only `regions` of `samples`, joined by short silences, and returns
+/// the map needed to put timestamps back on the original timeline.
+pub fn strip_silence(
+    samples: &[f32],
+    sample_rate: u32,
+    regions: &[Range<usize>],
+) -> (Vec<f32>, SpeechMap) {
+    let gap = VAD_JOIN_GAP_MS * sample_rate as usize / 1000;
+    let mut output = Vec::new();
+    let mut spans = Vec::with_capacity(regions.len());
+    for region in regions {
+        let region = region.start.min(samples.len())..region.end.min(samples.len());
+        if region.is_empty() {
+            continue;
+        }
+        if !output.is_empty() {
+            output.resize(output.len() + gap, 0.0);
+        }
+        spans.push(SpeechSpan {
+            source_start: region.start,
+            compact_start: output.len(),
+            len: region.len(),
+        });
+        output.extend_from_slice(&samples[region]);
+    }
+    (output, SpeechMap { spans, sample_rate })
+}
+
+/// Minimal symphonia `Decoder` over libopus for mono and stereo streams.
+struct OpusDecoder {
+    params: CodecParameters,
+    // libopus handles are `Send` but not `Sync`; symphonia requires both.
+    decoder: StdMutex<opus::Decoder>,
+    buffer: AudioBuffer<f32>,
+    interleaved: Vec<f32>,
+    channels: usize,
+    pre_skip: usize,
+}
+
+/// Reads channel count and pre-skip from an `OpusHead` identification header,
+/// which both Ogg and Matroska carry as the track's extra data.
+fn parse_opus_head(extra_data: &[u8]) -> Option<(usize, usize)> {
+    if extra_data.len() < 19 || &extra_data[..8] != b"OpusHead" {
+        return None;
+    }
+    let channels = extra_data[9] as usize;
+    let pre_skip = u16::from_le_bytes([extra_data[10], extra_data[11]]) as usize;
+    Some((channels, pre_skip))
+}
+
+impl Decoder for OpusDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> SymphoniaResult<Self> {
+        let head = params.extra_data.as_deref().and_then(parse_opus_head);
+        let channels = head
+            .map(|(channels, _)| channels)
+            .or_else(|| params.channels.map(|c| c.count()))
+            .unwrap_or(1);
+        let pre_skip = head
+            .map(|(_, pre_skip)| pre_skip)
+            .unwrap_or(params.delay.unwrap_or(0) as usize);
+
+        let (opus_channels, layout) = match channels {
+            1 => (opus::Channels::Mono, Channels::FRONT_LEFT),
+            2 => (
+                opus::Channels::Stereo,
+                Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+            ),
+            _ => return unsupported_error("opus: multistream audio is not supported"),
+        };
+        let decoder = opus::Decoder::new(OPUS_SAMPLE_RATE, opus_channels)
+            .map_err(|_| SymphoniaError::Unsupported("opus: failed to create decoder"))?;
+
+        let mut params = params.clone();
+        params
+            .with_sample_rate(OPUS_SAMPLE_RATE)
+            .with_channels(layout);
+
+        Ok(Self {
+            params,
+            decoder: StdMutex::new(decoder),
+            buffer: AudioBuffer::new(
+                OPUS_MAX_FRAME_SAMPLES as u64,
+                SignalSpec::new(OPUS_SAMPLE_RATE, layout),
+            ),
+            interleaved: vec![0.0; OPUS_MAX_FRAME_SAMPLES * channels],
+            channels,
+            pre_skip,
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[CodecDescriptor {
+            codec: CODEC_TYPE_OPUS,
+            short_name: "opus",
+            long_name: "Opus (libopus)",
+            inst_func: |params, options| Ok(Box::new(OpusDecoder::try_new(params, options)?)),
+        }]
+    }
+
+    fn reset(&mut self) {
+        if let Ok(decoder) = self.decoder.get_mut() {
+            let _ = decoder.reset_state();
+        }
+        // Pre-skip only applies to the start of the stream; after a seek the
+        // decoder state is cold, so decode and discard the pre-roll instead.
+        self.pre_skip = OPUS_SEEK_PRE_ROLL;
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> SymphoniaResult<AudioBufferRef<'_>> {
+        self.buffer.clear();
+        let decoder = match self.decoder.get_mut() {
+            Ok(decoder) => decoder,
+            Err(_) => return decode_error("opus: decoder poisoned"),
+        };
+        let frames = match decoder.decode_float(packet.buf(), &mut self.interleaved, false) {
+            Ok(frames) => frames,
+            Err(_) => return decode_error("opus: invalid packet"),
+        };
+
+        let skip = self.pre_skip.min(frames);
+        self.pre_skip -= skip;
+        let kept = frames - skip;
+
+        self.buffer.render_reserved(Some(kept));
+        for ch in 0..self.channels {
+            let plane = self.buffer.chan_mut(ch);
+            for (idx, sample) in plane.iter_mut().enumerate() {
+                *sample = self.interleaved[(skip + idx) * self.channels + ch];
+            }
+        }
+        Ok(self.buffer.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buffer.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn stream_in_chunks(
+        resampler: &mut StreamResampler,
+        input: &[i16],
+        sizes: &[usize],
+    ) -> Vec<i16> {
+        let mut output = Vec::new();
+        let mut rest = input;
+        for &size in sizes.iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (chunk, tail) = rest.split_at(size.min(rest.len()));
+            output.extend(resampler.process(chunk).unwrap());
+            rest = tail;
+        }
+        output.extend(resampler.flush().unwrap());
+        output
+    }
+
+    #[test]
+    fn stream_resampler_matches_batch_resampling() {
+        let source = sine(48_000, 48_000, 440.0);
+        let pcm: Vec<i16> = source.iter().map(|&s| f32_to_i16(s)).collect();
+        let reference = resample(
+            &pcm.iter().map(|&s| s.to_f32()).collect::<Vec<_>>(),
+            48_000,
+            16_000,
+            ResampleMode::Exact,
+        )
+        .unwrap();
+
+        let mut resampler = StreamResampler::new(48_000, 16_000).unwrap();
+        let streamed = stream_in_chunks(&mut resampler, &pcm, &[4_800, 333, 1_024, 7]);
+        assert_eq!(streamed.len(), reference.len());
+        for (streamed, reference) in streamed.iter().zip(&reference) {
+            assert!((streamed.to_f32() - reference).abs() < 2.0 / 32768.0);
+        }
+    }
+
+    #[test]
+    fn stream_resampler_output_length_tracks_input() {
+        for (from, to) in [(44_100, 16_000), (8_000, 16_000), (96_000, 24_000)] {
+            let input = vec![0i16; from as usize / 3 + 17];
+            let mut resampler = StreamResampler::new(from, to).unwrap();
+            let output = stream_in_chunks(&mut resampler, &input, &[from as usize / 10]);
+            assert_eq!(output.len(), resampled_len(input.len(), from, to));
+        }
+    }
+
+    #[test]
+    fn stream_resampler_passes_matching_rates_through() {
+        let mut resampler = StreamResampler::new(16_000, 16_000).unwrap();
+        assert_eq!(resampler.process(&[1, -2, 3]).unwrap(), vec![1, -2, 3]);
+        assert!(resampler.flush().unwrap().is_empty());
+    }
+
+    #[test]
+    fn signed_samples_map_onto_unit_range() {
+        assert_eq!(0i8.to_f32(), 0.0);
+        assert_eq!(i8::MIN.to_f32(), -1.0);
+        assert_eq!(i8::MAX.to_f32(), 127.0 / 128.0);
+        assert_eq!(i16::MIN.to_f32(), -1.0);
+        assert_eq!(16_384i16.to_f32(), 0.5);
+        assert_eq!(i32::MIN.to_f32(), -1.0);
+        assert_eq!((i32::MAX / 2 + 1).to_f32(), 0.5);
+        assert_eq!(i64::MIN.to_f32(), -1.0);
+        assert_eq!(0i64.to_f32(), 0.0);
+        assert!((i64::MAX.to_f32() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unsigned_samples_center_on_the_midpoint() {
+        assert_eq!(128u8.to_f32(), 0.0);
+        assert_eq!(0u8.to_f32(), -1.0);
+        assert_eq!(u8::MAX.to_f32(), 127.0 / 128.0);
+        assert_eq!(32_768u16.to_f32(), 0.0);
+        assert_eq!(0u16.to_f32(), -1.0);
+        assert_eq!(49_152u16.to_f32(), 0.5);
+        assert_eq!((1u32 << 31).to_f32(), 0.0);
+        assert_eq!(0u32.to_f32(), -1.0);
+        assert_eq!((1u64 << 63).to_f32(), 0.0);
+        assert_eq!(0u64.to_f32(), -1.0);
+    }
+
+    #[test]
+    fn float_samples_pass_through() {
+        assert_eq!(0.25f32.to_f32(), 0.25);
+        assert_eq!((-0.75f64).to_f32(), -0.75);
+        assert_eq!(1.0f64.to_f32(), 1.0);
+    }
+
+    #[test]
+    fn integer_formats_agree_on_the_same_level() {
+        // Half of full scale in every format.
+        let expected = 0.5;
+        assert_eq!(64i8.to_f32(), expected);
+        assert_eq!(16_384i16.to_f32(), expected);
+        assert_eq!((1i32 << 30).to_f32(), expected);
+        assert_eq!((1i64 << 62).to_f32(), expected);
+        assert_eq!(192u8.to_f32(), expected);
+        assert_eq!(49_152u16.to_f32(), expected);
+        assert_eq!((3u32 << 30).to_f32(), expected);
+        assert_eq!((3u64 << 62).to_f32(), expected);
+    }
+
+    #[test]
+    fn mono_mixer_averages_frames() {
+        let mut mixer = MonoMixer::new(2);
+        let mut mono = Vec::new();
+        mixer.mix(&[16_384i16, -16_384, 16_384, 16_384], &mut mono);
+        assert_eq!(mono, vec![0.0, 0.5]);
+
+        let mut mixer = MonoMixer::new(1);
+        let mut mono = Vec::new();
+        mixer.mix(&[0u8, 128, 192], &mut mono);
+        assert_eq!(mono, vec![-1.0, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn mono_mixer_carries_partial_frames() {
+        let mut mixer = MonoMixer::new(3);
+        let mut mono = Vec::new();
+        mixer.mix(&[0.3f32, 0.3], &mut mono);
+        assert!(mono.is_empty());
+        mixer.mix(&[0.3f32, -0.6, -0.6, -0.6, 0.9], &mut mono);
+        assert_eq!(mono.len(), 2);
+        assert!((mono[0] - 0.3).abs() < 1e-6);
+        assert!((mono[1] + 0.6).abs() < 1e-6);
+        mixer.mix(&[0.0f32, 0.0], &mut mono);
+        assert!((mono[2] - 0.3).abs() < 1e-6);
+    }
+
+    const RATES: [u32; 5] = [8_000, 22_050, 44_100, 48_000, 96_000];
+    /// Opus frame of 20 ms at 48 kHz.
+    const OPUS_FRAME: usize = 960;
+
+    fn sine(len: usize, rate: u32, freq: f32) -> Vec<f32> {
+        (0..len)
+            .map(|i| (i as f32 * freq * std::f32::consts::TAU / rate as f32).sin() * 0.5)
+            .collect()
+    }
+
+    /// Encodes one second of a 440 Hz tone as mono Opus packets and returns
+    /// them with the encoder's pre-skip.
+    fn opus_tone_packets() -> (Vec<Vec<u8>>, usize) {
+        let mut encoder = opus::Encoder::new(
+            OPUS_SAMPLE_RATE,
+            opus::Channels::Mono,
+            opus::Application::Audio,
+        )
+        .unwrap();
+        let tone = sine(OPUS_SAMPLE_RATE as usize, OPUS_SAMPLE_RATE, 440.0);
+        let packets = tone
+            .chunks_exact(OPUS_FRAME)
+            .map(|frame| {
+                let mut packet = vec![0u8; 4_000];
+                let len = encoder.encode_float(frame, &mut packet).unwrap();
+                packet.truncate(len);
+                packet
+            })
+            .collect();
+        // libopus' encoder lookahead at 48 kHz.
+        (packets, 312)
+    }
+
+    fn opus_head(pre_skip: usize) -> Vec<u8> {
+        let mut head = b"OpusHead".to_vec();
+        head.extend_from_slice(&[1, 1]);
+        head.extend_from_slice(&(pre_skip as u16).to_le_bytes());
+        head.extend_from_slice(&OPUS_SAMPLE_RATE.to_le_bytes());
+        head.extend_from_slice(&[0, 0, 0]);
+        head
+    }
+
+    fn ogg_crc(data: &[u8]) -> u32 {
+        data.iter().fold(0u32, |crc, &byte| {
+            (0..8).fold(crc ^ ((byte as u32) << 24), |crc, _| {
+                if crc & 0x8000_0000 != 0 {
+                    (crc << 1) ^ 0x04C1_1DB7
+                } else {
+                    crc << 1
+                }
+            })
+        })
+    }
+
+    /// One Ogg page holding one packet.
+    fn ogg_page(packet: &[u8], sequence: u32, granule: u64, flags: u8) -> Vec<u8> {
+        let mut page = b"OggS".to_vec();
+        page.extend_from_slice(&[0, flags]);
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&1u32.to_le_bytes());
+        page.extend_from_slice(&sequence.to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        let mut lacing = vec![255u8; packet.len() / 255];
+        lacing.push((packet.len() % 255) as u8);
+        page.push(lacing.len() as u8);
+        page.extend_from_slice(&lacing);
+        page.extend_from_slice(packet);
+        let crc = ogg_crc(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        page
+    }
+
+    fn ogg_opus_bytes(packets: &[Vec<u8>], pre_skip: usize) -> Vec<u8> {
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend_from_slice(&[0; 8]);
+        let mut bytes = ogg_page(&opus_head(pre_skip), 0, 0, 0x02);
+        bytes.extend(ogg_page(&tags, 1, 0, 0));
+        for (index, packet) in packets.iter().enumerate() {
+            let last = index + 1 == packets.len();
+            let granule = ((index + 1) * OPUS_FRAME) as u64;
+            let flags = if last { 0x04 } else { 0 };
+            bytes.extend(ogg_page(packet, index as u32 + 2, granule, flags));
+        }
+        bytes
+    }
+
+    fn ebml(id: &[u8], body: &[u8]) -> Vec<u8> {
+        // Sizes are written as eight-byte variable-length integers.
+        let mut element = id.to_vec();
+        element.push(0x01);
+        element.extend_from_slice(&(body.len() as u64).to_be_bytes()[1..]);
+        element.extend_from_slice(body);
+        element
+    }
+
+    fn webm_opus_bytes(packets: &[Vec<u8>], pre_skip: usize) -> Vec<u8> {
+        let header = [
+            ebml(&[0x42, 0x86], &[1]),
+            ebml(&[0x42, 0xF7], &[1]),
+            ebml(&[0x42, 0xF2], &[4]),
+            ebml(&[0x42, 0xF3], &[8]),
+            ebml(&[0x42, 0x82], b"webm"),
+        ]
+        .concat();
+        let audio = [
+            // Browsers report the capture rate here, not the Opus rate.
+            ebml(&[0xB5], &44_100f64.to_be_bytes()),
+            ebml(&[0x9F], &[1]),
+        ]
+        .concat();
+        let track = [
+            ebml(&[0xD7], &[1]),
+            ebml(&[0x73, 0xC5], &[1]),
+            ebml(&[0x83], &[2]),
+            ebml(&[0x86], b"A_OPUS"),
+            ebml(&[0x63, 0xA2], &opus_head(pre_skip)),
+            ebml(&[0xE1], &audio),
+        ]
+        .concat();
+        let mut cluster = ebml(&[0xE7], &[0]);
+        for (index, packet) in packets.iter().enumerate() {
+            let mut block = vec![0x81];
+            block.extend_from_slice(&(index as i16 * 20).to_be_bytes());
+            block.push(0x80);
+            block.extend_from_slice(packet);
+            cluster.extend(ebml(&[0xA3], &block));
+        }
+        let segment = [
+            ebml(
+                &[0x15, 0x49, 0xA9, 0x66],
+                &ebml(&[0x2A, 0xD7, 0xB1], &[0x0F, 0x42, 0x40]),
+            ),
+            ebml(&[0x16, 0x54, 0xAE, 0x6B], &ebml(&[0xAE], &track)),
+            ebml(&[0x1F, 0x43, 0xB6, 0x75], &cluster),
+        ]
+        .concat();
+        [
+            ebml(&[0x1A, 0x45, 0xDF, 0xA3], &header),
+            ebml(&[0x18, 0x53, 0x80, 0x67], &segment),
+        ]
+        .concat()
+    }
+
+    /// Checks a decoded one-second 440 Hz tone: pre-skip trimmed, resampled
+    /// to 16 kHz and still the same tone.
+    fn assert_decoded_tone(decoded: &[f32], pre_skip: usize) {
+        let expected = resampled_len(
+            OPUS_SAMPLE_RATE as usize - pre_skip,
+            OPUS_SAMPLE_RATE,
+            TARGET_SAMPLE_RATE,
+        );
+        assert_eq!(decoded.len(), expected);
+        let middle = &decoded[4_000..12_000];
+        let rms = (middle.iter().map(|s| s * s).sum::<f32>() / middle.len() as f32).sqrt();
+        assert!((rms - 0.5 / 2f32.sqrt()).abs() < 0.05, "rms {rms}");
+        let crossings = middle
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count();
+        // 440 Hz crosses zero 880 times a second; the window is half a second.
+        assert!(crossings.abs_diff(440) <= 4, "{crossings} zero crossings");
+    }
+
+    #[test]
+    fn decodes_ogg_opus_from_memory() {
+        let (packets, pre_skip) = opus_tone_packets();
+        let bytes = ogg_opus_bytes(&packets, pre_skip);
+        let decoded = load_and_resample_bytes(
+            bytes,
+            Some("clip.opus"),
+            None,
+            TimeRange::default(),
+            ResampleMode::Exact,
+        )
+        .unwrap();
+        assert_decoded_tone(&decoded, pre_skip);
+    }
+
+    #[test]
+    fn decodes_webm_opus_at_the_opus_rate() {
+        let (packets, pre_skip) = opus_tone_packets();
+        let bytes = webm_opus_bytes(&packets, pre_skip);
+        let decoded = load_and_resample_bytes(
+            bytes,
+            None,
+            Some("audio/webm;codecs=opus"),
+            TimeRange::default(),
+            ResampleMode::Exact,
+        )
+        .unwrap();
+        assert_decoded_tone(&decoded, pre_skip);
+    }
+
+    #[test]
+    fn discards_opus_pre_roll_after_a_seek() {
+        let (packets, pre_skip) = opus_tone_packets();
+        let bytes = ogg_opus_bytes(&packets, pre_skip);
+        let range = TimeRange {
+            start: 0.5,
+            end: None,
+        };
+        let decoded =
+            load_and_resample_bytes(bytes, Some("clip.opus"), None, range, ResampleMode::Exact)
+                .unwrap();
+        // A cold decoder fades in; with the pre-roll dropped, the tone is at
+        // full level from the first frame of the range.
+        let head = &decoded[160..480];
+        let rms = (head.iter().map(|s| s * s).sum::<f32>() / head.len() as f32).sqrt();
+        assert!((rms - 0.5 / 2f32.sqrt()).abs() < 0.05, "rms {rms}");
+    }
+
+    /// A WAV file of `data`, already encoded as `bits`-bit samples of
+    /// `format` (1 for integer PCM, 3 for float).
+    fn pcm_wav_bytes(
+        format: u16,
+        bits: u16,
+        channels: u16,
+        sample_rate: u32,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let data_len = data.len() as u32;
+        let mut bytes = Vec::with_capacity(44 + data.len());
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&format.to_le_bytes());
+        bytes.extend_from_slice(&channels.to_le_bytes());
+        bytes.extend_from_slice(&sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        bytes.extend_from_slice(&block_align.to_le_bytes());
+        bytes.extend_from_slice(&bits.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn decodes_every_pcm_sample_format() {
+        // Stereo frames of +0.5 left and -0.5 right, in each format.
+        let frame_formats: [(u16, u16, [&[u8]; 2]); 5] = [
+            (1, 8, [&[192], &[64]]),
+            (1, 24, [&[0x00, 0x00, 0x40], &[0x00, 0x00, 0xC0]]),
+            (
+                1,
+                32,
+                [&(1i32 << 30).to_le_bytes(), &(-(1i32 << 30)).to_le_bytes()],
+            ),
+            (3, 32, [&0.5f32.to_le_bytes(), &(-0.5f32).to_le_bytes()]),
+            (3, 64, [&0.5f64.to_le_bytes(), &(-0.5f64).to_le_bytes()]),
+        ];
+        for (format, bits, [left, right]) in frame_formats {
+            let data = [left, right].concat().repeat(160);
+            let bytes = pcm_wav_bytes(format, bits, 2, TARGET_SAMPLE_RATE, &data);
+
+            let channels = load_and_resample_channels_bytes(
+                bytes.clone(),
+                Some("clip.wav"),
+                None,
+                TimeRange::default(),
+                ResampleMode::Exact,
+            )
+            .unwrap();
+            assert_eq!(channels.len(), 2, "{bits}-bit format {format}");
+            assert!(channels[0].len() == 160 && channels[0].iter().all(|&s| s == 0.5));
+            assert!(channels[1].len() == 160 && channels[1].iter().all(|&s| s == -0.5));
+
+            let mono = load_and_resample_bytes(
+                bytes,
+                Some("clip.wav"),
+                None,
+                TimeRange::default(),
+                ResampleMode::Exact,
+            )
+            .unwrap();
+            assert_eq!(mono, vec![0.0; 160], "{bits}-bit format {format}");
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(48))]
+
+        #[test]
+        fn exact_resample_matches_expected_length(
+            from in prop::sample::select(RATES.to_vec()),
+            to in prop::sample::select(RATES.to_vec()),
+            len in 0usize..40_000,
+        ) {
+            let input = sine(len, from, 440.0);
+            let output = resample(&input, from, to, ResampleMode::Exact).unwrap();
+            prop_assert_eq!(output.len(), resampled_len(len, from, to));
+        }
+
+        #[test]
+        fn exact_resample_to_target_rate_matches_expected_length(
+            from in prop::sample::select(RATES.to_vec()),
+            len in 1usize..40_000,
+        ) {
+            let input = sine(len, from, 440.0);
+            let output = resample(&input, from, TARGET_SAMPLE_RATE, ResampleMode::Exact).unwrap();
+            prop_assert_eq!(output.len(), resampled_len(len, from, TARGET_SAMPLE_RATE));
+        }
+    }
+
+    #[test]
+    fn padded_resample_is_not_sample_accurate() {
+        let input = sine(5_000, 48_000, 440.0);
+        let padded = resample(&input, 48_000, 16_000, ResampleMode::Padded).unwrap();
+        let exact = resample(&input, 48_000, 16_000, ResampleMode::Exact).unwrap();
+        assert_ne!(padded.len(), exact.len());
+        assert_eq!(exact.len(), resampled_len(input.len(), 48_000, 16_000));
+    }
+
+    #[test]
+    fn exact_resample_compensates_output_delay() {
+        for &from in &RATES {
+            let len = from as usize;
+            let mut input = vec![0.0f32; len];
+            let impulse_at = len / 2;
+            input[impulse_at] = 1.0;
+
+            let output = resample(&input, from, TARGET_SAMPLE_RATE, ResampleMode::Exact).unwrap();
+            let peak = output
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+                .map(|(idx, _)| idx)
+                .unwrap();
+            let expected = resampled_len(impulse_at, from, TARGET_SAMPLE_RATE);
+            assert!(
+                peak.abs_diff(expected) <= 1,
+                "{from} Hz: peak at {peak}, expected {expected}"
+            );
+        }
+    }
+
+    fn speech_with_silence(rate: u32) -> Vec<f32> {
+        let second = rate as usize;
+        let mut samples = vec![0.0f32; 2 * second];
+        samples.extend(sine(second, rate, 220.0));
+        samples.extend(vec![0.0f32; 3 * second]);
+        samples.extend(sine(second / 2, rate, 220.0));
+        samples.extend(vec![0.0f32; second]);
+        samples
+    }
+
+    #[test]
+    fn energy_vad_finds_speech_regions() {
+        let rate = TARGET_SAMPLE_RATE;
+        let samples = speech_with_silence(rate);
+        let regions = speech_regions(&samples, rate);
+        assert_eq!(regions.len(), 2);
+        let seconds = |pos: usize| pos as f64 / rate as f64;
+        assert!((seconds(regions[0].start) - 1.8).abs() < 0.05);
+        assert!((seconds(regions[0].end) - 3.2).abs() < 0.05);
+        assert!((seconds(regions[1].start) - 5.8).abs() < 0.05);
+        assert!((seconds(regions[1].end) - 6.7).abs() < 0.05);
+    }
+
+    #[test]
+    fn energy_vad_keeps_continuous_speech() {
+        let samples = sine(3 * TARGET_SAMPLE_RATE as usize, TARGET_SAMPLE_RATE, 220.0);
+        let regions = speech_regions(&samples, TARGET_SAMPLE_RATE);
+        assert_eq!(regions, vec![0..samples.len()]);
+    }
+
+    fn live_vad_run(vad: &mut LiveVad, rms: f32, secs: f32) -> f32 {
+        let mut silent = 0.0;
+        for _ in 0..(secs * 20.0) as usize {
+            silent = vad.push(rms, 0.05);
+        }
+        silent
+    }
+
+    #[test]
+    fn live_vad_counts_trailing_silence() {
+        let mut vad = LiveVad::default();
+        assert_eq!(live_vad_run(&mut vad, 0.1, 2.0), 0.0);
+        let silent = live_vad_run(&mut vad, 0.0005, 1.5);
+        assert!((silent - 1.5).abs() < 0.01, "{silent}");
+        assert_eq!(live_vad_run(&mut vad, 0.1, 0.1), 0.0);
+    }
+
+    #[test]
+    fn live_vad_adapts_to_a_noisy_room() {
+        // -40 dBFS of steady noise is above the starting floor, but the
+        // floor catches up and the noise ends up counted as silence.
+        let mut vad = LiveVad::default();
+        let silent = live_vad_run(&mut vad, 0.01, 15.0);
+        assert!(silent > 2.0, "{silent}");
+        assert_eq!(live_vad_run(&mut vad, 0.1, 0.1), 0.0);
+    }
+
+    #[test]
+    fn strip_silence_maps_times_back_to_source() {
+        let rate = TARGET_SAMPLE_RATE;
+        let samples = speech_with_silence(rate);
+        let regions = speech_regions(&samples, rate);
+        let (speech, map) = strip_silence(&samples, rate, &regions);
+        assert!(speech.len() < samples.len() / 2);
+        assert_eq!(
+            map.speech_samples(),
+            regions.iter().map(|r| r.len()).sum::<usize>()
+        );
+
+        let first = regions[0].start as f64 / rate as f64;
+        assert!((map.source_seconds(0.0) - first).abs() < 1e-6);
+        assert!((map.source_seconds(0.5) - (first + 0.5)).abs() < 1e-3);
+
+        let second_compact = (regions[0].len() + rate as usize / 10) as f64 / rate as f64;
+        let second = regions[1].start as f64 / rate as f64;
+        assert!((map.source_seconds(second_compact + 0.25) - (second + 0.25)).abs() < 1e-3);
+    }
+
+    fn wav_bytes(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+        let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        pcm_wav_bytes(1, 16, 1, sample_rate, &data)
+    }
+
+    #[test]
+    fn decodes_wav_from_memory_with_content_type_hint() {
+        let samples: Vec<i16> = sine(48_000, 48_000, 440.0)
+            .iter()
+            .map(|s| (s * i16::MAX as f32) as i16)
+            .collect();
+        let bytes = wav_bytes(&samples, 48_000);
+        let decoded = load_and_resample_bytes(
+            bytes,
+            None,
+            Some("audio/wav; codecs=1"),
+            TimeRange::default(),
+            ResampleMode::Exact,
+        )
+        .unwrap();
+        assert_eq!(decoded.len(), TARGET_SAMPLE_RATE as usize);
+    }
+
+    #[test]
+    fn decodes_only_the_requested_range() {
+        // A ramp makes the first decoded sample identify its position.
+        let samples: Vec<i16> = (0..16_000 * 4).map(|i| (i / 16) as i16).collect();
+        let bytes = wav_bytes(&samples, 16_000);
+        let range = TimeRange {
+            start: 1.5,
+            end: Some(2.25),
+        };
+        let decoded =
+            load_and_resample_bytes(bytes, Some("clip.wav"), None, range, ResampleMode::Exact)
+                .unwrap();
+        assert_eq!(decoded.len(), 12_000);
+        assert_eq!((decoded[0] * 32768.0).round() as i32, 24_000 / 16);
+    }
+}
@@ -9,7 +9,13 @@ async fn main() {
     println!("Testing ElevenLabs Realtime API...");
     println!("API Key: {}...", &api_key[..20]);
 
-    match openstt_app_lib::elevenlabs_realtime::RealtimeSession::start(&api_key, 16000, None).await
+    match openstt_app_lib::elevenlabs_realtime::RealtimeSession::start(
+        &api_key,
+        16000,
+        None,
+        Vec::new(),
+    )
+    .await
     {
         Ok(session) => {
             println!("✓ WebSocket connected");
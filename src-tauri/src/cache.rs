@@ -0,0 +1,209 @@
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+const MAX_BYTES: u64 = 100 * 1024 * 1024;
+const MAX_AGE: Duration = Duration::from_secs(30 * 86_400);
+
+/// On-disk cache of transcription results, one JSON file per key. Entries
+/// older than `max_age` are dropped, and the least recently used ones go
+/// first once the directory grows past `max_bytes`. Every method does
+/// blocking file I/O; call them from `spawn_blocking`.
+#[derive(Clone)]
+pub struct TranscriptCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_age: Duration,
+}
+
+impl TranscriptCache {
+    /// Keeps at most 100 MB, for up to 30 days.
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            max_bytes: MAX_BYTES,
+            max_age: MAX_AGE,
+        }
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let path = self.entry_path(key);
+        let mut file = std::fs::File::open(&path).ok()?;
+        let modified = file.metadata().and_then(|meta| meta.modified()).ok()?;
+        if self.is_expired(modified) {
+            drop(file);
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).ok()?;
+        // Bump the mtime so eviction treats this entry as recently used. A
+        // read-only cache still serves hits, it just evicts by write time.
+        let _ = file.set_modified(SystemTime::now());
+        drop(file);
+
+        match serde_json::from_slice(&bytes) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                let _ = std::fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    pub fn put<T: Serialize>(&self, key: &str, value: &T) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|err| format!("Failed to create cache dir: {err}"))?;
+        let bytes = serde_json::to_vec(value)
+            .map_err(|err| format!("Failed to serialize cache entry: {err}"))?;
+        // Write then rename so a concurrent reader never sees a partial entry.
+        let path = self.entry_path(key);
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, bytes)
+            .map_err(|err| format!("Failed to write cache entry: {err}"))?;
+        std::fs::rename(&temp_path, &path)
+            .map_err(|err| format!("Failed to write cache entry: {err}"))?;
+        self.evict();
+        Ok(())
+    }
+
+    fn evict(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut files: Vec<(PathBuf, SystemTime, u64)> = entries
+            .flatten()
+            .filter(|entry| entry.path().extension().and_then(|ext| ext.to_str()) == Some("json"))
+            .filter_map(|entry| {
+                let meta = entry.metadata().ok()?;
+                Some((entry.path(), meta.modified().ok()?, meta.len()))
+            })
+            .collect();
+
+        files.retain(|(path, modified, _)| {
+            if self.is_expired(*modified) {
+                let _ = std::fs::remove_file(path);
+                false
+            } else {
+                true
+            }
+        });
+
+        let mut total: u64 = files.iter().map(|(_, _, len)| len).sum();
+        files.sort_by_key(|(_, modified, _)| *modified);
+        for (path, _, len) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                total = total.saturating_sub(len);
+            }
+        }
+    }
+
+    fn is_expired(&self, modified: SystemTime) -> bool {
+        SystemTime::now()
+            .duration_since(modified)
+            .map(|age| age > self.max_age)
+            .unwrap_or(false)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
+
+/// Hex SHA-256 over length-prefixed parts, so `["ab", "c"]` and `["a", "bc"]`
+/// hash differently.
+pub fn key(parts: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(name: &str, max_bytes: u64) -> TranscriptCache {
+        let dir = std::env::temp_dir().join(format!("openstt-cache-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        TranscriptCache {
+            dir,
+            max_bytes,
+            max_age: Duration::from_secs(3_600),
+        }
+    }
+
+    /// Backdates an entry so tests control the eviction order.
+    fn set_age(cache: &TranscriptCache, key: &str, age: Duration) {
+        std::fs::File::open(cache.entry_path(key))
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+    }
+
+    #[test]
+    fn key_is_stable_and_separates_parts() {
+        assert_eq!(
+            key(&[b"audio", b"base"]),
+            "cb9a510b59b7d95459a466434fe32136ffb7b550d0dadcee0e8688c82de1e283"
+        );
+        assert_ne!(key(&[b"ab", b"c"]), key(&[b"a", b"bc"]));
+        assert_ne!(key(&[b"ab"]), key(&[b"ab", b""]));
+    }
+
+    #[test]
+    fn get_returns_what_put_stored() {
+        let cache = cache("roundtrip", 1024 * 1024);
+        assert_eq!(cache.get::<String>("a"), None);
+        cache.put("a", &"hello".to_string()).unwrap();
+        assert_eq!(cache.get::<String>("a").as_deref(), Some("hello"));
+        let _ = std::fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entries_past_the_size_limit() {
+        // Each entry serializes to 12 bytes; three fit.
+        let cache = cache("lru", 36);
+        for (index, name) in ["a", "b", "c"].iter().enumerate() {
+            cache.put(name, &format!("entry {name}...")).unwrap();
+            set_age(&cache, name, Duration::from_secs(60 - index as u64 * 10));
+        }
+        // Reading `a` makes `b` the least recently used.
+        assert!(cache.get::<String>("a").is_some());
+        cache.put("d", &"entry d...".to_string()).unwrap();
+        let present: Vec<bool> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|name| cache.entry_path(name).exists())
+            .collect();
+        assert_eq!(present, [true, false, true, true]);
+        let _ = std::fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn drops_expired_entries() {
+        let cache = cache("age", 1024 * 1024);
+        cache.put("old", &1).unwrap();
+        cache.put("older", &2).unwrap();
+        set_age(&cache, "old", Duration::from_secs(7_200));
+        set_age(&cache, "older", Duration::from_secs(7_200));
+
+        assert_eq!(cache.get::<i32>("old"), None);
+        assert!(!cache.entry_path("old").exists());
+        cache.put("new", &3).unwrap();
+        assert!(!cache.entry_path("older").exists());
+        assert_eq!(cache.get::<i32>("new"), Some(3));
+        let _ = std::fs::remove_dir_all(&cache.dir);
+    }
+}
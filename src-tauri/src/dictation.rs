@@ -1,13 +1,23 @@
 use crate::elevenlabs_realtime::{RealtimeSession as ElevenLabsSession, TypeAction as ElevenLabsTypeAction};
+use crate::local_realtime::RealtimeSession as LocalSession;
 use crate::soniox_realtime::{RealtimeSession as SonioxSession, TypeAction as SonioxTypeAction};
-use crate::recording::{self, RecordingSession, StreamingRecordingSession};
-use crate::AppState;
-use cpal::traits::{DeviceTrait, HostTrait};
-use serde::Serialize;
+use crate::recording::{
+    self, FinishedRecording, LevelSender, PreRoll, RecordingSession, StreamErrorReceiver,
+    StreamingRecordingSession, UploadFormat,
+};
+use crate::punctuation::{self, CommandTable};
+use crate::typed_history::{self, EditCommand, TypedHistory};
+use crate::vocabulary::{Replacer, Vocabulary};
+use crate::{audio, elevenlabs_realtime, history, local_realtime, soniox_realtime, AppState};
+use cpal::traits::DeviceTrait;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex as StdMutex};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tauri::Emitter;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, Manager};
 use tokio::sync::{mpsc, Mutex};
 
 // Unified TypeAction enum for internal use
@@ -23,10 +33,11 @@ pub enum SonioxWarmPolicy {
     Forever,
 }
 
-// Unified RealtimeSession wrapper to handle both providers
+// Unified RealtimeSession wrapper to handle all providers
 enum RealtimeSessionWrapper {
     ElevenLabs(ElevenLabsSession),
     Soniox(SonioxSession),
+    Local(LocalSession),
 }
 
 impl RealtimeSessionWrapper {
@@ -34,6 +45,7 @@ impl RealtimeSessionWrapper {
         match self {
             RealtimeSessionWrapper::ElevenLabs(s) => s.send_audio(samples).await,
             RealtimeSessionWrapper::Soniox(s) => s.send_audio(samples).await,
+            RealtimeSessionWrapper::Local(s) => s.send_audio(samples).await,
         }
     }
 
@@ -41,6 +53,7 @@ impl RealtimeSessionWrapper {
         match self {
             RealtimeSessionWrapper::ElevenLabs(s) => s.stop().await,
             RealtimeSessionWrapper::Soniox(s) => s.stop().await,
+            RealtimeSessionWrapper::Local(s) => s.stop().await,
         }
     }
 
@@ -55,6 +68,7 @@ impl RealtimeSessionWrapper {
                 let rx = s.clone_transcript_rx();
                 Arc::new(Mutex::new(async_bridge_soniox(rx)))
             }
+            RealtimeSessionWrapper::Local(s) => s.clone_transcript_rx(),
         }
     }
 }
@@ -116,6 +130,14 @@ pub struct DictationStateEvent {
     pub queue_len: u32,
 }
 
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingErrorEvent {
+    pub message: String,
+    /// Device the recording continued on after the original one was lost.
+    pub fallback_device: Option<String>,
+}
+
 #[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PlaygroundTranscriptionResult {
@@ -123,9 +145,112 @@ pub struct PlaygroundTranscriptionResult {
     pub error: Option<String>,
 }
 
+/// How the dictation shortcut starts and stops dictation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShortcutMode {
+    /// Dictate while the shortcut is held.
+    #[default]
+    Hold,
+    /// Press to start, press again to stop.
+    Toggle,
+    /// Hold to dictate, or double-tap to keep dictating until the next press.
+    DoubleTap,
+}
+
+/// What a shortcut event asks the caller to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShortcutAction {
+    None,
+    Start,
+    Stop,
+    /// The press was a tap that may become a double-tap. Call
+    /// `DictationManager::shortcut_tap_expired` after `DOUBLE_TAP_WINDOW`.
+    AwaitSecondTap,
+}
+
+/// Presses released sooner than this are taps rather than holds.
+const TAP_MAX: Duration = Duration::from_millis(250);
+/// A second tap within this time of the first locks dictation on.
+pub const DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(300);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ShortcutPhase {
+    Idle,
+    /// Dictating while the key is held.
+    Held {
+        since: Instant,
+    },
+    /// Double-tap mode: dictating after a tap, waiting for a second one.
+    Tapped {
+        at: Instant,
+    },
+    /// Dictating, key still down from the press that locked it on.
+    Locking,
+    /// Dictating with the key up, until the next press.
+    Locked,
+    /// Dictation was stopped by a press whose release is still to come.
+    Stopping,
+}
+
+/// Shortcut state machine, fed with key events and their times.
+struct ShortcutTracker {
+    phase: ShortcutPhase,
+}
+
+impl ShortcutTracker {
+    fn press(&mut self, mode: ShortcutMode, now: Instant) -> ShortcutAction {
+        let (phase, action) = match self.phase {
+            ShortcutPhase::Idle if mode == ShortcutMode::Toggle => {
+                (ShortcutPhase::Locking, ShortcutAction::Start)
+            }
+            ShortcutPhase::Idle => (ShortcutPhase::Held { since: now }, ShortcutAction::Start),
+            ShortcutPhase::Tapped { at } if now.duration_since(at) <= DOUBLE_TAP_WINDOW => {
+                (ShortcutPhase::Locking, ShortcutAction::None)
+            }
+            ShortcutPhase::Tapped { .. } | ShortcutPhase::Locked => {
+                (ShortcutPhase::Stopping, ShortcutAction::Stop)
+            }
+            phase => (phase, ShortcutAction::None),
+        };
+        self.phase = phase;
+        action
+    }
+
+    fn release(&mut self, mode: ShortcutMode, now: Instant) -> ShortcutAction {
+        let (phase, action) = match self.phase {
+            ShortcutPhase::Held { since }
+                if mode == ShortcutMode::DoubleTap && now.duration_since(since) < TAP_MAX =>
+            {
+                (
+                    ShortcutPhase::Tapped { at: now },
+                    ShortcutAction::AwaitSecondTap,
+                )
+            }
+            ShortcutPhase::Held { .. } => (ShortcutPhase::Idle, ShortcutAction::Stop),
+            ShortcutPhase::Locking => (ShortcutPhase::Locked, ShortcutAction::None),
+            ShortcutPhase::Stopping => (ShortcutPhase::Idle, ShortcutAction::None),
+            phase => (phase, ShortcutAction::None),
+        };
+        self.phase = phase;
+        action
+    }
+
+    /// A single tap ends once no second tap followed it in time.
+    fn tap_expired(&mut self, now: Instant) -> ShortcutAction {
+        match self.phase {
+            ShortcutPhase::Tapped { at } if now.duration_since(at) >= DOUBLE_TAP_WINDOW => {
+                self.phase = ShortcutPhase::Idle;
+                ShortcutAction::Stop
+            }
+            _ => ShortcutAction::None,
+        }
+    }
+}
+
 pub struct DictationManager {
-    recording: StdMutex<Option<RecordingSession>>,
-    streaming_recording: Mutex<Option<StreamingRecordingSession>>,
+    recording: Arc<StdMutex<Option<RecordingSession>>>,
+    streaming_recording: Arc<Mutex<Option<StreamingRecordingSession>>>,
     realtime_session: Arc<Mutex<Option<RealtimeSessionWrapper>>>,
     realtime_active: Arc<Mutex<bool>>,
     realtime_pipeline_running: Arc<Mutex<bool>>,
@@ -133,9 +258,46 @@ pub struct DictationManager {
     soniox_warm_until_ms: Arc<Mutex<Option<u64>>>,
     soniox_warm_forever: Arc<Mutex<bool>>,
     soniox_warm_close_handle: Mutex<Option<tokio::task::JoinHandle<()>>>,
-    playground_recording: StdMutex<Option<RecordingSession>>,
-    queue: Mutex<VecDeque<Vec<u8>>>,
+    playground_recording: Arc<StdMutex<Option<RecordingSession>>>,
+    /// Finished recordings. They are encoded when transcribed, once the
+    /// model (and so the upload format) is known.
+    queue: Mutex<VecDeque<FinishedRecording>>,
     processing: Mutex<bool>,
+    /// Name of the microphone chosen in the settings; `None` uses the default.
+    input_device: StdMutex<Option<String>>,
+    /// Always-on capture of the last moments before a recording starts.
+    pre_roll: StdMutex<Option<PreRoll>>,
+    /// Realtime audio lost in the current or last streaming session.
+    stream_dropped_samples: Arc<AtomicU64>,
+    /// Length of the audio chunks sent to realtime providers.
+    stream_chunk_ms: AtomicU32,
+    shortcut: StdMutex<ShortcutTracker>,
+    /// Terms sent to the speech models when a realtime session starts.
+    vocabulary_terms: StdMutex<Vec<String>>,
+    /// How dictated text is rewritten; swapped when the settings change.
+    text_rules: Arc<StdMutex<TextRules>>,
+    /// What dictation inserted into other apps, for undo.
+    typed_history: Arc<StdMutex<TypedHistory>>,
+    /// Text typed by the current realtime session; replaced when one starts.
+    realtime_text: Arc<StdMutex<SharedSessionText>>,
+}
+
+/// Rewrites applied to final dictation text before it is typed.
+#[derive(Clone, Default)]
+struct TextRules {
+    spoken_commands: Option<&'static CommandTable>,
+    replacer: Arc<Replacer>,
+}
+
+impl TextRules {
+    /// Spoken commands run first, so replacement rules see the typed text.
+    fn apply(&self, text: &str) -> String {
+        let text = match self.spoken_commands {
+            Some(table) => punctuation::apply(text, table),
+            None => text.to_string(),
+        };
+        self.replacer.apply(&text)
+    }
 }
 
 fn coalesce_typing_action(initial: TypeAction, rx: &mut mpsc::Receiver<TypeAction>) -> TypeAction {
@@ -164,22 +326,362 @@ fn now_millis() -> u64 {
         .as_millis() as u64
 }
 
-fn default_input_sample_rate() -> Result<u32, String> {
-    let host = cpal::default_host();
-    let device = host
-        .default_input_device()
-        .ok_or_else(|| "No input device available".to_string())?;
+fn watch_recording_errors(
+    errors: StreamErrorReceiver,
+    app_handle: tauri::AppHandle,
+    session: Arc<StdMutex<Option<RecordingSession>>>,
+) {
+    watch_stream_errors(errors, app_handle, move || {
+        let session = session.clone();
+        // Opening a stream blocks; keep it and the session lock off the
+        // async workers.
+        async move {
+            tokio::task::spawn_blocking(move || {
+                session
+                    .lock()
+                    .unwrap()
+                    .as_mut()
+                    .map(RecordingSession::reopen)
+            })
+            .await
+            .unwrap_or_else(|err| Some(Err(format!("Reopen task failed: {err}"))))
+        }
+    });
+}
+
+/// Reports stream errors of a running recording as `recording-error` events.
+/// When the device is gone, `reopen` moves the recording to another device;
+/// it returns `None` once the session has already been stopped.
+fn watch_stream_errors<F, Fut>(
+    mut errors: StreamErrorReceiver,
+    app_handle: tauri::AppHandle,
+    reopen: F,
+) where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Option<Result<String, String>>> + Send,
+{
+    tauri::async_runtime::spawn(async move {
+        while let Some(err) = errors.recv().await {
+            let mut event = RecordingErrorEvent {
+                message: err.to_string(),
+                fallback_device: None,
+            };
+            if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+                match reopen().await {
+                    None => break,
+                    Some(Ok(device)) => event.fallback_device = Some(device),
+                    Some(Err(reopen_err)) => {
+                        event.message = format!("{}: {reopen_err}", event.message);
+                    }
+                }
+                // A disconnect is often reported more than once.
+                while errors.try_recv().is_ok() {}
+            }
+
+            let log = match &event.fallback_device {
+                Some(device) => format!(
+                    "Recording device lost ({}), continuing on {device}",
+                    event.message
+                ),
+                None => format!("Recording stream error: {}", event.message),
+            };
+            eprintln!("[dictation] {log}");
+            app_handle.state::<AppState>().logs.push("error", log).await;
+            let _ = app_handle.emit("recording-error", event);
+        }
+    });
+}
+
+/// RMS below this (about -60 dBFS) counts as silence.
+const SILENCE_FLOOR: f32 = 0.001;
+/// How long the input must stay silent before the user is warned.
+const SILENCE_WARNING: Duration = Duration::from_secs(4);
+
+/// Limits that end a dictation without the hotkey.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AutoStop {
+    /// Stop after this much trailing silence.
+    pub silence: Option<Duration>,
+    /// Stop once the recording is this long.
+    pub max_duration: Option<Duration>,
+}
+
+impl AutoStop {
+    /// Why the recording should stop now, if it should.
+    fn reason(&self, elapsed: Duration, silent_secs: f32) -> Option<String> {
+        if let Some(max) = self.max_duration.filter(|max| elapsed >= *max) {
+            return Some(format!("reached the {} s limit", max.as_secs()));
+        }
+        self.silence
+            .filter(|silence| silent_secs >= silence.as_secs_f32())
+            .map(|silence| format!("{} s of silence", silence.as_secs()))
+    }
+}
+
+/// Runs the same stop path as the hotkey on behalf of `AutoStop`.
+fn auto_stop_dictation(app_handle: tauri::AppHandle, reason: String) {
+    tauri::async_runtime::spawn(async move {
+        let state = app_handle.state::<AppState>();
+        eprintln!("[dictation] auto-stop: {reason}");
+        state
+            .logs
+            .push("info", format!("Dictation stopped automatically: {reason}"))
+            .await;
+        let _guard = state.dictation_op_lock.lock().await;
+        state.dictation.sync_shortcut(false);
+        if let Err(err) = crate::stop_dictation_inner(&state, &app_handle).await {
+            state
+                .logs
+                .push("error", format!("Dictation stop failed: {err}"))
+                .await;
+        }
+    });
+}
+
+/// Forwards input levels of a recording as `input-level` events until the
+/// recording ends, and emits `input-silence` once when the input stays below
+/// `SILENCE_FLOOR` for `SILENCE_WARNING`, which usually means a muted mic.
+/// With `auto_stop`, the dictation is stopped once a limit is reached.
+fn watch_input_levels(app_handle: tauri::AppHandle, auto_stop: Option<AutoStop>) -> LevelSender {
+    let (levels, mut level_rx) = mpsc::unbounded_channel::<recording::InputLevel>();
+    tauri::async_runtime::spawn(async move {
+        let started = Instant::now();
+        let window_secs = recording::LEVEL_WINDOW_MS as f32 / 1000.0;
+        let mut vad = audio::LiveVad::default();
+        let mut auto_stop = auto_stop;
+        let mut silent_since: Option<Instant> = None;
+        let mut warned = false;
+        while let Some(level) = level_rx.recv().await {
+            let _ = app_handle.emit("input-level", level);
+            let silent_secs = vad.push(level.rms, window_secs);
+            let stop_reason =
+                auto_stop.and_then(|limits| limits.reason(started.elapsed(), silent_secs));
+            if let Some(reason) = stop_reason {
+                // Stop once; levels keep flowing until the session is gone.
+                auto_stop = None;
+                auto_stop_dictation(app_handle.clone(), reason);
+            }
+            if level.rms >= SILENCE_FLOOR {
+                silent_since = None;
+                warned = false;
+                continue;
+            }
+            let since = *silent_since.get_or_insert_with(Instant::now);
+            if !warned && since.elapsed() >= SILENCE_WARNING {
+                warned = true;
+                let message = format!(
+                    "No input detected for {} seconds, is the microphone muted?",
+                    SILENCE_WARNING.as_secs()
+                );
+                eprintln!("[dictation] {message}");
+                app_handle
+                    .state::<AppState>()
+                    .logs
+                    .push("info", message)
+                    .await;
+                let _ = app_handle.emit("input-silence", ());
+            }
+        }
+    });
+    levels
+}
+
+/// Tells the user where the audio of a failed transcription is kept. It is
+/// offered again on the next launch.
+fn spool_note(recorded: &FinishedRecording) -> String {
+    match recorded.spool_files.first() {
+        Some(path) => format!(" (audio kept at {})", path.display()),
+        None => String::new(),
+    }
+}
+
+/// Encodes a finished recording in the active model's upload format and
+/// returns it with a file name carrying the matching extension.
+async fn encode_recording(
+    app_state: &AppState,
+    label: &str,
+    recording: &mut FinishedRecording,
+) -> (String, Vec<u8>) {
+    let samples = std::mem::take(&mut recording.samples);
+    let sample_rate = recording.sample_rate;
+    let model_id = crate::normalize_model_id(&app_state.active_model_id.lock().await);
+    let flac_uploads = app_state.ui_settings.lock().await.flac_uploads;
+    let format = UploadFormat::for_model(&model_id, flac_uploads);
+    let wav_bytes = 44 + samples.len() * 2;
+    let audio = match tokio::task::spawn_blocking(move || {
+        recording::encode_upload(&samples, sample_rate, format)
+    })
+    .await
+    {
+        Ok(audio) => audio,
+        Err(err) => {
+            eprintln!("[dictation] encode task failed: {err}");
+            Vec::new()
+        }
+    };
+    if format != UploadFormat::Wav {
+        app_state
+            .logs
+            .push(
+                "info",
+                format!(
+                    "Encoded {label} as {}: {} bytes (WAV {wav_bytes} bytes)",
+                    format.extension(),
+                    audio.len()
+                ),
+            )
+            .await;
+    }
+    (format!("{label}.{}", format.extension()), audio)
+}
+
+/// A recording being transcribed, added to the history once it is done.
+struct PendingHistory {
+    source: history::Source,
+    model: String,
+    duration_ms: u64,
+    started: Instant,
+    audio: Option<(String, Vec<u8>)>,
+}
+
+impl PendingHistory {
+    async fn start(
+        app_state: &AppState,
+        source: history::Source,
+        duration_ms: u64,
+        file_name: &str,
+        audio: &[u8],
+    ) -> Self {
+        let keep_audio = crate::history_keeps_audio(app_state, source).await;
+        Self {
+            source,
+            model: crate::normalize_model_id(&app_state.active_model_id.lock().await),
+            duration_ms,
+            started: Instant::now(),
+            audio: keep_audio.then(|| (crate::upload_extension(Some(file_name)), audio.to_vec())),
+        }
+    }
+
+    fn finish(self, text: String) -> history::Record {
+        history::Record {
+            source: self.source,
+            model: self.model,
+            duration_ms: Some(self.duration_ms),
+            latency_ms: self.started.elapsed().as_millis() as u64,
+            text,
+            audio: self.audio,
+        }
+    }
+}
+
+/// Final words can still arrive this long after a realtime session stops.
+const REALTIME_SETTLE: Duration = Duration::from_secs(2);
+
+/// Text one realtime session typed, added to the history as one entry.
+struct SessionText {
+    started: Instant,
+    text: String,
+    last_commit: Option<Instant>,
+}
+
+type SharedSessionText = Arc<StdMutex<SessionText>>;
+
+impl SessionText {
+    fn start() -> SharedSessionText {
+        Arc::new(StdMutex::new(Self {
+            started: Instant::now(),
+            text: String::new(),
+            last_commit: None,
+        }))
+    }
+
+    fn push(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.last_commit = Some(Instant::now());
+    }
+
+    /// Drops the last `count` characters, as a voice edit did on screen.
+    fn remove(&mut self, count: usize) {
+        let keep = self.text.chars().count().saturating_sub(count);
+        self.text = self.text.chars().take(keep).collect();
+    }
+
+    fn finish(&self, model: String, stopped: Instant) -> history::Record {
+        history::Record {
+            source: history::Source::Dictation,
+            model,
+            duration_ms: Some(stopped.saturating_duration_since(self.started).as_millis() as u64),
+            latency_ms: self.last_commit.map_or(0, |last| {
+                last.saturating_duration_since(stopped).as_millis() as u64
+            }),
+            text: self.text.clone(),
+            audio: None,
+        }
+    }
+}
+
+fn recording_duration_ms(recording: &FinishedRecording) -> u64 {
+    recording.samples.len() as u64 * 1000 / recording.sample_rate.max(1) as u64
+}
+
+/// Speech loses nothing at 16 kHz, so higher device rates are not streamed.
+const PREFERRED_STREAM_RATE: u32 = 16_000;
+
+/// Picks the rate to stream at: the accepted rate closest to the device
+/// rate, capped at `PREFERRED_STREAM_RATE`.
+fn stream_sample_rate(device_rate: u32, accepted: &[u32]) -> u32 {
+    let wanted = device_rate.min(PREFERRED_STREAM_RATE);
+    accepted
+        .iter()
+        .copied()
+        .min_by_key(|rate| rate.abs_diff(wanted))
+        .unwrap_or(device_rate)
+}
+
+fn input_sample_rate(input_device: Option<&str>) -> Result<u32, String> {
+    let device = recording::input_device(input_device)?;
     let config = device
         .default_input_config()
         .map_err(|err| format!("Failed to get input config: {err}"))?;
     Ok(config.sample_rate().0)
 }
 
+/// Options for transcribing microphone recordings with `model_id`, or the
+/// active model, taken from the settings. VAD and preprocessing only apply
+/// to whisper models, so they are left out for the others.
+pub(crate) async fn recording_transcribe_options(
+    app_state: &AppState,
+    model_id: Option<&str>,
+) -> crate::TranscribeOptions {
+    let model_id = match model_id {
+        Some(model_id) => model_id.to_string(),
+        None => crate::normalize_model_id(&app_state.active_model_id.lock().await),
+    };
+    let whisper = crate::runs_whisper(&model_id);
+    let settings = app_state.ui_settings.lock().await;
+    crate::TranscribeOptions {
+        vad: if whisper && settings.dictation_vad {
+            crate::VadMode::Energy
+        } else {
+            crate::VadMode::Off
+        },
+        preprocess: if whisper {
+            settings.audio_preprocess
+        } else {
+            Default::default()
+        },
+        // Every recording is unique, so caching would only fill the disk.
+        cache: Some(false),
+        vocabulary: settings.vocabulary.terms(),
+        ..Default::default()
+    }
+}
+
 impl DictationManager {
     pub fn new() -> Self {
         Self {
-            recording: StdMutex::new(None),
-            streaming_recording: Mutex::new(None),
+            recording: Arc::new(StdMutex::new(None)),
+            streaming_recording: Arc::new(Mutex::new(None)),
             realtime_session: Arc::new(Mutex::new(None)),
             realtime_active: Arc::new(Mutex::new(false)),
             realtime_pipeline_running: Arc::new(Mutex::new(false)),
@@ -187,12 +689,92 @@ impl DictationManager {
             soniox_warm_until_ms: Arc::new(Mutex::new(None)),
             soniox_warm_forever: Arc::new(Mutex::new(false)),
             soniox_warm_close_handle: Mutex::new(None),
-            playground_recording: StdMutex::new(None),
+            playground_recording: Arc::new(StdMutex::new(None)),
             queue: Mutex::new(VecDeque::new()),
             processing: Mutex::new(false),
+            input_device: StdMutex::new(None),
+            pre_roll: StdMutex::new(None),
+            stream_dropped_samples: Arc::new(AtomicU64::new(0)),
+            stream_chunk_ms: AtomicU32::new(recording::DEFAULT_STREAM_CHUNK_MS),
+            shortcut: StdMutex::new(ShortcutTracker {
+                phase: ShortcutPhase::Idle,
+            }),
+            vocabulary_terms: StdMutex::new(Vec::new()),
+            text_rules: Arc::new(StdMutex::new(TextRules::default())),
+            typed_history: Arc::new(StdMutex::new(TypedHistory::default())),
+            realtime_text: Arc::new(StdMutex::new(SessionText::start())),
         }
     }
 
+    /// Applies the vocabulary from the settings. Fails, keeping the previous
+    /// rules, when a replacement pattern is invalid.
+    pub fn configure_vocabulary(&self, vocabulary: &Vocabulary) -> Result<(), String> {
+        let replacer = Replacer::new(&vocabulary.replacements)?;
+        self.text_rules.lock().unwrap().replacer = Arc::new(replacer);
+        *self.vocabulary_terms.lock().unwrap() = vocabulary.terms();
+        Ok(())
+    }
+
+    fn vocabulary_terms(&self) -> Vec<String> {
+        self.vocabulary_terms.lock().unwrap().clone()
+    }
+
+    pub fn configure_spoken_commands(&self, table: Option<&'static CommandTable>) {
+        self.text_rules.lock().unwrap().spoken_commands = table;
+    }
+
+    /// Runs spoken commands and replacement rules over a final transcript.
+    /// Line breaks the speaker asked for at either end are kept.
+    pub fn finish_text(&self, text: &str) -> String {
+        let rules = self.text_rules.lock().unwrap().clone();
+        rules.apply(text.trim()).trim_matches(' ').to_string()
+    }
+
+    async fn record_realtime_session(&self, app_handle: &tauri::AppHandle) {
+        let session = self.realtime_text.lock().unwrap().clone();
+        let stopped = Instant::now();
+        let model =
+            crate::normalize_model_id(&app_handle.state::<AppState>().active_model_id.lock().await);
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(REALTIME_SETTLE).await;
+            let app_state = app_handle.state::<AppState>();
+            let record = session.lock().unwrap().finish(model, stopped);
+            crate::record_history(&app_state, record).await;
+        });
+    }
+
+    /// Deletes dictated text from the focused app. Returns how many
+    /// characters were removed.
+    pub async fn edit_typed(&self, command: EditCommand) -> Result<usize, String> {
+        let history = self.typed_history.clone();
+        tokio::task::spawn_blocking(move || edit_typed(&history, command))
+            .await
+            .map_err(|err| format!("Edit task failed: {err}"))?
+    }
+
+    pub fn shortcut_pressed(&self, mode: ShortcutMode) -> ShortcutAction {
+        self.shortcut.lock().unwrap().press(mode, Instant::now())
+    }
+
+    pub fn shortcut_released(&self, mode: ShortcutMode) -> ShortcutAction {
+        self.shortcut.lock().unwrap().release(mode, Instant::now())
+    }
+
+    pub fn shortcut_tap_expired(&self) -> ShortcutAction {
+        self.shortcut.lock().unwrap().tap_expired(Instant::now())
+    }
+
+    /// Tells the shortcut about dictation started or stopped some other way,
+    /// so its next press does the expected thing.
+    pub fn sync_shortcut(&self, listening: bool) {
+        self.shortcut.lock().unwrap().phase = if listening {
+            ShortcutPhase::Locked
+        } else {
+            ShortcutPhase::Idle
+        };
+    }
+
     /// Derive state from actual conditions rather than storing it.
     /// Recording takes priority: if mic is active the user sees "listening"
     /// even while the queue is draining in the background.
@@ -213,10 +795,68 @@ impl DictationManager {
         self.queue.lock().await.len() as u32
     }
 
-    pub fn start_recording(&self) -> Result<(), String> {
-        let session = RecordingSession::start()?;
+    /// Selects the microphone and (re)starts the pre-roll capture on it;
+    /// `pre_roll_ms == 0` turns the pre-roll off.
+    pub fn configure_input(&self, name: Option<String>, pre_roll_ms: u32) -> Result<(), String> {
+        let name = name.filter(|name| !name.is_empty());
+        *self.input_device.lock().unwrap() = name.clone();
+        let mut pre_roll = self.pre_roll.lock().unwrap();
+        *pre_roll = None;
+        if pre_roll_ms > 0 {
+            *pre_roll = Some(PreRoll::start(name.as_deref(), pre_roll_ms)?);
+        }
+        Ok(())
+    }
+
+    /// Sets the chunk length for realtime sessions started from now on.
+    pub fn configure_stream_chunk_ms(&self, chunk_ms: u32) {
+        self.stream_chunk_ms.store(chunk_ms, Ordering::Relaxed);
+    }
+
+    /// The pre-roll for a recording about to start. One whose device was
+    /// lost is rebuilt first, in case the device is back.
+    fn pre_roll_buffer(&self) -> Option<recording::PreRollBuffer> {
+        let mut pre_roll = self.pre_roll.lock().unwrap();
+        let restarted = match pre_roll.as_ref() {
+            Some(lost) if lost.is_lost() => Some(lost.restart()),
+            _ => None,
+        };
+        match restarted {
+            Some(Ok(restarted)) => *pre_roll = Some(restarted),
+            Some(Err(err)) => {
+                eprintln!("[dictation] pre-roll not restarted: {err}");
+                return None;
+            }
+            None => {}
+        }
+        pre_roll.as_ref().map(PreRoll::buffer)
+    }
+
+    fn input_device(&self) -> Option<String> {
+        self.input_device.lock().unwrap().clone()
+    }
+
+    /// With `spool_dir`, the audio is also written to disk while recording
+    /// so it survives a crash.
+    pub fn start_recording(
+        &self,
+        app_handle: &tauri::AppHandle,
+        auto_stop: AutoStop,
+        spool_dir: Option<PathBuf>,
+    ) -> Result<(), String> {
+        self.typed_history
+            .lock()
+            .unwrap()
+            .start_dictation(Instant::now());
+        let (session, errors) = RecordingSession::start(
+            self.input_device().as_deref(),
+            self.pre_roll_buffer(),
+            watch_input_levels(app_handle.clone(), Some(auto_stop)),
+            spool_dir,
+        )?;
         let mut rec = self.recording.lock().unwrap();
         *rec = Some(session);
+        watch_recording_errors(errors, app_handle.clone(), self.recording.clone());
         Ok(())
     }
 
@@ -230,26 +870,33 @@ impl DictationManager {
             return Ok(self.current_state());
         };
 
-        let (samples, sample_rate) = session.stop();
-        if recording::is_too_short(samples.len(), sample_rate) {
+        let recorded = session.stop();
+        if recording::is_too_short(recorded.samples.len(), recorded.sample_rate) {
+            recorded.remove_spool_files();
             return Ok(self.current_state());
         }
 
-        let wav = recording::encode_wav(&samples, sample_rate);
-        self.queue.lock().await.push_back(wav);
+        self.queue.lock().await.push_back(recorded);
 
         Ok(self.current_state())
     }
 
-    /// Start realtime dictation with cloud provider WebSocket
+    /// Start realtime dictation with a cloud provider WebSocket, or with the
+    /// active whisper model for the "local" provider.
     pub async fn start_realtime(
         &self,
         provider: &str,
         api_key: &str,
         language: Option<String>,
-        _app_handle: tauri::AppHandle,
+        app_handle: tauri::AppHandle,
+        auto_stop: AutoStop,
     ) -> Result<(), String> {
         eprintln!("[dictation] start_realtime called, provider: {}", provider);
+        self.typed_history
+            .lock()
+            .unwrap()
+            .start_dictation(Instant::now());
+        *self.realtime_text.lock().unwrap() = SessionText::start();
 
         // Stop any previous forwarder (should already be finished)
         if let Some(handle) = self.audio_forward_handle.lock().await.take() {
@@ -267,8 +914,32 @@ impl DictationManager {
         let (chunk_tx, mut chunk_rx) = mpsc::channel::<Vec<i16>>(100);
 
         // Start streaming recording immediately (buffer chunks while connecting)
-        let streaming = StreamingRecordingSession::start(chunk_tx)?;
-        let sample_rate = streaming.sample_rate;
+        self.stream_dropped_samples.store(0, Ordering::Relaxed);
+        let (streaming, stream_errors) = StreamingRecordingSession::start(
+            self.input_device().as_deref(),
+            chunk_tx,
+            self.stream_chunk_ms.load(Ordering::Relaxed),
+            self.pre_roll_buffer(),
+            self.stream_dropped_samples.clone(),
+            watch_input_levels(app_handle.clone(), Some(auto_stop)),
+        )?;
+        let accepted_rates = match provider {
+            "soniox" => soniox_realtime::SAMPLE_RATES,
+            "local" => local_realtime::SAMPLE_RATES,
+            _ => elevenlabs_realtime::SAMPLE_RATES,
+        };
+        let sample_rate = stream_sample_rate(streaming.sample_rate, accepted_rates);
+        let mut resampler = match audio::StreamResampler::new(streaming.sample_rate, sample_rate) {
+            Ok(resampler) => resampler,
+            Err(err) => {
+                streaming.stop();
+                return Err(err);
+            }
+        };
+        eprintln!(
+            "[dictation] streaming {} Hz input at {} Hz",
+            streaming.sample_rate, sample_rate
+        );
 
         // If the provider changed, stop the previous session.
         let mut stop_previous: Option<RealtimeSessionWrapper> = None;
@@ -278,7 +949,9 @@ impl DictationManager {
                 (Some(RealtimeSessionWrapper::Soniox(session)), "soniox") => {
                     session.is_alive() && session.sample_rate() == sample_rate
                 }
-                (Some(RealtimeSessionWrapper::ElevenLabs(_)), "elevenlabs") => true,
+                (Some(RealtimeSessionWrapper::ElevenLabs(session)), "elevenlabs") => {
+                    session.sample_rate() == sample_rate
+                }
                 (None, _) => true,
                 _ => false,
             };
@@ -298,11 +971,15 @@ impl DictationManager {
             if guard.is_none() {
                 let realtime_result: Result<RealtimeSessionWrapper, String> = match provider {
                     "soniox" => {
-                        SonioxSession::start(api_key, sample_rate, language.clone()).await
+                        SonioxSession::start(api_key, sample_rate, language.clone(), self.vocabulary_terms()).await
                             .map(RealtimeSessionWrapper::Soniox)
                     }
+                    "local" => {
+                        LocalSession::start(&app_handle.state::<AppState>()).await
+                            .map(RealtimeSessionWrapper::Local)
+                    }
                     _ => {
-                        ElevenLabsSession::start(api_key, sample_rate, language.clone()).await
+                        ElevenLabsSession::start(api_key, sample_rate, language.clone(), self.vocabulary_terms()).await
                             .map(RealtimeSessionWrapper::ElevenLabs)
                     }
                 };
@@ -323,15 +1000,48 @@ impl DictationManager {
         // Store streaming recording and mark active
         *self.streaming_recording.lock().await = Some(streaming);
         *self.realtime_active.lock().await = true;
+        let streaming_recording = self.streaming_recording.clone();
+        watch_stream_errors(stream_errors, app_handle, move || {
+            let streaming_recording = streaming_recording.clone();
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    streaming_recording
+                        .blocking_lock()
+                        .as_mut()
+                        .map(StreamingRecordingSession::reopen)
+                })
+                .await
+                .unwrap_or_else(|err| Some(Err(format!("Reopen task failed: {err}"))))
+            }
+        });
 
         // Spawn task to forward audio chunks to the active realtime session
         let realtime_session = self.realtime_session.clone();
+        let dropped_samples = self.stream_dropped_samples.clone();
         let forward_handle = tokio::spawn(async move {
-            while let Some(samples) = chunk_rx.recv().await {
+            let mut input_done = false;
+            while !input_done {
+                let samples = match chunk_rx.recv().await {
+                    Some(samples) => resampler.process(&samples),
+                    None => {
+                        input_done = true;
+                        resampler.flush()
+                    }
+                };
+                let samples = match samples {
+                    Ok(samples) if samples.is_empty() => continue,
+                    Ok(samples) => samples,
+                    Err(e) => {
+                        eprintln!("[dictation] resample error: {}", e);
+                        continue;
+                    }
+                };
                 let session = realtime_session.lock().await;
                 if let Some(ref s) = *session {
+                    let len = samples.len() as u64;
                     if let Err(e) = s.send_audio(samples).await {
                         eprintln!("[dictation] send_audio error: {}", e);
+                        dropped_samples.fetch_add(len, Ordering::Relaxed);
                     }
                 } else {
                     eprintln!("[dictation] session gone, stopping audio forward");
@@ -357,6 +1067,9 @@ impl DictationManager {
             };
 
             let pipeline_flag = self.realtime_pipeline_running.clone();
+            let text_rules = self.text_rules.clone();
+            let typed_history = self.typed_history.clone();
+            let realtime_text = self.realtime_text.clone();
             let (typing_tx, mut typing_rx) = mpsc::channel::<TypeAction>(100);
 
             // Forward transcripts to typing channel
@@ -383,10 +1096,24 @@ impl DictationManager {
                         );
                     }
                     TypeAction::CommitDraft(text) => {
+                        let session = realtime_text.lock().unwrap().clone();
+                        if let Some(command) = typed_history::edit_command(&text) {
+                            match edit_typed(&typed_history, command) {
+                                Ok(count) => {
+                                    session.lock().unwrap().remove(count);
+                                    eprintln!("[dictation] {command:?}: removed {count}");
+                                }
+                                Err(err) => eprintln!("[dictation] {command:?} failed: {err}"),
+                            }
+                            return;
+                        }
+                        let text = text_rules.lock().unwrap().apply(&text);
                         let len = text.chars().count();
                         eprintln!("[dictation] commit_draft: len={}", len);
                         if !text.is_empty() {
                             if type_via_clipboard(&text).is_ok() {
+                                typed_history.lock().unwrap().append(&text, Instant::now());
+                                session.lock().unwrap().push(&text);
                                 *chars += len;
                             }
                         }
@@ -406,11 +1133,17 @@ impl DictationManager {
         Ok(())
     }
 
-    /// Stop realtime dictation.
-    pub async fn stop_realtime(&self, soniox_warm: SonioxWarmPolicy) -> Result<DictationState, String> {
+    /// Stop realtime dictation. What the session typed is added to the
+    /// history once its final words had time to arrive.
+    pub async fn stop_realtime(
+        &self,
+        soniox_warm: SonioxWarmPolicy,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<DictationState, String> {
         eprintln!("[dictation] stop_realtime called");
         *self.realtime_active.lock().await = false;
         eprintln!("[dictation] realtime_active set to false");
+        self.record_realtime_session(app_handle).await;
 
         // Stop the streaming recording
         if let Some(streaming) = self.streaming_recording.lock().await.take() {
@@ -512,7 +1245,7 @@ impl DictationManager {
                     }
                 }
             }
-            Some(RealtimeSessionWrapper::ElevenLabs(_)) => {
+            Some(RealtimeSessionWrapper::ElevenLabs(_) | RealtimeSessionWrapper::Local(_)) => {
                 if let Some(session) = guard.take() {
                     let _ = session.stop().await;
                     *self.realtime_pipeline_running.lock().await = false;
@@ -579,8 +1312,12 @@ impl DictationManager {
         *self.realtime_pipeline_running.lock().await = false;
 
         // Establish a new Soniox WebSocket session.
-        let sample_rate = default_input_sample_rate()?;
-        let session = SonioxSession::start(api_key, sample_rate, language).await?;
+        let sample_rate = stream_sample_rate(
+            input_sample_rate(self.input_device().as_deref())?,
+            soniox_realtime::SAMPLE_RATES,
+        );
+        let session =
+            SonioxSession::start(api_key, sample_rate, language, self.vocabulary_terms()).await?;
         {
             let mut guard = self.realtime_session.lock().await;
             *guard = Some(RealtimeSessionWrapper::Soniox(session));
@@ -628,6 +1365,10 @@ impl DictationManager {
         *self.realtime_pipeline_running.lock().await = false;
     }
 
+    pub async fn realtime_active(&self) -> bool {
+        *self.realtime_active.lock().await
+    }
+
     pub async fn soniox_realtime_connected(&self) -> bool {
         let guard = self.realtime_session.lock().await;
         match guard.as_ref() {
@@ -636,6 +1377,10 @@ impl DictationManager {
         }
     }
 
+    pub fn stream_dropped_samples(&self) -> u64 {
+        self.stream_dropped_samples.load(Ordering::Relaxed)
+    }
+
     pub async fn soniox_warm_until_ms(&self) -> Option<u64> {
         *self.soniox_warm_until_ms.lock().await
     }
@@ -655,28 +1400,54 @@ impl DictationManager {
         self.emit_state(app_handle).await;
 
         loop {
-            let wav = self.queue.lock().await.pop_front();
-            let Some(wav) = wav else {
+            let recorded = self.queue.lock().await.pop_front();
+            let Some(mut recorded) = recorded else {
                 break;
             };
 
             self.emit_state(app_handle).await;
 
+            let duration_ms = recording_duration_ms(&recorded);
+            let (file_name, audio) = encode_recording(app_state, "dictation", &mut recorded).await;
+            let pending = PendingHistory::start(
+                app_state,
+                history::Source::Dictation,
+                duration_ms,
+                &file_name,
+                &audio,
+            )
+            .await;
             let result = crate::transcribe_bytes(
                 app_state,
                 None,
-                Some("dictation.wav".to_string()),
-                wav,
-                None,
-                None,
-                None,
-                None,
+                Some(file_name),
+                audio,
+                recording_transcribe_options(app_state, None).await,
             )
             .await;
 
             match result {
-                Ok(text) => {
-                    let trimmed = text.trim().to_string();
+                Ok(transcript) => {
+                    recorded.remove_spool_files();
+                    if let Some(command) = typed_history::edit_command(&transcript.text) {
+                        match self.edit_typed(command).await {
+                            Ok(count) => {
+                                app_state
+                                    .logs
+                                    .push("info", format!("Removed {count} dictated characters"))
+                                    .await
+                            }
+                            Err(err) => {
+                                app_state
+                                    .logs
+                                    .push("error", format!("Voice edit failed: {err}"))
+                                    .await
+                            }
+                        }
+                        continue;
+                    }
+                    let trimmed = self.finish_text(&transcript.text);
+                    crate::record_history(app_state, pending.finish(trimmed.clone())).await;
                     if !trimmed.is_empty() {
                         if let Err(err) = write_clipboard(&trimmed) {
                             app_state
@@ -688,7 +1459,12 @@ impl DictationManager {
                                 app_state.ui_settings.lock().await.dictation_auto_paste;
                             if auto_paste {
                                 tokio::time::sleep(tokio::time::Duration::from_millis(80)).await;
-                                let _ = crate::paste_clipboard_inner().await;
+                                if crate::paste_clipboard_inner().await.is_ok() {
+                                    self.typed_history
+                                        .lock()
+                                        .unwrap()
+                                        .push(&trimmed, Instant::now());
+                                }
                             }
                         }
                     }
@@ -698,7 +1474,11 @@ impl DictationManager {
                         .logs
                         .push(
                             "error",
-                            format!("Dictation transcription failed: {}", err.message),
+                            format!(
+                                "Dictation transcription failed: {}{}",
+                                err.message,
+                                spool_note(&recorded)
+                            ),
                         )
                         .await;
                 }
@@ -712,10 +1492,24 @@ impl DictationManager {
         self.emit_state(app_handle).await;
     }
 
-    pub fn start_playground(&self) -> Result<(), String> {
-        let session = RecordingSession::start()?;
+    pub fn start_playground(
+        &self,
+        app_handle: &tauri::AppHandle,
+        spool_dir: Option<PathBuf>,
+    ) -> Result<(), String> {
+        let (session, errors) = RecordingSession::start(
+            self.input_device().as_deref(),
+            self.pre_roll_buffer(),
+            watch_input_levels(app_handle.clone(), None),
+            spool_dir,
+        )?;
         let mut rec = self.playground_recording.lock().unwrap();
         *rec = Some(session);
+        watch_recording_errors(
+            errors,
+            app_handle.clone(),
+            self.playground_recording.clone(),
+        );
         Ok(())
     }
 
@@ -735,35 +1529,44 @@ impl DictationManager {
             };
         };
 
-        let (samples, sample_rate) = session.stop();
-        if recording::is_too_short(samples.len(), sample_rate) {
+        let mut recorded = session.stop();
+        if recording::is_too_short(recorded.samples.len(), recorded.sample_rate) {
+            recorded.remove_spool_files();
             return PlaygroundTranscriptionResult {
                 text: String::new(),
                 error: None,
             };
         }
 
-        let wav = recording::encode_wav(&samples, sample_rate);
+        let duration_ms = recording_duration_ms(&recorded);
+        let (file_name, audio) = encode_recording(app_state, "playground", &mut recorded).await;
+        let pending = PendingHistory::start(
+            app_state,
+            history::Source::Playground,
+            duration_ms,
+            &file_name,
+            &audio,
+        )
+        .await;
         let result = crate::transcribe_bytes(
             app_state,
             None,
-            Some("playground.wav".to_string()),
-            wav,
-            None,
-            None,
-            None,
-            None,
+            Some(file_name),
+            audio,
+            recording_transcribe_options(app_state, None).await,
         )
         .await;
 
         match result {
-            Ok(text) => PlaygroundTranscriptionResult {
-                text: text.trim().to_string(),
-                error: None,
-            },
+            Ok(transcript) => {
+                recorded.remove_spool_files();
+                let text = self.finish_text(&transcript.text);
+                crate::record_history(app_state, pending.finish(text.clone())).await;
+                PlaygroundTranscriptionResult { text, error: None }
+            }
             Err(err) => PlaygroundTranscriptionResult {
                 text: String::new(),
-                error: Some(err.message),
+                error: Some(format!("{}{}", err.message, spool_note(&recorded))),
             },
         }
     }
@@ -781,7 +1584,16 @@ impl DictationManager {
     }
 }
 
-fn write_clipboard(text: &str) -> Result<(), String> {
+/// Forgets the text `command` takes back and deletes it with backspaces.
+fn edit_typed(history: &StdMutex<TypedHistory>, command: EditCommand) -> Result<usize, String> {
+    let count = history.lock().unwrap().take(command, Instant::now());
+    if count > 0 {
+        type_backspace(count)?;
+    }
+    Ok(count)
+}
+
+pub fn write_clipboard(text: &str) -> Result<(), String> {
     let mut clipboard =
         arboard::Clipboard::new().map_err(|err| format!("Failed to open clipboard: {err}"))?;
     clipboard
@@ -790,7 +1602,6 @@ fn write_clipboard(text: &str) -> Result<(), String> {
 }
 
 #[cfg(target_os = "macos")]
-#[allow(dead_code)]
 fn type_backspace(count: usize) -> Result<(), String> {
     use core_graphics::event::{CGEvent, CGEventTapLocation};
     use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
@@ -816,7 +1627,6 @@ fn type_backspace(count: usize) -> Result<(), String> {
 }
 
 #[cfg(not(target_os = "macos"))]
-#[allow(dead_code)]
 fn type_backspace(_count: usize) -> Result<(), String> {
     Err("Not supported on this platform".to_string())
 }
@@ -913,3 +1723,127 @@ fn type_via_clipboard(text: &str) -> Result<(), String> {
 fn type_via_clipboard(_text: &str) -> Result<(), String> {
     Err("Not supported on this platform".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    fn tracker() -> ShortcutTracker {
+        ShortcutTracker {
+            phase: ShortcutPhase::Idle,
+        }
+    }
+
+    #[test]
+    fn hold_dictates_while_the_key_is_down() {
+        let t0 = Instant::now();
+        let mut shortcut = tracker();
+        assert_eq!(
+            shortcut.press(ShortcutMode::Hold, t0),
+            ShortcutAction::Start
+        );
+        assert_eq!(
+            shortcut.release(ShortcutMode::Hold, t0 + ms(50)),
+            ShortcutAction::Stop
+        );
+        assert_eq!(shortcut.phase, ShortcutPhase::Idle);
+    }
+
+    #[test]
+    fn toggle_starts_and_stops_on_presses() {
+        let manager = DictationManager::new();
+        let mode = ShortcutMode::Toggle;
+        assert_eq!(manager.shortcut_pressed(mode), ShortcutAction::Start);
+        assert_eq!(manager.shortcut_released(mode), ShortcutAction::None);
+        assert_eq!(manager.shortcut_pressed(mode), ShortcutAction::Stop);
+        assert_eq!(manager.shortcut_released(mode), ShortcutAction::None);
+        assert_eq!(manager.shortcut_pressed(mode), ShortcutAction::Start);
+    }
+
+    #[test]
+    fn double_tap_locks_dictation_on() {
+        let t0 = Instant::now();
+        let mode = ShortcutMode::DoubleTap;
+        let mut shortcut = tracker();
+        assert_eq!(shortcut.press(mode, t0), ShortcutAction::Start);
+        assert_eq!(
+            shortcut.release(mode, t0 + ms(100)),
+            ShortcutAction::AwaitSecondTap
+        );
+        assert_eq!(shortcut.press(mode, t0 + ms(250)), ShortcutAction::None);
+        assert_eq!(shortcut.release(mode, t0 + ms(330)), ShortcutAction::None);
+        // The timer of the first tap finds the dictation locked.
+        assert_eq!(shortcut.tap_expired(t0 + ms(400)), ShortcutAction::None);
+        assert_eq!(shortcut.release(mode, t0 + ms(5000)), ShortcutAction::None);
+        assert_eq!(shortcut.press(mode, t0 + ms(9000)), ShortcutAction::Stop);
+        assert_eq!(shortcut.release(mode, t0 + ms(9100)), ShortcutAction::None);
+        assert_eq!(shortcut.phase, ShortcutPhase::Idle);
+    }
+
+    #[test]
+    fn double_tap_mode_still_holds_to_talk() {
+        let t0 = Instant::now();
+        let mode = ShortcutMode::DoubleTap;
+        let mut shortcut = tracker();
+        assert_eq!(shortcut.press(mode, t0), ShortcutAction::Start);
+        assert_eq!(shortcut.release(mode, t0 + ms(2000)), ShortcutAction::Stop);
+    }
+
+    #[test]
+    fn single_tap_stops_when_the_window_closes() {
+        let t0 = Instant::now();
+        let mode = ShortcutMode::DoubleTap;
+        let mut shortcut = tracker();
+        shortcut.press(mode, t0);
+        shortcut.release(mode, t0 + ms(100));
+        assert_eq!(shortcut.tap_expired(t0 + ms(200)), ShortcutAction::None);
+        assert_eq!(shortcut.tap_expired(t0 + ms(400)), ShortcutAction::Stop);
+        assert_eq!(shortcut.press(mode, t0 + ms(1000)), ShortcutAction::Start);
+    }
+
+    #[test]
+    fn late_second_tap_stops_instead_of_locking() {
+        let t0 = Instant::now();
+        let mode = ShortcutMode::DoubleTap;
+        let mut shortcut = tracker();
+        shortcut.press(mode, t0);
+        shortcut.release(mode, t0 + ms(100));
+        // The expiry timer has not run yet, but the window has passed.
+        assert_eq!(shortcut.press(mode, t0 + ms(600)), ShortcutAction::Stop);
+        assert_eq!(shortcut.release(mode, t0 + ms(700)), ShortcutAction::None);
+        assert_eq!(shortcut.tap_expired(t0 + ms(800)), ShortcutAction::None);
+    }
+
+    #[test]
+    fn sync_follows_dictation_stopped_elsewhere() {
+        let manager = DictationManager::new();
+        let mode = ShortcutMode::Toggle;
+        assert_eq!(manager.shortcut_pressed(mode), ShortcutAction::Start);
+        manager.shortcut_released(mode);
+        // Auto-stop ended the dictation; the next press starts a new one.
+        manager.sync_shortcut(false);
+        assert_eq!(manager.shortcut_pressed(mode), ShortcutAction::Start);
+        manager.shortcut_released(mode);
+        manager.sync_shortcut(true);
+        assert_eq!(manager.shortcut_pressed(mode), ShortcutAction::Stop);
+    }
+
+    #[test]
+    fn realtime_session_text_follows_voice_edits() {
+        let session = SessionText::start();
+        let mut text = session.lock().unwrap();
+        text.push("Hello there.");
+        text.push(" 你好。");
+        text.remove(4);
+        let record = text.finish("soniox:stt-rt-v3".to_string(), Instant::now());
+        assert_eq!(record.text, "Hello there.");
+        assert_eq!(record.source, history::Source::Dictation);
+        assert!(record.audio.is_none());
+        text.remove(100);
+        assert_eq!(text.text, "");
+    }
+}
@@ -22,6 +22,9 @@ struct WsMessage {
     error: String,
 }
 
+/// Rates accepted by the `pcm_{rate}` audio formats.
+pub const SAMPLE_RATES: &[u32] = &[8_000, 16_000, 22_050, 24_000, 44_100, 48_000];
+
 pub enum TypeAction {
     /// Replace the current draft utterance with this full partial text.
     SetDraft(String),
@@ -30,16 +33,31 @@ pub enum TypeAction {
 }
 
 pub struct RealtimeSession {
+    sample_rate: u32,
     audio_tx: mpsc::Sender<Vec<i16>>,
     stop_tx: mpsc::Sender<()>,
     transcript_rx: Arc<Mutex<mpsc::Receiver<TypeAction>>>,
 }
 
+/// Percent-encodes a query parameter value.
+fn query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
 impl RealtimeSession {
     pub async fn start(
         api_key: &str,
         sample_rate: u32,
         language: Option<String>,
+        keyterms: Vec<String>,
     ) -> Result<Self, String> {
         eprintln!(
             "[elevenlabs] start, key_len: {}, sample_rate: {}",
@@ -56,6 +74,9 @@ impl RealtimeSession {
         if let Some(ref lang) = language {
             url.push_str(&format!("&language_code={}", lang));
         }
+        for term in &keyterms {
+            url.push_str(&format!("&keyterms={}", query_value(term)));
+        }
 
         // Build request with xi-api-key header
         let request = tungstenite::http::Request::builder()
@@ -197,12 +218,17 @@ impl RealtimeSession {
         });
 
         Ok(Self {
+            sample_rate,
             audio_tx,
             stop_tx,
             transcript_rx: Arc::new(Mutex::new(transcript_rx)),
         })
     }
 
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
     pub async fn send_audio(&self, samples: Vec<i16>) -> Result<(), String> {
         self.audio_tx
             .send(samples)
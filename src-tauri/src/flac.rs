@@ -0,0 +1,401 @@
+//! Minimal FLAC encoder for mono 16-bit recordings.
+//!
+//! Each block uses a constant subframe or the best fixed predictor
+//! (orders 0-4) with partitioned Rice coding. That gets most of FLAC's
+//! gain on speech without pulling in an LPC encoder. Only mono 16-bit
+//! input is needed, so this stays smaller than a general encoder crate;
+//! the tests check its output against symphonia's decoder.
+
+const BLOCK_SIZE: usize = 4096;
+const BITS_PER_SAMPLE: u32 = 16;
+const MAX_FIXED_ORDER: usize = 4;
+const MAX_PARTITION_ORDER: u32 = 6;
+const MAX_RICE_PARAM: u32 = 14;
+
+/// Encodes mono 16-bit samples as a FLAC stream.
+pub fn encode(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(samples.len() + 64);
+    out.extend_from_slice(b"fLaC");
+    write_stream_info(&mut out, samples.len() as u64, sample_rate);
+
+    for (index, block) in samples.chunks(BLOCK_SIZE).enumerate() {
+        write_frame(&mut out, index as u64, block);
+    }
+    out
+}
+
+fn write_stream_info(out: &mut Vec<u8>, total_samples: u64, sample_rate: u32) {
+    let block_size = total_samples.clamp(16, BLOCK_SIZE as u64);
+    let mut bits = BitWriter::default();
+    // Last metadata block, type 0 (STREAMINFO), 34 bytes.
+    bits.write(1, 1);
+    bits.write(0, 7);
+    bits.write(34, 24);
+    bits.write(block_size, 16);
+    bits.write(block_size, 16);
+    // Minimum and maximum frame sizes are unknown.
+    bits.write(0, 24);
+    bits.write(0, 24);
+    bits.write(sample_rate as u64, 20);
+    bits.write(0, 3); // channels - 1
+    bits.write((BITS_PER_SAMPLE - 1) as u64, 5);
+    bits.write(total_samples, 36);
+    // No MD5 signature.
+    bits.write(0, 64);
+    bits.write(0, 64);
+    out.extend_from_slice(&bits.finish());
+}
+
+fn write_frame(out: &mut Vec<u8>, frame_number: u64, block: &[i16]) {
+    let start = out.len();
+    let mut bits = BitWriter::default();
+    bits.write(0b11_1111_1111_1110, 14); // sync code
+    bits.write(0, 1);
+    bits.write(0, 1); // fixed block size
+    bits.write(0b0111, 4); // block size - 1 follows as 16 bits
+    bits.write(0b0000, 4); // sample rate from STREAMINFO
+    bits.write(0b0000, 4); // mono
+    bits.write(0b100, 3); // 16 bits per sample
+    bits.write(0, 1);
+    write_utf8_number(&mut bits, frame_number);
+    bits.write(block.len() as u64 - 1, 16);
+    let header = bits.finish();
+    out.extend_from_slice(&header);
+    out.push(crc8(&header));
+
+    let mut bits = BitWriter::default();
+    write_subframe(&mut bits, block);
+    out.extend_from_slice(&bits.finish());
+
+    let crc = crc16(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn write_utf8_number(bits: &mut BitWriter, value: u64) {
+    if value < 0x80 {
+        bits.write(value, 8);
+        return;
+    }
+    let mut continuation = 1;
+    while value >= 1 << (5 * continuation + 6) {
+        continuation += 1;
+    }
+    let lead_bits = 6 - continuation;
+    let marker = (0xFF00u64 >> (continuation + 1)) & 0xFF;
+    bits.write(marker | (value >> (6 * continuation)), 8);
+    for shift in (0..continuation).rev() {
+        bits.write(0x80 | ((value >> (6 * shift)) & 0x3F), 8);
+    }
+    debug_assert!(value >> (6 * continuation) < 1 << lead_bits);
+}
+
+fn write_subframe(bits: &mut BitWriter, block: &[i16]) {
+    let samples: Vec<i64> = block.iter().map(|&sample| sample as i64).collect();
+    if samples.iter().all(|&sample| sample == samples[0]) {
+        bits.write(0, 1);
+        bits.write(0b000000, 6);
+        bits.write(0, 1);
+        bits.write_signed(samples[0], BITS_PER_SAMPLE);
+        return;
+    }
+
+    let verbatim_bits = samples.len() as u64 * BITS_PER_SAMPLE as u64;
+    let best = (0..=MAX_FIXED_ORDER.min(samples.len() - 1))
+        .map(|order| {
+            let residuals = fixed_residuals(&samples, order);
+            let plan = RicePlan::choose(&residuals, order, samples.len());
+            (order, residuals, plan)
+        })
+        .min_by_key(|(order, _, plan)| plan.bits + (*order as u64 * BITS_PER_SAMPLE as u64));
+
+    match best {
+        Some((order, residuals, plan)) if plan.bits < verbatim_bits => {
+            bits.write(0, 1);
+            bits.write(0b001000 | order as u64, 6);
+            bits.write(0, 1);
+            for &sample in &samples[..order] {
+                bits.write_signed(sample, BITS_PER_SAMPLE);
+            }
+            plan.write(bits, &residuals, order, samples.len());
+        }
+        _ => {
+            bits.write(0, 1);
+            bits.write(0b000001, 6);
+            bits.write(0, 1);
+            for &sample in &samples {
+                bits.write_signed(sample, BITS_PER_SAMPLE);
+            }
+        }
+    }
+}
+
+/// Residuals of the fixed polynomial predictor, starting after the warm-up.
+fn fixed_residuals(samples: &[i64], order: usize) -> Vec<i64> {
+    let mut residuals = samples.to_vec();
+    for _ in 0..order {
+        for index in (1..residuals.len()).rev() {
+            residuals[index] -= residuals[index - 1];
+        }
+    }
+    residuals.split_off(order)
+}
+
+struct RicePlan {
+    partition_order: u32,
+    params: Vec<u32>,
+    bits: u64,
+}
+
+impl RicePlan {
+    /// Picks the partition order and per-partition parameters with the
+    /// smallest encoded size.
+    fn choose(residuals: &[i64], order: usize, block_len: usize) -> Self {
+        let mut best: Option<Self> = None;
+        for partition_order in 0..=MAX_PARTITION_ORDER {
+            let partitions = 1usize << partition_order;
+            if block_len % partitions != 0 || block_len / partitions <= order {
+                break;
+            }
+            let mut params = Vec::with_capacity(partitions);
+            let mut bits = 2 + 4;
+            for partition in partition_ranges(partition_order, order, block_len) {
+                let (param, cost) = best_rice_param(&residuals[partition]);
+                params.push(param);
+                bits += 4 + cost;
+            }
+            if best.as_ref().map_or(true, |plan| bits < plan.bits) {
+                best = Some(Self {
+                    partition_order,
+                    params,
+                    bits,
+                });
+            }
+        }
+        best.expect("partition order 0 always fits")
+    }
+
+    fn write(&self, bits: &mut BitWriter, residuals: &[i64], order: usize, block_len: usize) {
+        bits.write(0b00, 2); // 4-bit Rice parameters
+        bits.write(self.partition_order as u64, 4);
+        let ranges = partition_ranges(self.partition_order, order, block_len);
+        for (range, &param) in ranges.zip(&self.params) {
+            bits.write(param as u64, 4);
+            for &residual in &residuals[range] {
+                let folded = fold(residual);
+                bits.write_unary(folded >> param);
+                bits.write(folded & ((1 << param) - 1), param);
+            }
+        }
+    }
+}
+
+/// Residual index ranges for each partition. The first partition is short
+/// by the predictor order, since the warm-up samples are stored verbatim.
+fn partition_ranges(
+    partition_order: u32,
+    order: usize,
+    block_len: usize,
+) -> impl Iterator<Item = std::ops::Range<usize>> {
+    let partition_len = block_len >> partition_order;
+    (0..1usize << partition_order).map(move |index| {
+        let start = (index * partition_len).saturating_sub(order);
+        let end = (index + 1) * partition_len - order;
+        start..end
+    })
+}
+
+/// The best parameter sits next to log2 of the mean folded residual, so
+/// only the neighbours of that estimate are costed exactly.
+fn best_rice_param(residuals: &[i64]) -> (u32, u64) {
+    let total: u64 = residuals.iter().map(|&residual| fold(residual)).sum();
+    let mean = total / residuals.len().max(1) as u64;
+    let estimate = (u64::BITS - mean.leading_zeros()).min(MAX_RICE_PARAM);
+    (estimate.saturating_sub(1)..=(estimate + 1).min(MAX_RICE_PARAM))
+        .map(|param| {
+            let cost = residuals
+                .iter()
+                .map(|&residual| (fold(residual) >> param) + 1 + param as u64)
+                .sum();
+            (param, cost)
+        })
+        .min_by_key(|&(_, cost)| cost)
+        .unwrap_or((0, 0))
+}
+
+fn fold(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, bits: u32) {
+        for shift in (0..bits).rev() {
+            self.acc = (self.acc << 1) | ((value >> shift) & 1);
+            self.len += 1;
+            if self.len == 8 {
+                self.bytes.push(self.acc as u8);
+                self.acc = 0;
+                self.len = 0;
+            }
+        }
+    }
+
+    fn write_signed(&mut self, value: i64, bits: u32) {
+        self.write(value as u64 & ((1 << bits) - 1), bits);
+    }
+
+    fn write_unary(&mut self, zeros: u64) {
+        for _ in 0..zeros {
+            self.write(0, 1);
+        }
+        self.write(1, 1);
+    }
+
+    /// Pads to a byte boundary with zero bits.
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.write(0, 8 - self.len);
+        }
+        self.bytes
+    }
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: Vec<u8>) -> (Vec<i16>, u32) {
+        use symphonia::core::audio::SampleBuffer;
+        use symphonia::core::codecs::DecoderOptions;
+        use symphonia::core::formats::FormatOptions;
+        use symphonia::core::io::MediaSourceStream;
+        use symphonia::core::meta::MetadataOptions;
+        use symphonia::core::probe::Hint;
+
+        let source =
+            MediaSourceStream::new(Box::new(std::io::Cursor::new(bytes)), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension("flac");
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                source,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .unwrap();
+        let mut format = probed.format;
+        let track = format.default_track().unwrap();
+        let sample_rate = track.codec_params.sample_rate.unwrap();
+        let mut decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions { verify: true })
+            .unwrap();
+
+        let mut samples = Vec::new();
+        while let Ok(packet) = format.next_packet() {
+            let decoded = decoder.decode(&packet).unwrap();
+            let mut buffer = SampleBuffer::<i16>::new(decoded.capacity() as u64, *decoded.spec());
+            buffer.copy_interleaved_ref(decoded);
+            samples.extend_from_slice(buffer.samples());
+        }
+        (samples, sample_rate)
+    }
+
+    #[test]
+    fn round_trips_through_a_decoder() {
+        // A tone with noise and silence, sized to end on a partial block.
+        let mut seed = 1u32;
+        let samples: Vec<i16> = (0..BLOCK_SIZE * 3 + 123)
+            .map(|i| {
+                if (BLOCK_SIZE..BLOCK_SIZE * 2).contains(&i) {
+                    return 0;
+                }
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                let noise = (seed >> 16) as i16 as f32 / 32.0;
+                let tone = (i as f32 * 0.05).sin() * 20_000.0;
+                (tone + noise).clamp(-32_768.0, 32_767.0) as i16
+            })
+            .collect();
+
+        let (decoded, sample_rate) = decode(encode(&samples, 16_000));
+        assert_eq!(sample_rate, 16_000);
+        assert_eq!(decoded, samples);
+    }
+
+    #[test]
+    fn reference_decoder_accepts_edge_cases() {
+        // Full-scale spikes give residuals far past the Rice parameter.
+        let spiky: Vec<i16> = (0..BLOCK_SIZE + 500)
+            .map(|i| match i % 700 {
+                0 => i16::MAX,
+                350 => i16::MIN,
+                _ => ((i as f32 * 0.01).sin() * 1_000.0) as i16,
+            })
+            .collect();
+        // Alternating extremes only fit verbatim.
+        let extremes: Vec<i16> = (0..300)
+            .map(|i| if i % 2 == 0 { i16::MIN } else { i16::MAX })
+            .collect();
+        let short = vec![3, -7, 12, 0, 5];
+        for (samples, sample_rate) in [(spiky, 48_000), (extremes, 44_100), (short, 16_000)] {
+            assert_eq!(
+                decode(encode(&samples, sample_rate)),
+                (samples, sample_rate)
+            );
+        }
+    }
+
+    #[test]
+    fn compresses_speech_like_audio() {
+        let samples: Vec<i16> = (0..16_000)
+            .map(|i| ((i as f32 * 0.02).sin() * 8_000.0) as i16)
+            .collect();
+        let encoded = encode(&samples, 16_000);
+        assert!(encoded.len() < samples.len(), "{} bytes", encoded.len());
+    }
+
+    #[test]
+    fn encodes_large_frame_numbers() {
+        let mut bits = BitWriter::default();
+        write_utf8_number(&mut bits, 0x7FF);
+        assert_eq!(bits.finish(), [0xDF, 0xBF]);
+
+        let mut bits = BitWriter::default();
+        write_utf8_number(&mut bits, 0x800);
+        assert_eq!(bits.finish(), [0xE0, 0xA0, 0x80]);
+    }
+}
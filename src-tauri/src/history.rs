@@ -0,0 +1,375 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+const MAX_ENTRIES: usize = 5000;
+const HISTORY_FILE: &str = "history.jsonl";
+const AUDIO_DIR: &str = "audio";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Dictation,
+    Playground,
+    Gateway,
+}
+
+/// One finished transcription.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    /// Unique and increasing; milliseconds since the epoch unless two
+    /// entries land in the same millisecond.
+    pub id: u64,
+    pub timestamp: u64,
+    pub source: Source,
+    pub model: String,
+    /// Length of the audio, when known.
+    pub duration_ms: Option<u64>,
+    /// Time the transcription took.
+    pub latency_ms: u64,
+    pub text: String,
+    /// File name of the kept audio inside the audio directory.
+    pub audio: Option<String>,
+}
+
+/// Why a history operation failed.
+#[derive(Debug, PartialEq)]
+pub enum HistoryError {
+    /// No entry has this id.
+    NotFound(u64),
+    /// The history files could not be read or written.
+    Storage(String),
+}
+
+impl std::fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryError::NotFound(id) => write!(f, "Unknown history entry: {id}"),
+            HistoryError::Storage(message) => f.write_str(message),
+        }
+    }
+}
+
+/// A transcription to record.
+pub struct Record {
+    pub source: Source,
+    pub model: String,
+    pub duration_ms: Option<u64>,
+    pub latency_ms: u64,
+    pub text: String,
+    /// File extension and bytes of the audio, when it should be kept.
+    pub audio: Option<(String, Vec<u8>)>,
+}
+
+/// Transcription history, one JSON line per entry plus an optional audio
+/// file each. The newest `MAX_ENTRIES` are kept. Entries load on first
+/// use. Methods do blocking file I/O; call them from `spawn_blocking`.
+pub struct HistoryStore {
+    dir: PathBuf,
+    max_entries: usize,
+    entries: Option<Vec<HistoryEntry>>,
+    /// The file has lines that did not parse and is rewritten on the next add.
+    damaged: bool,
+}
+
+impl HistoryStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            max_entries: MAX_ENTRIES,
+            entries: None,
+            damaged: false,
+        }
+    }
+
+    pub fn add(&mut self, record: Record, now_ms: u64) -> Result<HistoryEntry, HistoryError> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|err| HistoryError::Storage(format!("Failed to create history dir: {err}")))?;
+        let last_id = self.entries().last().map_or(0, |entry| entry.id);
+        let id = now_ms.max(last_id + 1);
+        let audio = match record.audio {
+            Some((extension, bytes)) => {
+                let name = format!("{id}.{extension}");
+                let audio_dir = self.dir.join(AUDIO_DIR);
+                std::fs::create_dir_all(&audio_dir)
+                    .and_then(|_| std::fs::write(audio_dir.join(&name), bytes))
+                    .map_err(|err| {
+                        HistoryError::Storage(format!("Failed to keep history audio: {err}"))
+                    })?;
+                Some(name)
+            }
+            None => None,
+        };
+        let entry = HistoryEntry {
+            id,
+            timestamp: now_ms,
+            source: record.source,
+            model: record.model,
+            duration_ms: record.duration_ms,
+            latency_ms: record.latency_ms,
+            text: record.text,
+            audio,
+        };
+
+        self.entries().push(entry.clone());
+
+        // Prune in batches so a full history is not rewritten on every add.
+        if self.entries().len() > self.max_entries + self.max_entries / 10 {
+            let excess = self.entries().len() - self.max_entries;
+            let removed: Vec<HistoryEntry> = self.entries().drain(..excess).collect();
+            for entry in &removed {
+                self.remove_audio(entry);
+            }
+            self.rewrite()?;
+        } else if self.damaged {
+            self.rewrite()?;
+        } else {
+            let mut line = serde_json::to_string(&entry).map_err(|err| {
+                HistoryError::Storage(format!("Failed to serialize history entry: {err}"))
+            })?;
+            line.push('\n');
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.file_path())
+                .and_then(|mut file| file.write_all(line.as_bytes()))
+                .map_err(|err| HistoryError::Storage(format!("Failed to write history: {err}")))?;
+        }
+        Ok(entry)
+    }
+
+    /// Entries whose text contains every word of `query`, ignoring case,
+    /// newest first.
+    pub fn search(&mut self, query: &str, limit: usize) -> Vec<HistoryEntry> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        self.entries()
+            .iter()
+            .rev()
+            .filter(|entry| {
+                let text = entry.text.to_lowercase();
+                words.iter().all(|word| text.contains(word.as_str()))
+            })
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&mut self, id: u64) -> Result<HistoryEntry, HistoryError> {
+        self.entries()
+            .iter()
+            .find(|entry| entry.id == id)
+            .cloned()
+            .ok_or(HistoryError::NotFound(id))
+    }
+
+    pub fn audio_path(&self, entry: &HistoryEntry) -> Option<PathBuf> {
+        entry
+            .audio
+            .as_ref()
+            .map(|name| self.dir.join(AUDIO_DIR).join(name))
+    }
+
+    /// Replaces the transcript of an entry, as after re-transcribing it.
+    pub fn update(
+        &mut self,
+        id: u64,
+        model: String,
+        latency_ms: u64,
+        text: String,
+    ) -> Result<HistoryEntry, HistoryError> {
+        let entry = self
+            .entries()
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .ok_or(HistoryError::NotFound(id))?;
+        entry.model = model;
+        entry.latency_ms = latency_ms;
+        entry.text = text;
+        let entry = entry.clone();
+        self.rewrite()?;
+        Ok(entry)
+    }
+
+    pub fn delete(&mut self, id: u64) -> Result<(), HistoryError> {
+        let index = self
+            .entries()
+            .iter()
+            .position(|entry| entry.id == id)
+            .ok_or(HistoryError::NotFound(id))?;
+        let entry = self.entries().remove(index);
+        self.remove_audio(&entry);
+        self.rewrite()
+    }
+
+    fn entries(&mut self) -> &mut Vec<HistoryEntry> {
+        if self.entries.is_none() {
+            let contents = std::fs::read_to_string(self.file_path()).unwrap_or_default();
+            let lines: Vec<&str> = contents.lines().collect();
+            // A line cut short by a crash is skipped, not fatal.
+            let entries: Vec<HistoryEntry> = lines
+                .iter()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect();
+            self.damaged = entries.len() != lines.len();
+            self.entries = Some(entries);
+        }
+        self.entries.get_or_insert_with(Vec::new)
+    }
+
+    fn remove_audio(&self, entry: &HistoryEntry) {
+        if let Some(path) = self.audio_path(entry) {
+            if let Err(err) = std::fs::remove_file(&path) {
+                eprintln!("[history] failed to remove {}: {err}", path.display());
+            }
+        }
+    }
+
+    /// Writes all entries to a new file and swaps it in.
+    fn rewrite(&mut self) -> Result<(), HistoryError> {
+        self.damaged = false;
+        let mut contents = String::new();
+        for entry in self.entries().iter() {
+            let line = serde_json::to_string(entry).map_err(|err| {
+                HistoryError::Storage(format!("Failed to serialize history entry: {err}"))
+            })?;
+            contents.push_str(&line);
+            contents.push('\n');
+        }
+        let path = self.file_path();
+        let temp_path = path.with_extension("jsonl.tmp");
+        std::fs::write(&temp_path, contents)
+            .and_then(|_| std::fs::rename(&temp_path, &path))
+            .map_err(|err| HistoryError::Storage(format!("Failed to write history: {err}")))
+    }
+
+    fn file_path(&self) -> PathBuf {
+        self.dir.join(HISTORY_FILE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(name: &str) -> HistoryStore {
+        let dir =
+            std::env::temp_dir().join(format!("openstt-history-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        HistoryStore::new(dir)
+    }
+
+    fn record(text: &str, audio: Option<&[u8]>) -> Record {
+        Record {
+            source: Source::Dictation,
+            model: "base".to_string(),
+            duration_ms: Some(1500),
+            latency_ms: 200,
+            text: text.to_string(),
+            audio: audio.map(|bytes| ("wav".to_string(), bytes.to_vec())),
+        }
+    }
+
+    #[test]
+    fn entries_survive_a_reload_and_ids_increase() {
+        let mut history = store("reload");
+        let first = history.add(record("first", None), 1000).unwrap();
+        let second = history.add(record("second", None), 1000).unwrap();
+        assert_eq!((first.id, second.id), (1000, 1001));
+
+        let mut reloaded = HistoryStore::new(history.dir.clone());
+        assert_eq!(reloaded.search("", 10), [second, first]);
+        let _ = std::fs::remove_dir_all(&history.dir);
+    }
+
+    #[test]
+    fn search_matches_all_words_in_any_case() {
+        let mut history = store("search");
+        history
+            .add(record("Deploy the Kubernetes cluster", None), 1)
+            .unwrap();
+        history.add(record("Kubernetes notes", None), 2).unwrap();
+        history.add(record("Lunch order", None), 3).unwrap();
+        let texts = |entries: Vec<HistoryEntry>| -> Vec<String> {
+            entries.into_iter().map(|entry| entry.text).collect()
+        };
+        assert_eq!(
+            texts(history.search("kubernetes", 10)),
+            ["Kubernetes notes", "Deploy the Kubernetes cluster"]
+        );
+        assert_eq!(
+            texts(history.search("CLUSTER kube", 10)),
+            ["Deploy the Kubernetes cluster"]
+        );
+        assert_eq!(texts(history.search("", 1)), ["Lunch order"]);
+        let _ = std::fs::remove_dir_all(&history.dir);
+    }
+
+    #[test]
+    fn update_and_delete_persist_and_remove_audio() {
+        let mut history = store("edit");
+        let entry = history.add(record("helo", Some(b"RIFF")), 5).unwrap();
+        let audio = history.audio_path(&entry).unwrap();
+        assert!(audio.exists());
+
+        history
+            .update(entry.id, "large".to_string(), 900, "hello".to_string())
+            .unwrap();
+        let mut reloaded = HistoryStore::new(history.dir.clone());
+        let updated = reloaded.get(entry.id).unwrap();
+        assert_eq!(
+            (updated.model.as_str(), updated.text.as_str()),
+            ("large", "hello")
+        );
+
+        reloaded.delete(entry.id).unwrap();
+        assert!(!audio.exists());
+        assert!(HistoryStore::new(history.dir.clone())
+            .get(entry.id)
+            .is_err());
+        let _ = std::fs::remove_dir_all(&history.dir);
+    }
+
+    #[test]
+    fn only_unknown_ids_are_not_found() {
+        let mut history = store("errors");
+        assert_eq!(history.get(7), Err(HistoryError::NotFound(7)));
+        assert_eq!(history.delete(7), Err(HistoryError::NotFound(7)));
+
+        let entry = history.add(record("kept", None), 7).unwrap();
+        std::fs::remove_dir_all(&history.dir).unwrap();
+        assert!(matches!(
+            history.delete(entry.id),
+            Err(HistoryError::Storage(_))
+        ));
+    }
+
+    #[test]
+    fn prunes_the_oldest_entries_and_skips_broken_lines() {
+        let mut history = store("prune");
+        history.max_entries = 10;
+        for index in 0..11 {
+            history
+                .add(record(&index.to_string(), None), index)
+                .unwrap();
+        }
+        assert_eq!(history.search("", 100).len(), 11);
+        history.add(record("11", None), 11).unwrap();
+        assert_eq!(history.search("", 100).len(), 10);
+        assert_eq!(history.search("", 100).last().unwrap().text, "2");
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(history.file_path())
+            .unwrap();
+        file.write_all(b"{\"id\": 99, \"tex").unwrap();
+        let mut reloaded = HistoryStore::new(history.dir.clone());
+        assert_eq!(reloaded.search("", 100).len(), 10);
+        reloaded.add(record("after", None), 100).unwrap();
+        let mut reloaded = HistoryStore::new(history.dir.clone());
+        assert_eq!(reloaded.search("", 1)[0].text, "after");
+        assert!(!reloaded.damaged);
+        let _ = std::fs::remove_dir_all(&history.dir);
+    }
+}
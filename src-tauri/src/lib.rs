@@ -1,12 +1,20 @@
 mod audio;
+mod cache;
 mod dictation;
 pub mod elevenlabs_realtime;
+mod flac;
+mod history;
+mod local_realtime;
 mod models;
+mod preprocess;
+mod punctuation;
 mod recording;
 pub mod soniox_realtime;
+mod typed_history;
+mod vocabulary;
 
 use axum::{
-    extract::{Multipart, State as AxumState},
+    extract::{Multipart, Path as AxumPath, Query, State as AxumState},
     http::StatusCode,
     response::{IntoResponse, Response},
     routing::{get, post},
@@ -66,11 +74,15 @@ struct AppState {
     app_handle: Arc<Mutex<Option<tauri::AppHandle>>>,
     tray_snapshot: Arc<Mutex<Option<TraySnapshot>>>,
     dictation_shortcut: Arc<Mutex<Option<Shortcut>>>,
+    undo_shortcut: Arc<Mutex<Option<Shortcut>>>,
     dictation_tray_state: Arc<Mutex<DictationTrayState>>,
     dictation: Arc<dictation::DictationManager>,
     dictation_op_lock: Arc<Mutex<()>>,
     downloading: Arc<Mutex<bool>>,
     app_status: Arc<Mutex<AppStatus>>,
+    /// Spooled recordings found at launch that were never transcribed.
+    orphaned_recordings: Arc<Mutex<Vec<recording::OrphanedRecording>>>,
+    history: Arc<Mutex<history::HistoryStore>>,
 }
 
 struct ServerRuntime {
@@ -96,7 +108,12 @@ const TRAY_START: &str = "tray-start";
 const TRAY_STOP: &str = "tray-stop";
 const TRAY_SETTINGS: &str = "tray-settings";
 const TRAY_LOGS: &str = "tray-logs";
+const TRAY_UNDO: &str = "tray-undo-dictation";
 const TRAY_QUIT: &str = "tray-quit";
+/// Longest pre-roll kept in memory; the UI offers up to one second.
+const MAX_PRE_ROLL_MS: u32 = 2000;
+/// History entries returned by a search without a limit.
+const DEFAULT_HISTORY_LIMIT: usize = 100;
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -212,12 +229,71 @@ struct UiSettings {
     reduced_transparency: bool,
     language: String,
     dictation_shortcut: DictationShortcut,
+    /// Deletes the last dictation from the focused app; `None` disables it.
+    /// Its `mode` is not used.
+    undo_shortcut: Option<DictationShortcut>,
     dictation_auto_paste: bool,
     elevenlabs_api_key: String,
     soniox_api_key: String,
     soniox_warm_connection: bool,
     soniox_warm_connection_minutes: u32,
     soniox_warm_connection_forever: bool,
+    dictation_vad: bool,
+    audio_preprocess: preprocess::PreprocessOptions,
+    transcript_cache: bool,
+    /// Microphone name from `list_input_devices`; empty uses the system default.
+    input_device: String,
+    /// Audio kept from before the hotkey press; 0 disables the pre-roll.
+    pre_roll_ms: u32,
+    /// Length of each audio chunk sent to realtime providers.
+    stream_chunk_ms: u32,
+    /// Stop dictation after this many seconds of silence; 0 disables it.
+    auto_stop_silence_secs: u32,
+    /// Stop dictation once it runs this long; 0 disables the limit.
+    max_recording_secs: u32,
+    /// Write recordings to `recordings_dir()` while capturing, so a crash
+    /// does not lose them.
+    spool_recordings: bool,
+    /// Upload dictations to cloud models as 16 kHz FLAC instead of WAV.
+    flac_uploads: bool,
+    /// Dictate with local whisper models as the cloud realtime models do,
+    /// typing each pause-separated phrase instead of waiting for the release.
+    local_live_dictation: bool,
+    vocabulary: vocabulary::Vocabulary,
+    /// Turn spoken commands such as "comma" or "new line" into text, using
+    /// the commands of the app language.
+    spoken_punctuation: bool,
+    /// Record finished transcriptions under `history_dir()`.
+    history_enabled: bool,
+    /// Keep the audio with each history entry so it can be re-transcribed.
+    history_keep_audio: bool,
+    /// Also record transcriptions other apps request through the gateway.
+    history_gateway: bool,
+}
+
+impl UiSettings {
+    fn auto_stop(&self) -> dictation::AutoStop {
+        let seconds = |value: u32| (value > 0).then_some(Duration::from_secs(value as u64));
+        dictation::AutoStop {
+            silence: seconds(self.auto_stop_silence_secs),
+            max_duration: seconds(self.max_recording_secs),
+        }
+    }
+
+    fn spool_dir(&self) -> Option<PathBuf> {
+        self.spool_recordings.then(recordings_dir)
+    }
+
+    /// Whether transcriptions from `source` are recorded in the history.
+    fn records_history(&self, source: history::Source) -> bool {
+        self.history_enabled && (source != history::Source::Gateway || self.history_gateway)
+    }
+
+    fn spoken_commands(&self) -> Option<&'static punctuation::CommandTable> {
+        self.spoken_punctuation
+            .then(|| punctuation::table(&self.language))
+            .flatten()
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -225,6 +301,7 @@ struct UiSettings {
 struct DictationShortcut {
     key: String,
     modifiers: Vec<String>,
+    mode: dictation::ShortcutMode,
 }
 
 impl Default for DictationShortcut {
@@ -232,6 +309,7 @@ impl Default for DictationShortcut {
         Self {
             key: "AltLeft".to_string(),
             modifiers: Vec::new(),
+            mode: dictation::ShortcutMode::Hold,
         }
     }
 }
@@ -242,12 +320,29 @@ impl Default for UiSettings {
             reduced_transparency: false,
             language: "en".to_string(),
             dictation_shortcut: DictationShortcut::default(),
+            undo_shortcut: None,
             dictation_auto_paste: true,
             elevenlabs_api_key: String::new(),
             soniox_api_key: String::new(),
             soniox_warm_connection: false,
             soniox_warm_connection_minutes: 5,
             soniox_warm_connection_forever: false,
+            dictation_vad: false,
+            audio_preprocess: preprocess::PreprocessOptions::default(),
+            transcript_cache: false,
+            input_device: String::new(),
+            pre_roll_ms: 0,
+            stream_chunk_ms: recording::DEFAULT_STREAM_CHUNK_MS,
+            auto_stop_silence_secs: 0,
+            max_recording_secs: 0,
+            spool_recordings: false,
+            flac_uploads: true,
+            local_live_dictation: false,
+            vocabulary: vocabulary::Vocabulary::default(),
+            spoken_punctuation: false,
+            history_enabled: false,
+            history_keep_audio: false,
+            history_gateway: false,
         }
     }
 }
@@ -274,6 +369,154 @@ struct HealthResponse {
 #[derive(Serialize)]
 struct TranscriptionResponse {
     text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channels: Option<Vec<ChannelTranscript>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    segments: Option<Vec<TranscriptSegment>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct TranscriptSegment {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channel: Option<usize>,
+    /// Seconds from the start of the audio.
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ChannelTranscript {
+    channel: usize,
+    text: String,
+}
+
+/// Result of a transcription. `segments` are only available from local
+/// whisper models, and `channels` only for `ChannelMode::Split`.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub(crate) struct Transcript {
+    pub(crate) text: String,
+    segments: Vec<TranscriptSegment>,
+    channels: Option<Vec<ChannelTranscript>>,
+}
+
+impl Transcript {
+    fn from_text(text: String) -> Self {
+        Self {
+            text,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum ChannelMode {
+    /// Average all channels into one mono signal.
+    #[default]
+    Mix,
+    /// Transcribe each channel on its own and merge the segments by time.
+    Split,
+}
+
+impl ChannelMode {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "mix" | "mono" => Ok(Self::Mix),
+            "split" => Ok(Self::Split),
+            other => Err(format!("Unsupported channels value: {other}")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum VadMode {
+    #[default]
+    Off,
+    /// Cut silence found by `audio::speech_regions` before running whisper.
+    Energy,
+}
+
+impl VadMode {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "off" | "false" | "0" | "none" => Ok(Self::Off),
+            "on" | "true" | "1" | "auto" | "energy" => Ok(Self::Energy),
+            other => Err(format!("Unsupported vad value: {other}")),
+        }
+    }
+}
+
+/// Parses the audio-handling request parameters shared by the HTTP endpoint
+/// and the `transcribe_audio` command.
+fn parse_transcribe_options(
+    channels: Option<&str>,
+    vad: Option<&str>,
+    preprocess: Option<&str>,
+    cache: Option<&str>,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
+) -> Result<TranscribeOptions, String> {
+    let start = start_time.unwrap_or(0.0);
+    if !start.is_finite() || start < 0.0 {
+        return Err(format!("Invalid start_time: {start}"));
+    }
+    if let Some(end) = end_time {
+        if !end.is_finite() || end <= start {
+            return Err(format!("end_time must be after start_time: {end}"));
+        }
+    }
+    let cache = match cache.map(|value| value.trim().to_lowercase()).as_deref() {
+        None | Some("") => None,
+        Some("true" | "1" | "on") => Some(true),
+        Some("false" | "0" | "off") => Some(false),
+        Some(other) => return Err(format!("Unsupported cache value: {other}")),
+    };
+    Ok(TranscribeOptions {
+        channels: ChannelMode::parse(channels.unwrap_or(""))?,
+        vad: VadMode::parse(vad.unwrap_or(""))?,
+        preprocess: preprocess::PreprocessOptions::parse(preprocess.unwrap_or(""))?,
+        cache,
+        range: audio::TimeRange {
+            start,
+            end: end_time,
+        },
+        ..Default::default()
+    })
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct TranscribeOptions {
+    pub(crate) language: Option<String>,
+    pub(crate) task: Option<String>,
+    pub(crate) temperature: Option<f32>,
+    pub(crate) prompt: Option<String>,
+    pub(crate) channels: ChannelMode,
+    pub(crate) vad: VadMode,
+    pub(crate) preprocess: preprocess::PreprocessOptions,
+    /// Overrides the transcript cache setting for this request.
+    pub(crate) cache: Option<bool>,
+    /// Section of the file to transcribe; segment times stay relative to the
+    /// start of the file.
+    pub(crate) range: audio::TimeRange,
+    /// Terms from the user's vocabulary to bias recognition towards.
+    pub(crate) vocabulary: Vec<String>,
+}
+
+impl TranscribeOptions {
+    /// Options that only the local whisper path implements.
+    fn whisper_only_option(&self) -> Option<&'static str> {
+        if self.channels == ChannelMode::Split {
+            Some("channels=split")
+        } else if self.range != audio::TimeRange::default() {
+            Some("start_time/end_time")
+        } else if self.vad != VadMode::Off {
+            Some("vad")
+        } else if self.preprocess.is_enabled() {
+            Some("preprocess")
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -283,22 +526,47 @@ struct MlxTranscriptionResponse {
 
 pub(crate) struct TranscribeError {
     pub(crate) message: String,
+    status: StatusCode,
 }
 
 impl TranscribeError {
     fn bad_request(message: impl Into<String>) -> Self {
         Self {
             message: message.into(),
+            status: StatusCode::BAD_REQUEST,
         }
     }
 
     fn internal(message: impl Into<String>) -> Self {
         Self {
             message: message.into(),
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            status: StatusCode::NOT_FOUND,
+        }
+    }
+}
+
+impl From<history::HistoryError> for TranscribeError {
+    fn from(err: history::HistoryError) -> Self {
+        match err {
+            history::HistoryError::NotFound(_) => Self::not_found(err.to_string()),
+            history::HistoryError::Storage(message) => Self::internal(message),
         }
     }
 }
 
+impl IntoResponse for TranscribeError {
+    fn into_response(self) -> Response {
+        (self.status, self.message).into_response()
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct TranscribeAudioRequest {
@@ -306,6 +574,12 @@ struct TranscribeAudioRequest {
     file_name: Option<String>,
     model_id: Option<String>,
     language: Option<String>,
+    channels: Option<String>,
+    vad: Option<String>,
+    preprocess: Option<String>,
+    cache: Option<String>,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
 }
 
 impl LogStore {
@@ -446,11 +720,14 @@ impl AppState {
             app_handle: Arc::new(Mutex::new(None)),
             tray_snapshot: Arc::new(Mutex::new(None)),
             dictation_shortcut: Arc::new(Mutex::new(None)),
+            undo_shortcut: Arc::new(Mutex::new(None)),
             dictation_tray_state: Arc::new(Mutex::new(DictationTrayState::default())),
             dictation: Arc::new(dictation::DictationManager::new()),
             dictation_op_lock: Arc::new(Mutex::new(())),
             downloading: Arc::new(Mutex::new(false)),
             app_status: Arc::new(Mutex::new(AppStatus::Stopped)),
+            orphaned_recordings: Arc::new(Mutex::new(Vec::new())),
+            history: Arc::new(Mutex::new(history::HistoryStore::new(history_dir()))),
         }
     }
 }
@@ -560,39 +837,13 @@ fn start_modifier_event_tap(app: tauri::AppHandle, state: AppState) {
                 }
                 let flags = event.get_flags();
                 let is_down = flags.contains(target_flag);
-                let app_state = state.clone();
-                let app_handle = app.clone();
-                if is_down {
-                    tauri::async_runtime::spawn(async move {
-                        let _guard = app_state.dictation_op_lock.lock().await;
-                        if let Err(err) = start_dictation_inner(&app_state, &app_handle).await {
-                            app_state
-                                .logs
-                                .push("error", format!("Dictation start failed: {err}"))
-                                .await;
-                            return;
-                        }
-                        app_state.dictation.emit_state(&app_handle).await;
-                        let mut tray = app_state.dictation_tray_state.lock().await;
-                        tray.state = "listening".to_string();
-                        tray.queue_len = app_state.dictation.queue_len().await;
-                        tray.phase_started = Some(Instant::now());
-                        drop(tray);
-                        refresh_tray(&app_state).await;
-                        recompute_and_emit_app_status(&app_state).await;
-                    });
+                let action = if is_down {
+                    state.dictation.shortcut_pressed(shortcut.mode)
                 } else {
                     eprintln!("[lib] modifier event tap: key released");
-                    tauri::async_runtime::spawn(async move {
-                        let _guard = app_state.dictation_op_lock.lock().await;
-                        if let Err(err) = stop_dictation_inner(&app_state, &app_handle).await {
-                            app_state
-                                .logs
-                                .push("error", format!("Dictation stop failed: {err}"))
-                                .await;
-                        }
-                    });
-                }
+                    state.dictation.shortcut_released(shortcut.mode)
+                };
+                run_shortcut_action(app.clone(), state.clone(), action);
                 None
             },
         );
@@ -627,6 +878,56 @@ fn start_modifier_event_tap(app: tauri::AppHandle, state: AppState) {
     });
 }
 
+/// Starts or stops dictation as the shortcut state machine decided.
+fn run_shortcut_action(
+    app_handle: tauri::AppHandle,
+    app_state: AppState,
+    action: dictation::ShortcutAction,
+) {
+    match action {
+        dictation::ShortcutAction::None => {}
+        dictation::ShortcutAction::Start => {
+            tauri::async_runtime::spawn(async move {
+                let _guard = app_state.dictation_op_lock.lock().await;
+                if let Err(err) = start_dictation_inner(&app_state, &app_handle).await {
+                    app_state.dictation.sync_shortcut(false);
+                    app_state
+                        .logs
+                        .push("error", format!("Dictation start failed: {err}"))
+                        .await;
+                    return;
+                }
+                app_state.dictation.emit_state(&app_handle).await;
+                let mut tray = app_state.dictation_tray_state.lock().await;
+                tray.state = "listening".to_string();
+                tray.queue_len = app_state.dictation.queue_len().await;
+                tray.phase_started = Some(Instant::now());
+                drop(tray);
+                refresh_tray(&app_state).await;
+                recompute_and_emit_app_status(&app_state).await;
+            });
+        }
+        dictation::ShortcutAction::Stop => {
+            tauri::async_runtime::spawn(async move {
+                let _guard = app_state.dictation_op_lock.lock().await;
+                if let Err(err) = stop_dictation_inner(&app_state, &app_handle).await {
+                    app_state
+                        .logs
+                        .push("error", format!("Dictation stop failed: {err}"))
+                        .await;
+                }
+            });
+        }
+        dictation::ShortcutAction::AwaitSecondTap => {
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(dictation::DOUBLE_TAP_WINDOW).await;
+                let action = app_state.dictation.shortcut_tap_expired();
+                run_shortcut_action(app_handle, app_state, action);
+            });
+        }
+    }
+}
+
 fn show_main_window<R: tauri::Runtime>(app: &tauri::AppHandle<R>) {
     if let Some(window) = app.get_webview_window("main") {
         let _ = window.show();
@@ -724,6 +1025,7 @@ fn build_tray_menu<R: tauri::Runtime>(
         false,
         None::<String>,
     )?;
+    let undo_item = MenuItem::with_id(app, TRAY_UNDO, "Undo Last Dictation", true, None::<String>)?;
     let separator = PredefinedMenuItem::separator(app)?;
     let quit_item = MenuItem::with_id(app, TRAY_QUIT, "Quit OpenSTT", true, None::<String>)?;
 
@@ -736,6 +1038,7 @@ fn build_tray_menu<R: tauri::Runtime>(
     menu.append(&port_item)?;
     menu.append(&dictation_item)?;
     menu.append(&dictation_queue_item)?;
+    menu.append(&undo_item)?;
     menu.append(&separator)?;
     menu.append(&start_item)?;
     menu.append(&stop_item)?;
@@ -818,6 +1121,58 @@ async fn register_dictation_shortcut(
     Ok(())
 }
 
+async fn register_undo_shortcut(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    shortcut: Option<&DictationShortcut>,
+) -> Result<(), String> {
+    let mut current = state.undo_shortcut.lock().await;
+    let parsed = match shortcut {
+        Some(shortcut) if is_modifier_only_shortcut(shortcut) => {
+            return Err("The undo shortcut needs a key besides modifiers".to_string());
+        }
+        Some(shortcut) => Some(parse_dictation_shortcut(shortcut)?),
+        None => None,
+    };
+    if current.as_ref().map(|item| item.id()) == parsed.as_ref().map(|item| item.id()) {
+        return Ok(());
+    }
+    if let Some(existing) = current.take() {
+        let _ = app.global_shortcut().unregister(existing);
+    }
+    if let Some(parsed) = parsed {
+        app.global_shortcut()
+            .register(parsed)
+            .map_err(|err| format!("Failed to register undo shortcut: {err}"))?;
+        *current = Some(parsed);
+    }
+    Ok(())
+}
+
+/// Deletes the text of the last dictation from the focused app.
+async fn undo_last_dictation_inner(state: &AppState) -> Result<usize, String> {
+    let removed = state
+        .dictation
+        .edit_typed(typed_history::EditCommand::UndoLast)
+        .await;
+    match &removed {
+        Ok(0) => state.logs.push("info", "Nothing to undo").await,
+        Ok(count) => {
+            state
+                .logs
+                .push("info", format!("Removed {count} dictated characters"))
+                .await
+        }
+        Err(err) => {
+            state
+                .logs
+                .push("error", format!("Undo failed: {err}"))
+                .await
+        }
+    }
+    removed
+}
+
 fn now_millis() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -867,6 +1222,37 @@ fn is_supported_cloud_model(model_id: &str) -> bool {
     )
 }
 
+/// Whether `model_id` runs on the local whisper engine, the only one that
+/// implements the options in `TranscribeOptions::whisper_only_option`.
+fn runs_whisper(model_id: &str) -> bool {
+    models::model_entry(model_id).is_some_and(|entry| entry.engine == models::ModelEngine::Whisper)
+}
+
+/// Whether transcription uploads the audio rather than running locally.
+fn is_cloud_model(model_id: &str) -> bool {
+    model_id.starts_with("elevenlabs:") || model_id.starts_with("soniox:")
+}
+
+/// Lower-cased extension of an upload, for storing it under a new name.
+pub(crate) fn upload_extension(file_name: Option<&str>) -> String {
+    file_name
+        .and_then(|name| Path::new(name).extension())
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("bin")
+        .to_lowercase()
+}
+
+fn upload_mime(file_name: &str) -> &'static str {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str());
+    match extension {
+        Some("flac") => "audio/flac",
+        Some("wav") => "audio/wav",
+        _ => "application/octet-stream",
+    }
+}
+
 async fn start_dictation_inner(
     state: &AppState,
     app_handle: &tauri::AppHandle,
@@ -874,10 +1260,21 @@ async fn start_dictation_inner(
     let model_id = state.active_model_id.lock().await.clone();
     eprintln!("[lib] start_dictation_inner, model_id: {}", model_id);
 
-    if is_realtime_model(&model_id) {
+    let local_live = state.ui_settings.lock().await.local_live_dictation
+        && models::model_entry(&model_id).map(|entry| entry.engine)
+            == Some(models::ModelEngine::Whisper);
+    if local_live {
+        eprintln!("[lib] live dictation with local model, starting realtime");
+        let auto_stop = state.ui_settings.lock().await.auto_stop();
+        state
+            .dictation
+            .start_realtime("local", "", None, app_handle.clone(), auto_stop)
+            .await
+    } else if is_realtime_model(&model_id) {
         eprintln!("[lib] is realtime model, starting realtime");
         let settings = state.ui_settings.lock().await;
         let language = settings.language.clone();
+        let auto_stop = settings.auto_stop();
 
         // Choose API key based on model type
         let (api_key, provider) = if model_id.starts_with("soniox:") {
@@ -897,7 +1294,13 @@ async fn start_dictation_inner(
         
         match state
             .dictation
-            .start_realtime(provider, &api_key, Some(language), app_handle.clone())
+            .start_realtime(
+                provider,
+                &api_key,
+                Some(language),
+                app_handle.clone(),
+                auto_stop,
+            )
             .await
         {
             Ok(_) => {
@@ -911,7 +1314,13 @@ async fn start_dictation_inner(
         }
     } else {
         eprintln!("[lib] not realtime model, starting normal recording");
-        state.dictation.start_recording()
+        let (auto_stop, spool_dir) = {
+            let settings = state.ui_settings.lock().await;
+            (settings.auto_stop(), settings.spool_dir())
+        };
+        state
+            .dictation
+            .start_recording(app_handle, auto_stop, spool_dir)
     }
 }
 
@@ -923,7 +1332,7 @@ async fn stop_dictation_inner(
     let active_model = state.active_model_id.lock().await.clone();
     let model_id = normalize_model_id(&active_model);
 
-    if is_realtime_model(&model_id) {
+    if is_realtime_model(&model_id) || state.dictation.realtime_active().await {
         eprintln!("[lib] active model is realtime, calling stop_realtime");
         let soniox_warm_policy = if model_id.starts_with("soniox:") {
             let settings = state.ui_settings.lock().await;
@@ -940,8 +1349,21 @@ async fn stop_dictation_inner(
             dictation::SonioxWarmPolicy::Off
         };
 
-        state.dictation.stop_realtime(soniox_warm_policy).await?;
+        state
+            .dictation
+            .stop_realtime(soniox_warm_policy, app_handle)
+            .await?;
         eprintln!("[lib] stop_realtime completed");
+        let dropped = state.dictation.stream_dropped_samples();
+        if dropped > 0 {
+            state
+                .logs
+                .push(
+                    "error",
+                    format!("Realtime dictation dropped {dropped} audio samples"),
+                )
+                .await;
+        }
         // Update UI state
         state.dictation.emit_state(app_handle).await;
         {
@@ -996,6 +1418,10 @@ fn settings_path() -> PathBuf {
     openstt_dir().join("settings.json")
 }
 
+fn recordings_dir() -> PathBuf {
+    openstt_dir().join("recordings")
+}
+
 fn config_path() -> PathBuf {
     openstt_dir().join("state.json")
 }
@@ -1008,6 +1434,14 @@ fn logs_path() -> PathBuf {
     openstt_dir().join("logs").join("openstt.log")
 }
 
+fn history_dir() -> PathBuf {
+    openstt_dir().join("history")
+}
+
+fn transcript_cache_dir() -> PathBuf {
+    openstt_dir().join("cache").join("transcripts")
+}
+
 fn mlx_cache_dir() -> PathBuf {
     models_dir().join("mlx").join("cache")
 }
@@ -1565,8 +1999,10 @@ struct ElevenLabsResponse {
 async fn elevenlabs_transcribe(
     api_key: &str,
     audio_bytes: &[u8],
+    file_name: &str,
     elevenlabs_model: &str,
     language: Option<&str>,
+    keyterms: &[String],
 ) -> Result<String, TranscribeError> {
     let client = reqwest::Client::new();
 
@@ -1574,8 +2010,8 @@ async fn elevenlabs_transcribe(
         .part(
             "file",
             reqwest::multipart::Part::bytes(audio_bytes.to_vec())
-                .file_name("audio.wav")
-                .mime_str("audio/wav")
+                .file_name(file_name.to_string())
+                .mime_str(upload_mime(file_name))
                 .unwrap(),
         )
         .text("model_id", elevenlabs_model.to_string());
@@ -1583,6 +2019,9 @@ async fn elevenlabs_transcribe(
     if let Some(lang) = language {
         form = form.text("language_code", lang.to_string());
     }
+    for term in keyterms {
+        form = form.text("keyterms", term.clone());
+    }
 
     let response = client
         .post("https://api.elevenlabs.io/v1/speech-to-text")
@@ -1643,6 +2082,7 @@ async fn soniox_transcribe_async(
     file_name: &str,
     model: &str,
     language: Option<&str>,
+    terms: &[String],
 ) -> Result<String, TranscribeError> {
     let client = reqwest::Client::new();
     let auth = format!("Bearer {}", api_key);
@@ -1652,10 +2092,11 @@ async fn soniox_transcribe_async(
         "file",
         reqwest::multipart::Part::bytes(audio_bytes.to_vec())
             .file_name(file_name.to_string())
-            .mime_str("application/octet-stream")
+            .mime_str(upload_mime(file_name))
             .unwrap(),
     );
 
+    let upload_started = Instant::now();
     let upload_res = client
         .post("https://api.soniox.com/v1/files")
         .header("Authorization", &auth)
@@ -1677,6 +2118,12 @@ async fn soniox_transcribe_async(
         .await
         .map_err(|e| TranscribeError::internal(e.to_string()))?;
 
+    // Lets the WAV and FLAC upload times be compared.
+    eprintln!(
+        "[soniox] uploaded {file_name} ({} bytes) in {} ms",
+        audio_bytes.len(),
+        upload_started.elapsed().as_millis()
+    );
     let file_id = upload.id;
 
     // 2) Create transcription (async)
@@ -1687,6 +2134,9 @@ async fn soniox_transcribe_async(
     if let Some(lang) = language {
         body["language_hints"] = serde_json::json!([lang]);
     }
+    if !terms.is_empty() {
+        body["context"] = serde_json::json!({ "terms": terms });
+    }
 
     let create_res = client
         .post("https://api.soniox.com/v1/transcriptions")
@@ -1869,11 +2319,8 @@ pub(crate) async fn transcribe_bytes(
     model: Option<String>,
     file_name: Option<String>,
     file_bytes: Vec<u8>,
-    language: Option<String>,
-    task: Option<String>,
-    temperature: Option<f32>,
-    prompt: Option<String>,
-) -> Result<String, TranscribeError> {
+    options: TranscribeOptions,
+) -> Result<Transcript, TranscribeError> {
     if file_bytes.is_empty() {
         return Err(TranscribeError::bad_request("Empty audio payload"));
     }
@@ -1890,6 +2337,154 @@ pub(crate) async fn transcribe_bytes(
     };
     let model_id = normalize_model_id(&selected_model);
 
+    transcribe_cached(state, &model_id, file_bytes, &options, |file_bytes| {
+        transcribe_model(state, &model_id, file_name, file_bytes, &options)
+    })
+    .await
+}
+
+/// Answers from the transcript cache when it is enabled and holds this
+/// request, otherwise runs `transcribe` and stores its result.
+async fn transcribe_cached<F, Fut>(
+    state: &AppState,
+    model_id: &str,
+    file_bytes: Vec<u8>,
+    options: &TranscribeOptions,
+    transcribe: F,
+) -> Result<Transcript, TranscribeError>
+where
+    F: FnOnce(Vec<u8>) -> Fut,
+    Fut: std::future::Future<Output = Result<Transcript, TranscribeError>>,
+{
+    let cached = CachedRequest::open(state, model_id, &file_bytes, options).await;
+    let hit = match &cached {
+        Some(cached) => cached.get().await,
+        None => None,
+    };
+    if let Some(transcript) = hit {
+        state
+            .logs
+            .push("info", format!("Transcription cache hit model={model_id}"))
+            .await;
+        return Ok(transcript);
+    }
+
+    let transcript = transcribe(file_bytes).await?;
+    if let Some(cached) = &cached {
+        cached.put(&transcript);
+    }
+    Ok(transcript)
+}
+
+/// A transcript cache slot for one request. Only present when caching is
+/// enabled for it, either by the request or by the settings.
+struct CachedRequest {
+    cache: cache::TranscriptCache,
+    key: String,
+}
+
+impl CachedRequest {
+    async fn open(
+        state: &AppState,
+        model_id: &str,
+        file_bytes: &[u8],
+        options: &TranscribeOptions,
+    ) -> Option<Self> {
+        let enabled = match options.cache {
+            Some(enabled) => enabled,
+            None => state.ui_settings.lock().await.transcript_cache,
+        };
+        if !enabled {
+            return None;
+        }
+
+        // Hashing a long upload takes a while. block_in_place keeps the
+        // other tasks moving without copying the upload into a blocking task.
+        let key =
+            tokio::task::block_in_place(|| transcript_cache_key(model_id, file_bytes, options));
+        Some(Self {
+            cache: cache::TranscriptCache::new(transcript_cache_dir()),
+            key,
+        })
+    }
+
+    async fn get(&self) -> Option<Transcript> {
+        let cache = self.cache.clone();
+        let key = self.key.clone();
+        tokio::task::spawn_blocking(move || cache.get(&key))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Stores the transcript in the background; the response does not wait
+    /// for the write or the eviction scan.
+    fn put(&self, transcript: &Transcript) {
+        let cache = self.cache.clone();
+        let key = self.key.clone();
+        let transcript = transcript.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = cache.put(&key, &transcript) {
+                eprintln!("[cache] {err}");
+            }
+        });
+    }
+}
+
+/// Hashes the upload with everything that can change its transcript. Each
+/// option is written out field by field, so the key does not change when
+/// the types' `Debug` output does.
+fn transcript_cache_key(model_id: &str, file_bytes: &[u8], options: &TranscribeOptions) -> String {
+    let channels: &[u8] = match options.channels {
+        ChannelMode::Mix => b"mix",
+        ChannelMode::Split => b"split",
+    };
+    let vad: &[u8] = match options.vad {
+        VadMode::Off => b"off",
+        VadMode::Energy => b"energy",
+    };
+    let preprocess = &options.preprocess;
+    let normalize: &[u8] = match preprocess.normalize {
+        preprocess::Normalization::Off => b"off",
+        preprocess::Normalization::Peak => b"peak",
+        preprocess::Normalization::Lufs => b"lufs",
+    };
+    let range_end = options.range.end.unwrap_or(f64::INFINITY);
+    cache::key(&[
+        file_bytes,
+        model_id.as_bytes(),
+        options.language.as_deref().unwrap_or("").as_bytes(),
+        options.prompt.as_deref().unwrap_or("").as_bytes(),
+        options.vocabulary.join("\n").as_bytes(),
+        &options.temperature.unwrap_or(0.0).to_le_bytes(),
+        options.task.as_deref().unwrap_or("transcribe").as_bytes(),
+        channels,
+        vad,
+        &[preprocess.dc_removal as u8, preprocess.noise_gate as u8],
+        &preprocess.high_pass_hz.to_le_bytes(),
+        normalize,
+        &options.range.start.to_le_bytes(),
+        &range_end.to_le_bytes(),
+    ])
+}
+
+async fn transcribe_model(
+    state: &AppState,
+    model_id: &str,
+    file_name: Option<String>,
+    file_bytes: Vec<u8>,
+    options: &TranscribeOptions,
+) -> Result<Transcript, TranscribeError> {
+    let language = options.language.clone();
+
+    if let Some(option) = options.whisper_only_option() {
+        if is_supported_cloud_model(model_id) {
+            let message = format!("{option} is not supported by {model_id}");
+            state.logs.push("error", message.clone()).await;
+            return Err(TranscribeError::bad_request(message));
+        }
+    }
+
     // Handle ElevenLabs cloud models
     if model_id.starts_with("elevenlabs:") {
         let file_label = file_name.clone().unwrap_or_else(|| "unknown".to_string());
@@ -1913,17 +2508,29 @@ pub(crate) async fn transcribe_bytes(
 
         // Extract ElevenLabs model ID (e.g., "elevenlabs:scribe_v2" -> "scribe_v2")
         let elevenlabs_model = model_id.strip_prefix("elevenlabs:").unwrap_or("scribe_v2");
-        let text =
-            elevenlabs_transcribe(&api_key, &file_bytes, elevenlabs_model, language.as_deref())
-                .await?;
+        let upload_name = file_name.unwrap_or_else(|| "audio.wav".to_string());
+        let started = Instant::now();
+        let text = elevenlabs_transcribe(
+            &api_key,
+            &file_bytes,
+            &upload_name,
+            elevenlabs_model,
+            language.as_deref(),
+            &options.vocabulary,
+        )
+        .await?;
         state
             .logs
             .push(
                 "info",
-                format!("Transcription complete: {} chars", text.len()),
+                format!(
+                    "Transcription complete: {} chars in {} ms",
+                    text.len(),
+                    started.elapsed().as_millis()
+                ),
             )
             .await;
-        return Ok(text);
+        return Ok(Transcript::from_text(text));
     }
 
     // Handle Soniox cloud models
@@ -1961,25 +2568,44 @@ pub(crate) async fn transcribe_bytes(
             "stt-async-v4"
         };
 
+        let started = Instant::now();
         let text = soniox_transcribe_async(
             &api_key,
             &file_bytes,
             &upload_name,
             soniox_model,
             language.as_deref(),
+            &options.vocabulary,
         )
         .await?;
         state
             .logs
             .push(
                 "info",
-                format!("Transcription complete: {} chars", text.len()),
+                format!(
+                    "Transcription complete: {} chars in {} ms",
+                    text.len(),
+                    started.elapsed().as_millis()
+                ),
             )
             .await;
-        return Ok(text);
+        return Ok(Transcript::from_text(text));
     }
 
-    let entry = match models::model_entry(&model_id) {
+    transcribe_local(state, model_id, file_name, None, file_bytes, options).await
+}
+
+/// Transcribes with a local whisper or MLX model. Shared by the Tauri
+/// commands and the OpenAI-compatible HTTP endpoint.
+async fn transcribe_local(
+    state: &AppState,
+    model_id: &str,
+    file_name: Option<String>,
+    content_type: Option<String>,
+    file_bytes: Vec<u8>,
+    options: &TranscribeOptions,
+) -> Result<Transcript, TranscribeError> {
+    let entry = match models::model_entry(model_id) {
         Some(entry) => entry,
         None => {
             let message = format!("Unknown model: {model_id}");
@@ -1987,6 +2613,13 @@ pub(crate) async fn transcribe_bytes(
             return Err(TranscribeError::bad_request(message));
         }
     };
+    if let Some(option) = options.whisper_only_option() {
+        if entry.engine == models::ModelEngine::Mlx {
+            let message = format!("{option} is not supported by {model_id}");
+            state.logs.push("error", message.clone()).await;
+            return Err(TranscribeError::bad_request(message));
+        }
+    }
     let file_label = file_name.clone().unwrap_or_else(|| "unknown".to_string());
     let size_label = file_bytes.len().to_string();
     state
@@ -1997,20 +2630,21 @@ pub(crate) async fn transcribe_bytes(
         )
         .await;
 
-    let extension = file_name
-        .as_ref()
-        .and_then(|name| Path::new(name).extension())
-        .and_then(|value| value.to_str())
-        .unwrap_or("bin");
-    let temp_path =
-        std::env::temp_dir().join(format!("openstt-upload-{}.{}", now_millis(), extension));
-    if let Err(err) = tokio::fs::write(&temp_path, &file_bytes).await {
-        let message = format!("Failed to write temp file: {err}");
-        state.logs.push("error", message.clone()).await;
-        return Err(TranscribeError::internal(message));
-    }
-
     if entry.engine == models::ModelEngine::Mlx {
+        // The MLX sidecar reads the upload by path.
+        let extension = file_name
+            .as_ref()
+            .and_then(|name| Path::new(name).extension())
+            .and_then(|value| value.to_str())
+            .unwrap_or("bin");
+        let temp_path =
+            std::env::temp_dir().join(format!("openstt-upload-{}.{}", now_millis(), extension));
+        if let Err(err) = tokio::fs::write(&temp_path, &file_bytes).await {
+            let message = format!("Failed to write temp file: {err}");
+            state.logs.push("error", message.clone()).await;
+            return Err(TranscribeError::internal(message));
+        }
+
         let dir = match resolve_models_dir(state).await {
             Ok(dir) => dir,
             Err(err) => {
@@ -2019,7 +2653,7 @@ pub(crate) async fn transcribe_bytes(
             }
         };
         let marker =
-            models::model_path(&dir, &model_id).ok_or_else(|| format!("Unknown model: {model_id}"));
+            models::model_path(&dir, model_id).ok_or_else(|| format!("Unknown model: {model_id}"));
         let marker = match marker {
             Ok(path) => path,
             Err(err) => {
@@ -2029,7 +2663,7 @@ pub(crate) async fn transcribe_bytes(
         };
         if !marker.exists() {
             if std::env::var("OPENSTT_AUTO_DOWNLOAD").ok().as_deref() == Some("1") {
-                if let Err(err) = download_model_inner(state, &model_id).await {
+                if let Err(err) = download_model_inner(state, model_id).await {
                     state.logs.push("error", err.clone()).await;
                     return Err(TranscribeError::bad_request(err));
                 }
@@ -2049,10 +2683,10 @@ pub(crate) async fn transcribe_bytes(
         };
 
         let _ = std::fs::remove_file(&temp_path);
-        return Ok(text);
+        return Ok(Transcript::from_text(text));
     }
 
-    let model_path = match ensure_whisper_model_path(state, &model_id).await {
+    let model_path = match ensure_whisper_model_path(state, model_id).await {
         Ok(path) => path,
         Err(err) => {
             state.logs.push("error", err.clone()).await;
@@ -2075,15 +2709,34 @@ pub(crate) async fn transcribe_bytes(
         }
     };
 
-    let language_value = language.clone();
-    let prompt_value = prompt.clone();
-    let translate = task.as_deref() == Some("translate");
-    let temperature_value = temperature.unwrap_or(0.0);
+    let options_value = options.clone();
     let model_path_value = model_path.clone();
-    let temp_path_value = temp_path.clone();
 
     let result = tokio::task::spawn_blocking(move || {
-        let audio = audio::load_and_resample(&temp_path_value)?;
+        let file_name = file_name.as_deref();
+        let content_type = content_type.as_deref();
+        let range = options_value.range;
+        let mut tracks = match options_value.channels {
+            ChannelMode::Mix => vec![audio::load_and_resample_bytes(
+                file_bytes,
+                file_name,
+                content_type,
+                range,
+                audio::ResampleMode::Exact,
+            )?],
+            ChannelMode::Split => audio::load_and_resample_channels_bytes(
+                file_bytes,
+                file_name,
+                content_type,
+                range,
+                audio::ResampleMode::Exact,
+            )?,
+        };
+        if options_value.preprocess.is_enabled() {
+            for track in &mut tracks {
+                preprocess::apply(track, audio::TARGET_SAMPLE_RATE, &options_value.preprocess);
+            }
+        }
         let context = if let Some(context) = cached_ctx {
             context
         } else {
@@ -2108,36 +2761,31 @@ pub(crate) async fn transcribe_bytes(
                 .map_err(|err| format!("Failed to create whisper state: {err:?}"))?
         };
 
-        let params = build_whisper_params(
-            language_value.as_deref(),
-            prompt_value.as_deref(),
-            translate,
-            temperature_value,
-        );
-        wstate
-            .full(params, &audio)
-            .map_err(|err| format!("Transcription failed: {err:?}"))?;
-        let segments = wstate
-            .full_n_segments()
-            .map_err(|err| format!("Failed to read segments: {err:?}"))?;
-        let mut text = String::new();
-        for index in 0..segments {
-            let segment = wstate
-                .full_get_segment_text(index)
-                .map_err(|err| format!("Failed to read segment text: {err:?}"))?;
-            text.push_str(&segment);
-        }
-        Ok::<(String, Arc<WhisperContext>, WhisperState), String>((
-            text.trim().to_string(),
-            context,
-            wstate,
-        ))
+        let mut per_channel = Vec::with_capacity(tracks.len());
+        for track in &tracks {
+            let mut segments = transcribe_track(&mut wstate, track, &options_value)?;
+            for segment in &mut segments {
+                segment.start += range.start;
+                segment.end += range.start;
+            }
+            per_channel.push(segments);
+        }
+        let transcript = match options_value.channels {
+            ChannelMode::Mix => {
+                let segments = per_channel.pop().unwrap_or_default();
+                Transcript {
+                    text: join_segments(&segments),
+                    segments,
+                    channels: None,
+                }
+            }
+            ChannelMode::Split => merge_channel_segments(per_channel),
+        };
+        Ok::<(Transcript, Arc<WhisperContext>, WhisperState), String>((transcript, context, wstate))
     })
     .await;
 
-    let _ = std::fs::remove_file(&temp_path);
-
-    let (text, context, wstate) = match result {
+    let (transcript, context, wstate) = match result {
         Ok(Ok(output)) => output,
         Ok(Err(err)) => {
             state.logs.push("error", err.clone()).await;
@@ -2153,22 +2801,148 @@ pub(crate) async fn transcribe_bytes(
     {
         let mut cached = state.cached_context.lock().await;
         *cached = Some(CachedWhisperContext {
-            model_id: model_id.clone(),
+            model_id: model_id.to_string(),
             context,
             state: wstate,
         });
     }
 
-    Ok(text)
+    Ok(transcript)
 }
 
-async fn resolve_models_dir(state: &AppState) -> Result<PathBuf, String> {
-    state
-        .models_dir
-        .lock()
-        .await
-        .clone()
-        .ok_or_else(|| "Models directory not initialized".to_string())
+/// Transcribes one 16 kHz mono track, cutting silence first when VAD is on.
+/// Segment times always refer to the uncut track.
+fn transcribe_track(
+    wstate: &mut WhisperState,
+    samples: &[f32],
+    options: &TranscribeOptions,
+) -> Result<Vec<TranscriptSegment>, String> {
+    if options.vad == VadMode::Off {
+        return run_whisper(wstate, samples, options);
+    }
+
+    let regions = audio::speech_regions(samples, audio::TARGET_SAMPLE_RATE);
+    let (speech, map) = audio::strip_silence(samples, audio::TARGET_SAMPLE_RATE, &regions);
+    let rate = audio::TARGET_SAMPLE_RATE as f32;
+    eprintln!(
+        "[vad] kept {:.1}s of speech from {:.1}s in {} regions",
+        map.speech_samples() as f32 / rate,
+        samples.len() as f32 / rate,
+        regions.len()
+    );
+    if speech.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut segments = run_whisper(wstate, &speech, options)?;
+    for segment in &mut segments {
+        segment.start = map.source_seconds(segment.start);
+        segment.end = map.source_seconds(segment.end);
+    }
+    Ok(segments)
+}
+
+/// Runs whisper over 16 kHz mono samples and returns its timed segments.
+fn run_whisper(
+    wstate: &mut WhisperState,
+    samples: &[f32],
+    options: &TranscribeOptions,
+) -> Result<Vec<TranscriptSegment>, String> {
+    let prompt = vocabulary::whisper_prompt(options.prompt.as_deref(), &options.vocabulary);
+    let params = build_whisper_params(
+        options.language.as_deref(),
+        prompt.as_deref(),
+        options.task.as_deref() == Some("translate"),
+        options.temperature.unwrap_or(0.0),
+    );
+    wstate
+        .full(params, samples)
+        .map_err(|err| format!("Transcription failed: {err:?}"))?;
+    let count = wstate
+        .full_n_segments()
+        .map_err(|err| format!("Failed to read segments: {err:?}"))?;
+    let mut segments = Vec::with_capacity(count.max(0) as usize);
+    for index in 0..count {
+        let text = wstate
+            .full_get_segment_text(index)
+            .map_err(|err| format!("Failed to read segment text: {err:?}"))?;
+        // whisper reports timestamps in centiseconds.
+        let t0 = wstate
+            .full_get_segment_t0(index)
+            .map_err(|err| format!("Failed to read segment timestamp: {err:?}"))?;
+        let t1 = wstate
+            .full_get_segment_t1(index)
+            .map_err(|err| format!("Failed to read segment timestamp: {err:?}"))?;
+        segments.push(TranscriptSegment {
+            channel: None,
+            start: t0 as f64 / 100.0,
+            end: t1 as f64 / 100.0,
+            text,
+        });
+    }
+    Ok(segments)
+}
+
+fn join_segments(segments: &[TranscriptSegment]) -> String {
+    segments
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Builds a split-channel transcript: one text per channel, plus every
+/// segment merged in time order with a `[channel N]` label on each line.
+fn merge_channel_segments(per_channel: Vec<Vec<TranscriptSegment>>) -> Transcript {
+    let channels = per_channel
+        .iter()
+        .enumerate()
+        .map(|(channel, segments)| ChannelTranscript {
+            channel,
+            text: join_segments(segments),
+        })
+        .collect();
+
+    let mut segments: Vec<TranscriptSegment> = per_channel
+        .into_iter()
+        .enumerate()
+        .flat_map(|(channel, segments)| {
+            segments.into_iter().map(move |segment| TranscriptSegment {
+                channel: Some(channel),
+                ..segment
+            })
+        })
+        .filter(|segment| !segment.text.trim().is_empty())
+        .collect();
+    segments.sort_by(|a, b| a.start.total_cmp(&b.start).then(a.channel.cmp(&b.channel)));
+
+    let text = segments
+        .iter()
+        .map(|segment| {
+            format!(
+                "[channel {}] {}",
+                segment.channel.unwrap_or_default(),
+                segment.text.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Transcript {
+        text,
+        segments,
+        channels: Some(channels),
+    }
+}
+
+async fn resolve_models_dir(state: &AppState) -> Result<PathBuf, String> {
+    state
+        .models_dir
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "Models directory not initialized".to_string())
 }
 
 fn hf_cache_dir_for(model_id: &str) -> PathBuf {
@@ -2225,6 +2999,9 @@ async fn start_server_inner(app_state: AppState, port: u16) -> Result<ServerStat
     let router = Router::new()
         .route("/health", get(health))
         .route("/v1/audio/transcriptions", post(transcribe))
+        .route("/v1/history", get(history_search))
+        .route("/v1/history/:id", get(history_get).delete(history_delete))
+        .route("/v1/history/:id/transcribe", post(history_retranscribe))
         .with_state(app_state.clone());
 
     let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
@@ -2331,6 +3108,218 @@ fn check_input_monitoring() -> bool {
     true
 }
 
+#[tauri::command]
+async fn list_orphaned_recordings(
+    state: TauriState<'_, AppState>,
+) -> Result<Vec<recording::OrphanedRecording>, String> {
+    Ok(state.orphaned_recordings.lock().await.clone())
+}
+
+/// Forgets an orphaned recording and returns its path. Only names found at
+/// launch are accepted, so the path always stays inside `recordings_dir()`.
+async fn take_orphaned_recording(state: &AppState, name: &str) -> Result<PathBuf, String> {
+    let mut orphans = state.orphaned_recordings.lock().await;
+    let index = orphans
+        .iter()
+        .position(|orphan| orphan.name == name)
+        .ok_or_else(|| format!("Unknown recording: {name}"))?;
+    orphans.remove(index);
+    Ok(recordings_dir().join(name))
+}
+
+#[tauri::command]
+async fn transcribe_orphaned_recording(
+    state: TauriState<'_, AppState>,
+    name: String,
+) -> Result<String, String> {
+    let path = recordings_dir().join(&name);
+    let duration_secs = state
+        .orphaned_recordings
+        .lock()
+        .await
+        .iter()
+        .find(|orphan| orphan.name == name)
+        .map(|orphan| orphan.duration_secs)
+        .ok_or_else(|| format!("Unknown recording: {name}"))?;
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+    let kept_audio = history_keeps_audio(&state, history::Source::Dictation)
+        .await
+        .then(|| ("wav".to_string(), bytes.clone()));
+    let model = normalize_model_id(&state.active_model_id.lock().await);
+    let options = dictation::recording_transcribe_options(&state, Some(&model)).await;
+    let started = Instant::now();
+    let transcript = transcribe_bytes(&state, None, Some(name.clone()), bytes, options)
+        .await
+        .map_err(|err| err.message)?;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    take_orphaned_recording(&state, &name).await?;
+    let _ = tokio::fs::remove_file(&path).await;
+    state
+        .logs
+        .push("info", format!("Recovered recording {name} transcribed"))
+        .await;
+    let text = state.dictation.finish_text(&transcript.text);
+    record_history(
+        &state,
+        history::Record {
+            source: history::Source::Dictation,
+            model,
+            duration_ms: Some((duration_secs * 1000.0) as u64),
+            latency_ms,
+            text: text.clone(),
+            audio: kept_audio,
+        },
+    )
+    .await;
+    Ok(text)
+}
+
+#[tauri::command]
+async fn discard_orphaned_recording(
+    state: TauriState<'_, AppState>,
+    name: String,
+) -> Result<(), String> {
+    let path = take_orphaned_recording(&state, &name).await?;
+    tokio::fs::remove_file(&path)
+        .await
+        .map_err(|err| format!("Failed to remove {}: {err}", path.display()))
+}
+
+/// Whether transcriptions from `source` should keep their audio in the
+/// history.
+pub(crate) async fn history_keeps_audio(state: &AppState, source: history::Source) -> bool {
+    let settings = state.ui_settings.lock().await;
+    settings.records_history(source) && settings.history_keep_audio
+}
+
+/// Adds a finished transcription to the history when its source is recorded.
+pub(crate) async fn record_history(state: &AppState, record: history::Record) {
+    let recorded = state
+        .ui_settings
+        .lock()
+        .await
+        .records_history(record.source);
+    if !recorded || record.text.is_empty() {
+        return;
+    }
+    let now_ms = now_millis();
+    let result = with_history(state, move |history| history.add(record, now_ms)).await;
+    if let Err(err) = result {
+        state.logs.push("error", err.to_string()).await;
+    }
+}
+
+/// Runs `operation` on the history store in a blocking task, since the
+/// store reads and rewrites its files.
+async fn with_history<T: Send + 'static>(
+    state: &AppState,
+    operation: impl FnOnce(&mut history::HistoryStore) -> Result<T, history::HistoryError>
+        + Send
+        + 'static,
+) -> Result<T, history::HistoryError> {
+    let mut history = state.history.clone().lock_owned().await;
+    tokio::task::spawn_blocking(move || operation(&mut history))
+        .await
+        .map_err(|err| history::HistoryError::Storage(format!("History task failed: {err}")))?
+}
+
+/// Transcribes the kept audio of a history entry again, with `model` or the
+/// active model, and stores the new text in the entry.
+async fn retranscribe_history_inner(
+    state: &AppState,
+    id: u64,
+    model: Option<String>,
+) -> Result<history::HistoryEntry, TranscribeError> {
+    let (entry, path) = with_history(state, move |history| {
+        let entry = history.get(id)?;
+        let path = history.audio_path(&entry);
+        Ok((entry, path))
+    })
+    .await?;
+    let path = path.ok_or_else(|| {
+        TranscribeError::bad_request(format!("No audio kept for history entry {id}"))
+    })?;
+    let bytes = tokio::fs::read(&path).await.map_err(|err| {
+        TranscribeError::internal(format!("Failed to read {}: {err}", path.display()))
+    })?;
+    let model_id = match model.as_deref().map(str::trim) {
+        Some(model) if !model.is_empty() => normalize_model_id(model),
+        _ => state.active_model_id.lock().await.clone(),
+    };
+    // Recordings go through the same options and text rules as dictation.
+    let from_recording = entry.source != history::Source::Gateway;
+    let options = if from_recording {
+        dictation::recording_transcribe_options(state, Some(&model_id)).await
+    } else {
+        TranscribeOptions::default()
+    };
+    let started = Instant::now();
+    let transcript =
+        transcribe_bytes(state, Some(model_id.clone()), entry.audio, bytes, options).await?;
+    let text = if from_recording {
+        state.dictation.finish_text(&transcript.text)
+    } else {
+        transcript.text
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+    with_history(state, move |history| {
+        history.update(id, model_id, latency_ms, text)
+    })
+    .await
+    .map_err(TranscribeError::from)
+}
+
+#[tauri::command]
+async fn search_history(
+    state: TauriState<'_, AppState>,
+    query: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<history::HistoryEntry>, String> {
+    let limit = limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+    let query = query.unwrap_or_default();
+    with_history(&state, move |history| Ok(history.search(&query, limit)))
+        .await
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn copy_history_entry(state: TauriState<'_, AppState>, id: u64) -> Result<(), String> {
+    let entry = with_history(&state, move |history| history.get(id))
+        .await
+        .map_err(|err| err.to_string())?;
+    dictation::write_clipboard(&entry.text)
+}
+
+#[tauri::command]
+async fn retranscribe_history_entry(
+    state: TauriState<'_, AppState>,
+    id: u64,
+    model_id: Option<String>,
+) -> Result<history::HistoryEntry, String> {
+    retranscribe_history_inner(&state, id, model_id)
+        .await
+        .map_err(|err| err.message)
+}
+
+#[tauri::command]
+async fn delete_history_entry(state: TauriState<'_, AppState>, id: u64) -> Result<(), String> {
+    with_history(&state, move |history| history.delete(id))
+        .await
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn get_capture_stats() -> recording::CaptureCounters {
+    recording::capture_counters()
+}
+
+#[tauri::command]
+fn list_input_devices() -> Result<Vec<recording::InputDeviceInfo>, String> {
+    recording::list_input_devices()
+}
+
 #[tauri::command]
 fn check_all_permissions() -> Result<PermissionStatus, String> {
     Ok(PermissionStatus {
@@ -2471,8 +3460,28 @@ async fn set_ui_settings(
     state: TauriState<'_, AppState>,
     settings: UiSettings,
 ) -> Result<UiSettings, String> {
+    state.dictation.configure_vocabulary(&settings.vocabulary)?;
+    state
+        .dictation
+        .configure_spoken_commands(settings.spoken_commands());
+    state
+        .dictation
+        .configure_stream_chunk_ms(settings.stream_chunk_ms);
     let previous = state.ui_settings.lock().await.clone();
     *state.ui_settings.lock().await = settings.clone();
+    if previous.input_device != settings.input_device
+        || previous.pre_roll_ms != settings.pre_roll_ms
+    {
+        if let Err(err) = state.dictation.configure_input(
+            Some(settings.input_device.clone()),
+            settings.pre_roll_ms.min(MAX_PRE_ROLL_MS),
+        ) {
+            state
+                .logs
+                .push("error", format!("Failed to start pre-roll: {err}"))
+                .await;
+        }
+    }
     if let Some(path) = state.settings_path.lock().await.clone() {
         save_ui_settings(&path, &settings)?;
     }
@@ -2488,6 +3497,18 @@ async fn set_ui_settings(
                 )
                 .await;
         }
+        if let Err(err) =
+            register_undo_shortcut(&app_handle, &state, settings.undo_shortcut.as_ref()).await
+        {
+            state.logs.push("error", err).await;
+        }
+    }
+
+    // A warm session still has the old terms.
+    if previous.vocabulary.terms() != settings.vocabulary.terms()
+        && !state.dictation.realtime_active().await
+    {
+        state.dictation.close_realtime_session().await;
     }
 
     // Soniox warm-connection lifecycle
@@ -2537,19 +3558,28 @@ async fn transcribe_audio(
     state: TauriState<'_, AppState>,
     request: TranscribeAudioRequest,
 ) -> Result<String, String> {
+    let options = parse_transcribe_options(
+        request.channels.as_deref(),
+        request.vad.as_deref(),
+        request.preprocess.as_deref(),
+        request.cache.as_deref(),
+        request.start_time,
+        request.end_time,
+    )?;
+    let options = TranscribeOptions {
+        language: request.language,
+        ..options
+    };
     let result = transcribe_bytes(
         &state,
         request.model_id,
         request.file_name,
         request.bytes,
-        request.language,
-        None,
-        None,
-        None,
+        options,
     )
     .await;
     match result {
-        Ok(text) => Ok(text),
+        Ok(transcript) => Ok(transcript.text),
         Err(err) => Err(err.message),
     }
 }
@@ -2567,6 +3597,8 @@ struct SonioxRealtimeStatusResponse {
     connected: bool,
     warm_until_ms: Option<u64>,
     warm_forever: bool,
+    /// Audio samples lost in the current or last realtime session.
+    dropped_samples: u64,
 }
 
 #[tauri::command]
@@ -2577,6 +3609,7 @@ async fn get_soniox_realtime_status(
         connected: state.dictation.soniox_realtime_connected().await,
         warm_until_ms: state.dictation.soniox_warm_until_ms().await,
         warm_forever: state.dictation.soniox_warm_forever().await,
+        dropped_samples: state.dictation.stream_dropped_samples(),
     })
 }
 
@@ -2589,6 +3622,7 @@ async fn start_dictation(state: TauriState<'_, AppState>) -> Result<(), String>
         .clone()
         .ok_or_else(|| "App handle not available".to_string())?;
     start_dictation_inner(&state, &app_handle).await?;
+    state.dictation.sync_shortcut(true);
     state.dictation.emit_state(&app_handle).await;
     let mut tray_state = state.dictation_tray_state.lock().await;
     tray_state.state = "listening".to_string();
@@ -2609,12 +3643,22 @@ async fn stop_dictation(state: TauriState<'_, AppState>) -> Result<(), String> {
         .clone()
         .ok_or_else(|| "App handle not available".to_string())?;
     stop_dictation_inner(&state, &app_handle).await?;
+    state.dictation.sync_shortcut(false);
     Ok(())
 }
 
 #[tauri::command]
-async fn start_playground_recording(state: TauriState<'_, AppState>) -> Result<(), String> {
-    state.dictation.start_playground()
+async fn undo_last_dictation(state: TauriState<'_, AppState>) -> Result<usize, String> {
+    undo_last_dictation_inner(&state).await
+}
+
+#[tauri::command]
+async fn start_playground_recording(
+    app: tauri::AppHandle,
+    state: TauriState<'_, AppState>,
+) -> Result<(), String> {
+    let spool_dir = state.ui_settings.lock().await.spool_dir();
+    state.dictation.start_playground(&app, spool_dir)
 }
 
 #[tauri::command]
@@ -3027,6 +4071,22 @@ async fn preload_whisper_model(state: &AppState) {
     }
 }
 
+/// The cached context of the active whisper model, loaded first if needed.
+/// Callers create their own `WhisperState` on it.
+async fn active_whisper_context(state: &AppState) -> Result<Arc<WhisperContext>, String> {
+    let model_id = state.active_model_id.lock().await.clone();
+    ensure_whisper_model_path(state, &model_id).await?;
+    preload_whisper_model(state).await;
+    state
+        .cached_context
+        .lock()
+        .await
+        .as_ref()
+        .filter(|cached| cached.model_id == model_id)
+        .map(|cached| cached.context.clone())
+        .ok_or_else(|| format!("Model {model_id} is not loaded"))
+}
+
 async fn preload_mlx_model(state: &AppState) {
     let model_id = state.active_model_id.lock().await.clone();
     let entry = match models::model_entry(&model_id) {
@@ -3106,8 +4166,64 @@ async fn health() -> Json<HealthResponse> {
     Json(HealthResponse { status: "ok" })
 }
 
+#[derive(Deserialize)]
+struct HistoryQuery {
+    q: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct RetranscribeRequest {
+    model: Option<String>,
+}
+
+async fn history_search(
+    AxumState(state): AxumState<AppState>,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+    let query = query.q.unwrap_or_default();
+    match with_history(&state, move |history| Ok(history.search(&query, limit))).await {
+        Ok(entries) => Json(entries).into_response(),
+        Err(err) => TranscribeError::from(err).into_response(),
+    }
+}
+
+async fn history_get(
+    AxumState(state): AxumState<AppState>,
+    AxumPath(id): AxumPath<u64>,
+) -> Response {
+    match with_history(&state, move |history| history.get(id)).await {
+        Ok(entry) => Json(entry).into_response(),
+        Err(err) => TranscribeError::from(err).into_response(),
+    }
+}
+
+async fn history_delete(
+    AxumState(state): AxumState<AppState>,
+    AxumPath(id): AxumPath<u64>,
+) -> Response {
+    match with_history(&state, move |history| history.delete(id)).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => TranscribeError::from(err).into_response(),
+    }
+}
+
+async fn history_retranscribe(
+    AxumState(state): AxumState<AppState>,
+    AxumPath(id): AxumPath<u64>,
+    request: Option<Json<RetranscribeRequest>>,
+) -> Response {
+    let model = request.and_then(|Json(request)| request.model);
+    match retranscribe_history_inner(&state, id, model).await {
+        Ok(entry) => Json(entry).into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
 async fn transcribe(AxumState(state): AxumState<AppState>, mut multipart: Multipart) -> Response {
     let mut file_name: Option<String> = None;
+    let mut content_type: Option<String> = None;
     let mut file_bytes: Option<Vec<u8>> = None;
     let mut model: Option<String> = None;
     let mut response_format: Option<String> = None;
@@ -3115,7 +4231,12 @@ async fn transcribe(AxumState(state): AxumState<AppState>, mut multipart: Multip
     let mut task: Option<String> = None;
     let mut temperature: Option<f32> = None;
     let mut prompt: Option<String> = None;
-    let mut size_bytes: Option<usize> = None;
+    let mut channels: Option<String> = None;
+    let mut vad: Option<String> = None;
+    let mut preprocess: Option<String> = None;
+    let mut cache: Option<String> = None;
+    let mut start_time: Option<String> = None;
+    let mut end_time: Option<String> = None;
 
     loop {
         let next = match multipart.next_field().await {
@@ -3133,9 +4254,9 @@ async fn transcribe(AxumState(state): AxumState<AppState>, mut multipart: Multip
         match next.name().unwrap_or("") {
             "file" => {
                 file_name = next.file_name().map(|value| value.to_string());
+                content_type = next.content_type().map(|value| value.to_string());
                 match next.bytes().await {
                     Ok(bytes) => {
-                        size_bytes = Some(bytes.len());
                         file_bytes = Some(bytes.to_vec());
                     }
                     Err(err) => {
@@ -3177,6 +4298,36 @@ async fn transcribe(AxumState(state): AxumState<AppState>, mut multipart: Multip
                     prompt = Some(text);
                 }
             }
+            "channels" => {
+                if let Ok(text) = next.text().await {
+                    channels = Some(text);
+                }
+            }
+            "vad" => {
+                if let Ok(text) = next.text().await {
+                    vad = Some(text);
+                }
+            }
+            "preprocess" => {
+                if let Ok(text) = next.text().await {
+                    preprocess = Some(text);
+                }
+            }
+            "cache" => {
+                if let Ok(text) = next.text().await {
+                    cache = Some(text);
+                }
+            }
+            "start_time" => {
+                if let Ok(text) = next.text().await {
+                    start_time = Some(text);
+                }
+            }
+            "end_time" => {
+                if let Ok(text) = next.text().await {
+                    end_time = Some(text);
+                }
+            }
             _ => {
                 let _ = next.bytes().await;
             }
@@ -3202,194 +4353,81 @@ async fn transcribe(AxumState(state): AxumState<AppState>, mut multipart: Multip
         state.active_model_id.lock().await.clone()
     };
     let model_id = normalize_model_id(&selected_model);
-    let entry = match models::model_entry(&model_id) {
-        Some(entry) => entry,
-        None => {
-            let message = format!("Unknown model: {model_id}");
-            state.logs.push("error", message.clone()).await;
-            return (StatusCode::BAD_REQUEST, message).into_response();
-        }
+    let parse_seconds = |name: &str, value: Option<String>| match value {
+        Some(text) => text
+            .trim()
+            .parse::<f64>()
+            .map(Some)
+            .map_err(|_| format!("Invalid {name}: {text}")),
+        None => Ok(None),
     };
-    let file_label = file_name.clone().unwrap_or_else(|| "unknown".to_string());
-    let size_label = size_bytes
-        .map(|bytes| bytes.to_string())
-        .unwrap_or_else(|| "unknown".to_string());
-    state
-        .logs
-        .push(
-            "info",
-            format!("Transcription request model={model_id} file={file_label} bytes={size_label}"),
+    let options = match parse_seconds("start_time", start_time).and_then(|start_time| {
+        parse_transcribe_options(
+            channels.as_deref(),
+            vad.as_deref(),
+            preprocess.as_deref(),
+            cache.as_deref(),
+            start_time,
+            parse_seconds("end_time", end_time)?,
         )
-        .await;
-
-    let extension = file_name
-        .as_ref()
-        .and_then(|name| Path::new(name).extension())
-        .and_then(|value| value.to_str())
-        .unwrap_or("bin");
-    let temp_path =
-        std::env::temp_dir().join(format!("openstt-upload-{}.{}", now_millis(), extension));
-    if let Err(err) = tokio::fs::write(&temp_path, &file_bytes).await {
-        let message = format!("Failed to write temp file: {err}");
-        state.logs.push("error", message.clone()).await;
-        return (StatusCode::INTERNAL_SERVER_ERROR, message).into_response();
-    }
-
-    if entry.engine == models::ModelEngine::Mlx {
-        let dir = match resolve_models_dir(&state).await {
-            Ok(dir) => dir,
-            Err(err) => {
-                state.logs.push("error", err.clone()).await;
-                return (StatusCode::INTERNAL_SERVER_ERROR, err).into_response();
-            }
-        };
-        let marker =
-            models::model_path(&dir, &model_id).ok_or_else(|| format!("Unknown model: {model_id}"));
-        let marker = match marker {
-            Ok(path) => path,
-            Err(err) => {
-                state.logs.push("error", err.clone()).await;
-                return (StatusCode::BAD_REQUEST, err).into_response();
-            }
-        };
-        if !marker.exists() {
-            if std::env::var("OPENSTT_AUTO_DOWNLOAD").ok().as_deref() == Some("1") {
-                if let Err(err) = download_model_inner(&state, &model_id).await {
-                    state.logs.push("error", err.clone()).await;
-                    return (StatusCode::BAD_REQUEST, err).into_response();
-                }
-            } else {
-                let err = format!("Model {model_id} not prepared");
-                state.logs.push("error", err.clone()).await;
-                return (StatusCode::BAD_REQUEST, err).into_response();
-            }
-        }
-
-        let text = match mlx_transcribe(&state, entry.download_url, &temp_path).await {
-            Ok(text) => text,
-            Err(err) => {
-                state.logs.push("error", err.clone()).await;
-                return (StatusCode::INTERNAL_SERVER_ERROR, err).into_response();
-            }
-        };
-
-        let _ = std::fs::remove_file(&temp_path);
-        if response_format.as_deref() == Some("text") {
-            return text.into_response();
-        }
-        return Json(TranscriptionResponse { text }).into_response();
-    }
-
-    let model_path = match ensure_whisper_model_path(&state, &model_id).await {
-        Ok(path) => path,
+    }) {
+        Ok(options) => TranscribeOptions {
+            language,
+            task,
+            temperature,
+            prompt,
+            ..options
+        },
         Err(err) => {
             state.logs.push("error", err.clone()).await;
             return (StatusCode::BAD_REQUEST, err).into_response();
         }
     };
 
-    let cached = {
-        let mut guard = state.cached_context.lock().await;
-        guard.take()
-    };
-    let (cached_ctx, cached_state) = match cached {
-        Some(c) if c.model_id == model_id => (Some(c.context), Some(c.state)),
-        other => {
-            let mut guard = state.cached_context.lock().await;
-            *guard = other;
-            (None, None)
-        }
-    };
-
-    let language_value = language.clone();
-    let prompt_value = prompt.clone();
-    let translate = task.as_deref() == Some("translate");
-    let temperature_value = temperature.unwrap_or(0.0);
-    let model_path_value = model_path.clone();
-    let temp_path_value = temp_path.clone();
-
-    let result = tokio::task::spawn_blocking(move || {
-        let audio = audio::load_and_resample(&temp_path_value)?;
-        let context = if let Some(context) = cached_ctx {
-            context
-        } else {
-            let mut params = WhisperContextParameters::default();
-            params.use_gpu(true);
-            params.flash_attn(true);
-            let context = WhisperContext::new_with_params(
-                model_path_value
-                    .to_str()
-                    .ok_or_else(|| "Invalid model path".to_string())?,
-                params,
-            )
-            .map_err(|err| format!("Failed to load model: {err:?}"))?;
-            Arc::new(context)
-        };
-
-        let mut wstate = if let Some(wstate) = cached_state {
-            wstate
-        } else {
-            context
-                .create_state()
-                .map_err(|err| format!("Failed to create whisper state: {err:?}"))?
-        };
-
-        let params = build_whisper_params(
-            language_value.as_deref(),
-            prompt_value.as_deref(),
-            translate,
-            temperature_value,
-        );
-        wstate
-            .full(params, &audio)
-            .map_err(|err| format!("Transcription failed: {err:?}"))?;
-        let segments = wstate
-            .full_n_segments()
-            .map_err(|err| format!("Failed to read segments: {err:?}"))?;
-        let mut text = String::new();
-        for index in 0..segments {
-            let segment = wstate
-                .full_get_segment_text(index)
-                .map_err(|err| format!("Failed to read segment text: {err:?}"))?;
-            text.push_str(&segment);
-        }
-        Ok::<(String, Arc<WhisperContext>, WhisperState), String>((
-            text.trim().to_string(),
-            context,
-            wstate,
-        ))
+    let kept_audio = history_keeps_audio(&state, history::Source::Gateway)
+        .await
+        .then(|| (upload_extension(file_name.as_deref()), file_bytes.clone()));
+    let started = Instant::now();
+    let transcribed = transcribe_cached(&state, &model_id, file_bytes, &options, |file_bytes| {
+        transcribe_local(
+            &state,
+            &model_id,
+            file_name,
+            content_type,
+            file_bytes,
+            &options,
+        )
     })
     .await;
-
-    let _ = std::fs::remove_file(&temp_path);
-
-    let (text, context, wstate) = match result {
-        Ok(Ok(output)) => output,
-        Ok(Err(err)) => {
-            state.logs.push("error", err.clone()).await;
-            return (StatusCode::INTERNAL_SERVER_ERROR, err).into_response();
-        }
-        Err(err) => {
-            let message = format!("Transcription task failed: {err}");
-            state.logs.push("error", message.clone()).await;
-            return (StatusCode::INTERNAL_SERVER_ERROR, message).into_response();
-        }
+    let transcript = match transcribed {
+        Ok(transcript) => transcript,
+        Err(err) => return err.into_response(),
     };
-
-    {
-        let mut cached = state.cached_context.lock().await;
-        *cached = Some(CachedWhisperContext {
-            model_id: model_id.clone(),
-            context,
-            state: wstate,
-        });
-    }
+    record_history(
+        &state,
+        history::Record {
+            source: history::Source::Gateway,
+            model: model_id,
+            duration_ms: None,
+            latency_ms: started.elapsed().as_millis() as u64,
+            text: transcript.text.clone(),
+            audio: kept_audio,
+        },
+    )
+    .await;
 
     if response_format.as_deref() == Some("text") {
-        return text.into_response();
+        return transcript.text.into_response();
     }
 
-    Json(TranscriptionResponse { text }).into_response()
+    let verbose = response_format.as_deref() == Some("verbose_json");
+    let segments = (verbose || transcript.channels.is_some()).then_some(transcript.segments);
+    Json(TranscriptionResponse {
+        text: transcript.text,
+        channels: transcript.channels,
+        segments,
+    })
+    .into_response()
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -3407,54 +4445,29 @@ pub fn run() {
                     tauri_plugin_global_shortcut::Builder::new()
                         .with_handler(|app, shortcut, event| {
                             let state = app.state::<AppState>();
+                            let undo = state.undo_shortcut.blocking_lock().clone();
+                            if undo.as_ref().map(|item| item.id()) == Some(shortcut.id()) {
+                                if matches!(event.state(), ShortcutState::Pressed) {
+                                    let app_state = (*state).clone();
+                                    tauri::async_runtime::spawn(async move {
+                                        let _ = undo_last_dictation_inner(&app_state).await;
+                                    });
+                                }
+                                return;
+                            }
                             let current = state.dictation_shortcut.blocking_lock().clone();
                             if current.as_ref().map(|item| item.id()) != Some(shortcut.id()) {
                                 return;
                             }
-                            let app_state = (*state).clone();
-                            let app_handle = app.clone();
-                            match event.state() {
-                                ShortcutState::Pressed => {
-                                    tauri::async_runtime::spawn(async move {
-                                        if let Err(err) =
-                                            start_dictation_inner(&app_state, &app_handle).await
-                                        {
-                                            app_state
-                                                .logs
-                                                .push(
-                                                    "error",
-                                                    format!("Dictation start failed: {err}"),
-                                                )
-                                                .await;
-                                            return;
-                                        }
-                                        app_state.dictation.emit_state(&app_handle).await;
-                                        let mut tray = app_state.dictation_tray_state.lock().await;
-                                        tray.state = "listening".to_string();
-                                        tray.queue_len = app_state.dictation.queue_len().await;
-                                        tray.phase_started = Some(Instant::now());
-                                        drop(tray);
-                                        refresh_tray(&app_state).await;
-                                        recompute_and_emit_app_status(&app_state).await;
-                                    });
-                                }
+                            let mode = state.ui_settings.blocking_lock().dictation_shortcut.mode;
+                            let action = match event.state() {
+                                ShortcutState::Pressed => state.dictation.shortcut_pressed(mode),
                                 ShortcutState::Released => {
                                     eprintln!("[lib] global shortcut: key released");
-                                    tauri::async_runtime::spawn(async move {
-                                        if let Err(err) =
-                                            stop_dictation_inner(&app_state, &app_handle).await
-                                        {
-                                            app_state
-                                                .logs
-                                                .push(
-                                                    "error",
-                                                    format!("Dictation stop failed: {err}"),
-                                                )
-                                                .await;
-                                        }
-                                    });
+                                    state.dictation.shortcut_released(mode)
                                 }
-                            }
+                            };
+                            run_shortcut_action(app.clone(), (*state).clone(), action);
                         })
                         .build(),
                 )?;
@@ -3477,6 +4490,29 @@ pub fn run() {
                 *path_guard = Some(settings_path.clone());
             }
             let settings = load_ui_settings(&settings_path);
+            {
+                // Nothing is recording yet, so every spool file is an orphan.
+                let orphans = recording::recover_spools(&recordings_dir());
+                if !orphans.is_empty() {
+                    eprintln!("[lib] found {} unfinished recordings", orphans.len());
+                }
+                *state.orphaned_recordings.blocking_lock() = orphans;
+            }
+            if let Err(err) = state.dictation.configure_input(
+                Some(settings.input_device.clone()),
+                settings.pre_roll_ms.min(MAX_PRE_ROLL_MS),
+            ) {
+                eprintln!("[lib] failed to start pre-roll: {err}");
+            }
+            if let Err(err) = state.dictation.configure_vocabulary(&settings.vocabulary) {
+                eprintln!("[lib] ignoring replacement rules: {err}");
+            }
+            state
+                .dictation
+                .configure_spoken_commands(settings.spoken_commands());
+            state
+                .dictation
+                .configure_stream_chunk_ms(settings.stream_chunk_ms);
             {
                 let mut settings_guard = state.ui_settings.blocking_lock();
                 *settings_guard = settings;
@@ -3484,11 +4520,13 @@ pub fn run() {
             {
                 let app_handle = app.handle().clone();
                 let state_clone = (*state).clone();
-                let dictation_shortcut = state_clone
-                    .ui_settings
-                    .blocking_lock()
-                    .dictation_shortcut
-                    .clone();
+                let (dictation_shortcut, undo_shortcut) = {
+                    let settings = state_clone.ui_settings.blocking_lock();
+                    (
+                        settings.dictation_shortcut.clone(),
+                        settings.undo_shortcut.clone(),
+                    )
+                };
                 tauri::async_runtime::spawn(async move {
                     if let Err(err) =
                         register_dictation_shortcut(&app_handle, &state_clone, &dictation_shortcut)
@@ -3502,6 +4540,12 @@ pub fn run() {
                             )
                             .await;
                     }
+                    if let Err(err) =
+                        register_undo_shortcut(&app_handle, &state_clone, undo_shortcut.as_ref())
+                            .await
+                    {
+                        state_clone.logs.push("error", err).await;
+                    }
                 });
             }
 
@@ -3654,6 +4698,13 @@ pub fn run() {
                                 let _ = stop_server_inner((*state).clone()).await;
                             });
                         }
+                        TRAY_UNDO => {
+                            let app_handle = app.clone();
+                            tauri::async_runtime::spawn(async move {
+                                let state = app_handle.state::<AppState>();
+                                let _ = undo_last_dictation_inner(&state).await;
+                            });
+                        }
                         TRAY_SETTINGS => open_page(app, "settings"),
                         TRAY_LOGS => open_page(app, "logs"),
                         TRAY_QUIT => app.exit(0),
@@ -3762,6 +4813,7 @@ pub fn run() {
             paste_clipboard,
             start_dictation,
             stop_dictation,
+            undo_last_dictation,
             start_playground_recording,
             stop_playground_recording,
             get_dictation_state,
@@ -3777,6 +4829,15 @@ pub fn run() {
             check_legacy_models,
             clean_legacy_models,
             check_all_permissions,
+            list_input_devices,
+            get_capture_stats,
+            list_orphaned_recordings,
+            transcribe_orphaned_recording,
+            discard_orphaned_recording,
+            search_history,
+            copy_history_entry,
+            retranscribe_history_entry,
+            delete_history_entry,
             open_permission_settings,
             restart_app,
             test_elevenlabs_api_key,
@@ -3785,3 +4846,116 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: f64, text: &str) -> TranscriptSegment {
+        TranscriptSegment {
+            channel: None,
+            start,
+            end: start + 1.0,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn channel_mode_parses_known_values() {
+        assert_eq!(ChannelMode::parse(""), Ok(ChannelMode::Mix));
+        assert_eq!(ChannelMode::parse(" Mono "), Ok(ChannelMode::Mix));
+        assert_eq!(ChannelMode::parse("SPLIT"), Ok(ChannelMode::Split));
+        assert!(ChannelMode::parse("left").is_err());
+    }
+
+    #[test]
+    fn vad_mode_parses_known_values() {
+        assert_eq!(VadMode::parse(""), Ok(VadMode::Off));
+        assert_eq!(VadMode::parse("false"), Ok(VadMode::Off));
+        assert_eq!(VadMode::parse(" Energy"), Ok(VadMode::Energy));
+        assert_eq!(VadMode::parse("auto"), Ok(VadMode::Energy));
+        assert!(VadMode::parse("silero").is_err());
+    }
+
+    #[test]
+    fn transcript_cache_key_covers_every_option() {
+        let base = TranscribeOptions::default();
+        let key = |options: &TranscribeOptions| transcript_cache_key("base", b"audio", options);
+        assert_eq!(key(&base), key(&base.clone()));
+        assert_ne!(key(&base), transcript_cache_key("small", b"audio", &base));
+
+        let changed = [
+            TranscribeOptions {
+                channels: ChannelMode::Split,
+                ..base.clone()
+            },
+            TranscribeOptions {
+                vad: VadMode::Energy,
+                ..base.clone()
+            },
+            TranscribeOptions {
+                preprocess: preprocess::PreprocessOptions {
+                    noise_gate: true,
+                    ..base.preprocess
+                },
+                ..base.clone()
+            },
+            TranscribeOptions {
+                preprocess: preprocess::PreprocessOptions {
+                    normalize: preprocess::Normalization::Peak,
+                    ..base.preprocess
+                },
+                ..base.clone()
+            },
+            TranscribeOptions {
+                range: audio::TimeRange {
+                    start: 0.0,
+                    end: Some(5.0),
+                },
+                ..base.clone()
+            },
+            TranscribeOptions {
+                vocabulary: vec!["Tauri".to_string()],
+                ..base.clone()
+            },
+        ];
+        for options in &changed {
+            assert_ne!(key(&base), key(options));
+        }
+    }
+
+    #[test]
+    fn merge_channel_segments_interleaves_by_start_time() {
+        let transcript = merge_channel_segments(vec![
+            vec![
+                segment(0.0, " Hello."),
+                segment(4.0, " Bye."),
+                segment(5.0, "  "),
+            ],
+            vec![segment(2.0, " Hi there."), segment(4.0, " See you.")],
+        ]);
+        assert_eq!(
+            transcript.text,
+            "[channel 0] Hello.\n[channel 1] Hi there.\n[channel 0] Bye.\n[channel 1] See you."
+        );
+        let labels: Vec<Option<usize>> = transcript
+            .segments
+            .iter()
+            .map(|segment| segment.channel)
+            .collect();
+        assert_eq!(labels, [Some(0), Some(1), Some(0), Some(1)]);
+        let channels: Vec<(usize, String)> = transcript
+            .channels
+            .unwrap()
+            .into_iter()
+            .map(|channel| (channel.channel, channel.text))
+            .collect();
+        assert_eq!(
+            channels,
+            [
+                (0, "Hello. Bye.".to_string()),
+                (1, "Hi there. See you.".to_string())
+            ]
+        );
+    }
+}
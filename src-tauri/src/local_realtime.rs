@@ -0,0 +1,293 @@
+use crate::dictation::TypeAction;
+use crate::{audio, AppState, TranscribeOptions};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use whisper_rs::WhisperState;
+
+/// Whisper decodes 16 kHz audio only.
+pub const SAMPLE_RATES: &[u32] = &[audio::TARGET_SAMPLE_RATE];
+
+/// How often the utterance in progress is decoded again for a draft.
+const DECODE_INTERVAL: Duration = Duration::from_millis(800);
+/// A pause this long after speech ends the utterance and commits it.
+const COMMIT_SILENCE_SECS: f32 = 0.7;
+/// Long utterances are committed here; whisper sees at most 30 s at a time.
+const MAX_UTTERANCE_SECS: f32 = 25.0;
+/// Audio kept from before the first word so its onset is not clipped.
+const LEAD_IN_SECS: f32 = 0.3;
+
+fn secs_to_samples(secs: f32) -> usize {
+    (secs * audio::TARGET_SAMPLE_RATE as f32) as usize
+}
+
+/// Live dictation with a local whisper model. Re-decodes the utterance in
+/// progress as audio arrives and commits it once the speaker pauses.
+pub struct RealtimeSession {
+    audio_tx: StdMutex<Option<mpsc::UnboundedSender<Vec<i16>>>>,
+    transcript_rx: Arc<Mutex<mpsc::Receiver<TypeAction>>>,
+}
+
+impl RealtimeSession {
+    /// Decodes with the cached context of the active whisper model, loading
+    /// it first if needed.
+    pub async fn start(app_state: &AppState) -> Result<Self, String> {
+        let context = crate::active_whisper_context(app_state).await?;
+        let options = crate::dictation::recording_transcribe_options(app_state).await;
+        let wstate = tokio::task::spawn_blocking(move || {
+            context
+                .create_state()
+                .map_err(|err| format!("Failed to create whisper state: {err:?}"))
+        })
+        .await
+        .map_err(|err| format!("Whisper state task failed: {err}"))??;
+        eprintln!("[local_realtime] session started");
+
+        let (audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (transcript_tx, transcript_rx) = mpsc::channel(100);
+        tokio::spawn(decode_loop(wstate, options, audio_rx, transcript_tx));
+
+        Ok(Self {
+            audio_tx: StdMutex::new(Some(audio_tx)),
+            transcript_rx: Arc::new(Mutex::new(transcript_rx)),
+        })
+    }
+
+    pub async fn send_audio(&self, samples: Vec<i16>) -> Result<(), String> {
+        self.audio_tx
+            .lock()
+            .unwrap()
+            .as_ref()
+            .ok_or_else(|| "Session stopped".to_string())?
+            .send(samples)
+            .map_err(|e| format!("Failed to send audio: {e}"))
+    }
+
+    /// Ends the input; the last utterance is still decoded and committed.
+    pub async fn stop(&self) -> Result<(), String> {
+        self.audio_tx.lock().unwrap().take();
+        Ok(())
+    }
+
+    pub fn clone_transcript_rx(&self) -> Arc<Mutex<mpsc::Receiver<TypeAction>>> {
+        Arc::clone(&self.transcript_rx)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Step {
+    Wait,
+    Draft,
+    Commit,
+}
+
+/// The utterance being dictated, and whether it is due for a decode.
+#[derive(Default)]
+struct Utterance {
+    samples: Vec<f32>,
+    vad: audio::LiveVad,
+    silent_secs: f32,
+    heard_speech: bool,
+    decoded_len: usize,
+}
+
+impl Utterance {
+    fn push(&mut self, chunk: &[i16]) {
+        if chunk.is_empty() {
+            return;
+        }
+        let start = self.samples.len();
+        self.samples
+            .extend(chunk.iter().map(|&sample| sample as f32 / 32768.0));
+        let added = &self.samples[start..];
+        let rms = (added.iter().map(|s| s * s).sum::<f32>() / added.len() as f32).sqrt();
+        let secs = added.len() as f32 / audio::TARGET_SAMPLE_RATE as f32;
+        self.silent_secs = self.vad.push(rms, secs);
+        if self.silent_secs == 0.0 {
+            self.heard_speech = true;
+        }
+    }
+
+    fn step(&mut self, input_done: bool) -> Step {
+        if !self.heard_speech {
+            // Nothing said yet: decoding silence only invites hallucinations.
+            let excess = self
+                .samples
+                .len()
+                .saturating_sub(secs_to_samples(LEAD_IN_SECS));
+            self.samples.drain(..excess);
+            return Step::Wait;
+        }
+        if input_done
+            || self.silent_secs >= COMMIT_SILENCE_SECS
+            || self.samples.len() >= secs_to_samples(MAX_UTTERANCE_SECS)
+        {
+            return Step::Commit;
+        }
+        if self.samples.len() == self.decoded_len {
+            return Step::Wait;
+        }
+        self.decoded_len = self.samples.len();
+        Step::Draft
+    }
+
+    /// Takes the committed audio and starts the next utterance.
+    fn take(&mut self) -> Vec<f32> {
+        self.heard_speech = false;
+        self.decoded_len = 0;
+        std::mem::take(&mut self.samples)
+    }
+}
+
+async fn decode(
+    mut wstate: WhisperState,
+    samples: Vec<f32>,
+    options: Arc<TranscribeOptions>,
+) -> Result<(WhisperState, Result<String, String>), String> {
+    tokio::task::spawn_blocking(move || {
+        let text = crate::run_whisper(&mut wstate, &samples, &options).map(|segments| {
+            segments
+                .into_iter()
+                .map(|segment| segment.text)
+                .collect::<String>()
+        });
+        (wstate, text)
+    })
+    .await
+    .map_err(|err| format!("Decode task failed: {err}"))
+}
+
+async fn decode_loop(
+    mut wstate: WhisperState,
+    options: TranscribeOptions,
+    mut audio_rx: mpsc::UnboundedReceiver<Vec<i16>>,
+    transcript_tx: mpsc::Sender<TypeAction>,
+) {
+    let options = Arc::new(options);
+    let mut utterance = Utterance::default();
+    let mut typed_any = false;
+    let mut input_done = false;
+    while !input_done {
+        let tick = tokio::time::sleep(DECODE_INTERVAL);
+        tokio::pin!(tick);
+        loop {
+            tokio::select! {
+                chunk = audio_rx.recv() => match chunk {
+                    Some(chunk) => utterance.push(&chunk),
+                    None => {
+                        input_done = true;
+                        break;
+                    }
+                },
+                _ = &mut tick => break,
+            }
+        }
+
+        let commit = match utterance.step(input_done) {
+            Step::Wait => continue,
+            Step::Draft => false,
+            Step::Commit => true,
+        };
+        let samples = if commit {
+            utterance.take()
+        } else {
+            utterance.samples.clone()
+        };
+        let text = match decode(wstate, samples, options.clone()).await {
+            Ok((state, text)) => {
+                wstate = state;
+                text
+            }
+            Err(err) => {
+                eprintln!("[local_realtime] {err}");
+                break;
+            }
+        };
+        let text = match text {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("[local_realtime] {err}");
+                continue;
+            }
+        };
+
+        let action = if commit {
+            if text.trim().is_empty() {
+                continue;
+            }
+            // Whisper starts words with their space, which separates commits.
+            let text = if typed_any {
+                text.trim_end()
+            } else {
+                text.trim()
+            };
+            typed_any = true;
+            TypeAction::CommitDraft(text.to_string())
+        } else {
+            TypeAction::SetDraft(text.trim().to_string())
+        };
+        if transcript_tx.send(action).await.is_err() {
+            break;
+        }
+    }
+    eprintln!("[local_realtime] decode loop ended");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(secs: f32) -> Vec<i16> {
+        (0..secs_to_samples(secs))
+            .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+            .collect()
+    }
+
+    fn silence(secs: f32) -> Vec<i16> {
+        vec![0; secs_to_samples(secs)]
+    }
+
+    /// Feeds audio in 20 ms chunks, as the capture stream does.
+    fn feed(utterance: &mut Utterance, samples: &[i16]) {
+        for chunk in samples.chunks(secs_to_samples(0.02)) {
+            utterance.push(chunk);
+        }
+    }
+
+    #[test]
+    fn leading_silence_is_trimmed_and_never_decoded() {
+        let mut utterance = Utterance::default();
+        feed(&mut utterance, &silence(3.0));
+        assert_eq!(utterance.step(false), Step::Wait);
+        assert_eq!(utterance.samples.len(), secs_to_samples(LEAD_IN_SECS));
+        assert_eq!(utterance.step(true), Step::Wait);
+    }
+
+    #[test]
+    fn speech_drafts_until_a_pause_commits_it() {
+        let mut utterance = Utterance::default();
+        let spoken = [tone(1.0), silence(0.3), silence(0.5)];
+        feed(&mut utterance, &spoken[0]);
+        assert_eq!(utterance.step(false), Step::Draft);
+        // No new audio, nothing to redo.
+        assert_eq!(utterance.step(false), Step::Wait);
+        feed(&mut utterance, &spoken[1]);
+        assert_eq!(utterance.step(false), Step::Draft);
+        feed(&mut utterance, &spoken[2]);
+        assert_eq!(utterance.step(false), Step::Commit);
+        assert_eq!(utterance.take().len(), spoken.concat().len());
+        assert_eq!(utterance.step(false), Step::Wait);
+    }
+
+    #[test]
+    fn long_speech_is_committed_in_pieces() {
+        let mut utterance = Utterance::default();
+        let second = [tone(0.8), silence(0.2)].concat();
+        for _ in 1..MAX_UTTERANCE_SECS as usize {
+            feed(&mut utterance, &second);
+            assert_eq!(utterance.step(false), Step::Draft);
+        }
+        feed(&mut utterance, &second);
+        assert_eq!(utterance.step(false), Step::Commit);
+    }
+}
@@ -0,0 +1,461 @@
+use std::f32::consts::PI;
+
+use realfft::num_complex::Complex;
+use realfft::RealFftPlanner;
+use serde::{Deserialize, Serialize};
+
+/// Loudness target for `Normalization::Lufs`, a comfortable speech level.
+const TARGET_LUFS: f32 = -20.0;
+/// Peak target for `Normalization::Peak` and ceiling after LUFS gain (-1 dBFS).
+const TARGET_PEAK: f32 = 0.89;
+/// Never amplify by more than this, so near-silent input is not blown up.
+const MAX_GAIN_DB: f32 = 30.0;
+/// Cutoff used when the high-pass is turned on without a frequency.
+pub const DEFAULT_HIGH_PASS_HZ: f32 = 80.0;
+
+const GATE_FFT_SIZE: usize = 512;
+const GATE_HOP: usize = GATE_FFT_SIZE / 2;
+/// Bins below this multiple of the noise profile are treated as noise.
+const GATE_THRESHOLD: f32 = 3.0;
+/// Gain applied to gated bins (-20 dB).
+const GATE_FLOOR: f32 = 0.1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Normalization {
+    #[default]
+    Off,
+    Peak,
+    Lufs,
+}
+
+/// Optional cleanup applied to 16 kHz mono audio before inference. Stages
+/// run in field order.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PreprocessOptions {
+    pub dc_removal: bool,
+    /// High-pass cutoff in Hz, `0` to disable.
+    pub high_pass_hz: f32,
+    pub normalize: Normalization,
+    pub noise_gate: bool,
+}
+
+impl PreprocessOptions {
+    /// Every stage on, tuned for laptop microphones.
+    pub fn recommended() -> Self {
+        Self {
+            dc_removal: true,
+            high_pass_hz: DEFAULT_HIGH_PASS_HZ,
+            normalize: Normalization::Lufs,
+            noise_gate: true,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// Parses a request value: `off`, `on` (recommended), or a comma list of
+    /// `dc`, `highpass[:hz]`, `peak`, `lufs` and `gate`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim().to_lowercase();
+        match value.as_str() {
+            "" | "off" | "false" | "0" | "none" => return Ok(Self::default()),
+            "on" | "true" | "1" => return Ok(Self::recommended()),
+            _ => {}
+        }
+
+        let mut options = Self::default();
+        for stage in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (name, arg) = match stage.split_once(':') {
+                Some((name, arg)) => (name, Some(arg)),
+                None => (stage, None),
+            };
+            match (name, arg) {
+                ("dc", None) => options.dc_removal = true,
+                ("highpass", None) => options.high_pass_hz = DEFAULT_HIGH_PASS_HZ,
+                ("highpass", Some(hz)) => {
+                    options.high_pass_hz = hz
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|hz| *hz > 0.0)
+                        .ok_or_else(|| format!("Invalid high-pass cutoff: {hz}"))?;
+                }
+                ("peak", None) => options.normalize = Normalization::Peak,
+                ("lufs", None) => options.normalize = Normalization::Lufs,
+                ("gate", None) => options.noise_gate = true,
+                _ => return Err(format!("Unsupported preprocess stage: {stage}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Runs the enabled stages over mono samples in place.
+pub fn apply(samples: &mut [f32], sample_rate: u32, options: &PreprocessOptions) {
+    if samples.is_empty() {
+        return;
+    }
+    if options.dc_removal {
+        remove_dc(samples);
+    }
+    if options.high_pass_hz > 0.0 {
+        high_pass(samples, sample_rate, options.high_pass_hz);
+    }
+    match options.normalize {
+        Normalization::Off => {}
+        Normalization::Peak => normalize_peak(samples),
+        Normalization::Lufs => normalize_lufs(samples, sample_rate),
+    }
+    if options.noise_gate {
+        noise_gate(samples);
+    }
+}
+
+pub fn remove_dc(samples: &mut [f32]) {
+    let mean = samples.iter().map(|&s| s as f64).sum::<f64>() / samples.len().max(1) as f64;
+    for sample in samples.iter_mut() {
+        *sample -= mean as f32;
+    }
+}
+
+/// Second-order Butterworth high-pass.
+pub fn high_pass(samples: &mut [f32], sample_rate: u32, cutoff_hz: f32) {
+    let cutoff = cutoff_hz.min(sample_rate as f32 * 0.45);
+    Biquad::high_pass(sample_rate, cutoff, std::f32::consts::FRAC_1_SQRT_2).process(samples);
+}
+
+pub fn normalize_peak(samples: &mut [f32]) {
+    let peak = peak(samples);
+    if peak > 0.0 {
+        apply_gain(samples, TARGET_PEAK / peak);
+    }
+}
+
+/// Scales to `TARGET_LUFS`, but never past a -1 dBFS peak.
+pub fn normalize_lufs(samples: &mut [f32], sample_rate: u32) {
+    let Some(loudness) = integrated_loudness(samples, sample_rate) else {
+        return;
+    };
+    let gain = 10f32.powf((TARGET_LUFS - loudness) / 20.0);
+    let peak = peak(samples);
+    let gain = if peak * gain > TARGET_PEAK {
+        TARGET_PEAK / peak
+    } else {
+        gain
+    };
+    apply_gain(samples, gain);
+}
+
+/// ITU-R BS.1770 integrated loudness of a mono signal in LUFS, or `None`
+/// when every block falls below the absolute gate.
+pub fn integrated_loudness(samples: &[f32], sample_rate: u32) -> Option<f32> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut weighted = samples.to_vec();
+    Biquad::k_shelf(sample_rate).process(&mut weighted);
+    Biquad::high_pass(sample_rate, 38.0, 0.5).process(&mut weighted);
+
+    let block = (sample_rate as usize * 400 / 1000).min(weighted.len());
+    let step = (block / 4).max(1);
+    let powers: Vec<f64> = (0..=weighted.len() - block)
+        .step_by(step)
+        .map(|start| {
+            let chunk = &weighted[start..start + block];
+            chunk.iter().map(|&s| (s as f64) * (s as f64)).sum::<f64>() / block as f64
+        })
+        .collect();
+
+    let loudness = |power: f64| -0.691 + 10.0 * power.log10();
+    let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+
+    let absolute: Vec<f64> = powers
+        .into_iter()
+        .filter(|&p| p > 0.0 && loudness(p) > -70.0)
+        .collect();
+    if absolute.is_empty() {
+        return None;
+    }
+    let relative_gate = loudness(mean(&absolute)) - 10.0;
+    let gated: Vec<f64> = absolute
+        .into_iter()
+        .filter(|&p| loudness(p) > relative_gate)
+        .collect();
+    Some(loudness(mean(&gated)) as f32)
+}
+
+/// Attenuates spectral bins that stay near the noise profile, estimated
+/// from the quietest tenth of the frames.
+pub fn noise_gate(samples: &mut [f32]) {
+    if samples.len() < GATE_FFT_SIZE {
+        return;
+    }
+
+    let mut planner = RealFftPlanner::<f32>::new();
+    let forward = planner.plan_fft_forward(GATE_FFT_SIZE);
+    let inverse = planner.plan_fft_inverse(GATE_FFT_SIZE);
+    // Periodic Hann windows at 50% overlap sum to one.
+    let window: Vec<f32> = (0..GATE_FFT_SIZE)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / GATE_FFT_SIZE as f32).cos())
+        .collect();
+
+    // Pad so every sample is covered by two frames.
+    let mut padded = vec![0.0f32; GATE_HOP];
+    padded.extend_from_slice(samples);
+    let frames = padded.len().div_ceil(GATE_HOP);
+    padded.resize((frames + 1) * GATE_HOP, 0.0);
+
+    let mut spectra = Vec::with_capacity(frames);
+    let mut input = forward.make_input_vec();
+    for frame in 0..frames {
+        let start = frame * GATE_HOP;
+        for (i, value) in input.iter_mut().enumerate() {
+            *value = padded[start + i] * window[i];
+        }
+        let mut spectrum = forward.make_output_vec();
+        if forward.process(&mut input, &mut spectrum).is_err() {
+            return;
+        }
+        spectra.push(spectrum);
+    }
+
+    let energy = |spectrum: &[Complex<f32>]| spectrum.iter().map(|c| c.norm_sqr()).sum::<f32>();
+    // The first and last frames overlap the zero padding and would drag the
+    // profile down.
+    let mut order: Vec<usize> = if spectra.len() > 2 {
+        (1..spectra.len() - 1).collect()
+    } else {
+        (0..spectra.len()).collect()
+    };
+    order.sort_by(|&a, &b| energy(&spectra[a]).total_cmp(&energy(&spectra[b])));
+    let quiet = &order[..(order.len() / 10).max(1)];
+    let bins = spectra[0].len();
+    let mut profile = vec![0.0f32; bins];
+    for &frame in quiet {
+        for (bin, value) in spectra[frame].iter().enumerate() {
+            profile[bin] += value.norm() / quiet.len() as f32;
+        }
+    }
+
+    let mut output = vec![0.0f32; padded.len()];
+    let mut frame_out = inverse.make_output_vec();
+    for (frame, spectrum) in spectra.iter_mut().enumerate() {
+        for (bin, value) in spectrum.iter_mut().enumerate() {
+            if value.norm() < profile[bin] * GATE_THRESHOLD {
+                *value *= GATE_FLOOR;
+            }
+        }
+        // The inverse transform needs purely real DC and Nyquist bins.
+        spectrum[0].im = 0.0;
+        spectrum[bins - 1].im = 0.0;
+        if inverse.process(spectrum, &mut frame_out).is_err() {
+            return;
+        }
+        let start = frame * GATE_HOP;
+        for (i, value) in frame_out.iter().enumerate() {
+            output[start + i] += value / GATE_FFT_SIZE as f32;
+        }
+    }
+
+    samples.copy_from_slice(&output[GATE_HOP..GATE_HOP + samples.len()]);
+}
+
+fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()))
+}
+
+fn apply_gain(samples: &mut [f32], gain: f32) {
+    let gain = gain.min(10f32.powf(MAX_GAIN_DB / 20.0));
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+}
+
+/// Direct form I biquad with coefficients normalized by `a0`.
+struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+}
+
+impl Biquad {
+    fn new(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self {
+            b: [b[0] / a[0], b[1] / a[0], b[2] / a[0]],
+            a: [a[1] / a[0], a[2] / a[0]],
+        }
+    }
+
+    fn high_pass(sample_rate: u32, cutoff_hz: f32, q: f32) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz / sample_rate as f32;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        Self::new(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// First stage of the BS.1770 K-weighting: +4 dB shelf above ~1.5 kHz.
+    fn k_shelf(sample_rate: u32) -> Self {
+        let gain = 10f32.powf(4.0 / 40.0);
+        let w0 = 2.0 * PI * 1500.0 / sample_rate as f32;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+        let root = 2.0 * gain.sqrt() * alpha;
+        Self::new(
+            [
+                gain * ((gain + 1.0) + (gain - 1.0) * cos + root),
+                -2.0 * gain * ((gain - 1.0) + (gain + 1.0) * cos),
+                gain * ((gain + 1.0) + (gain - 1.0) * cos - root),
+            ],
+            [
+                (gain + 1.0) - (gain - 1.0) * cos + root,
+                2.0 * ((gain - 1.0) - (gain + 1.0) * cos),
+                (gain + 1.0) - (gain - 1.0) * cos - root,
+            ],
+        )
+    }
+
+    fn process(&self, samples: &mut [f32]) {
+        let (mut x1, mut x2, mut y1, mut y2) = (0.0f32, 0.0f32, 0.0f32, 0.0f32);
+        for sample in samples.iter_mut() {
+            let x = *sample;
+            let y =
+                self.b[0] * x + self.b[1] * x1 + self.b[2] * x2 - self.a[0] * y1 - self.a[1] * y2;
+            x2 = x1;
+            x1 = x;
+            y2 = y1;
+            y1 = y;
+            *sample = y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+
+    fn sine(len: usize, freq: f32, amplitude: f32) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * PI * freq * i as f32 / RATE as f32).sin() * amplitude)
+            .collect()
+    }
+
+    /// Deterministic white noise in [-amplitude, amplitude].
+    fn noise(len: usize, amplitude: f32) -> Vec<f32> {
+        let mut state = 0x2545_f491_u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state as f32 / u32::MAX as f32 * 2.0 - 1.0) * amplitude
+            })
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn remove_dc_centres_signal() {
+        let mut samples: Vec<f32> = sine(RATE as usize, 440.0, 0.3)
+            .into_iter()
+            .map(|s| s + 0.25)
+            .collect();
+        remove_dc(&mut samples);
+        let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+        assert!(mean.abs() < 1e-4, "mean {mean}");
+    }
+
+    #[test]
+    fn high_pass_removes_hum_and_keeps_voice_band() {
+        let len = RATE as usize;
+        let mut hum = sine(len, 50.0, 0.5);
+        high_pass(&mut hum, RATE, DEFAULT_HIGH_PASS_HZ);
+        // Skip the filter's settling time.
+        assert!(rms(&hum[len / 2..]) < 0.5 / 2f32.sqrt() * 0.4);
+
+        let mut voice = sine(len, 1_000.0, 0.5);
+        high_pass(&mut voice, RATE, DEFAULT_HIGH_PASS_HZ);
+        let ratio = rms(&voice[len / 2..]) / (0.5 / 2f32.sqrt());
+        assert!((ratio - 1.0).abs() < 0.02, "ratio {ratio}");
+    }
+
+    #[test]
+    fn peak_normalization_hits_target() {
+        let mut samples = sine(RATE as usize, 440.0, 0.05);
+        normalize_peak(&mut samples);
+        assert!((peak(&samples) - TARGET_PEAK).abs() < 1e-3);
+    }
+
+    #[test]
+    fn peak_normalization_caps_gain() {
+        let mut samples = sine(RATE as usize, 440.0, 1e-4);
+        normalize_peak(&mut samples);
+        assert!(peak(&samples) < 1e-4 * 10f32.powf(MAX_GAIN_DB / 20.0) * 1.01);
+    }
+
+    #[test]
+    fn integrated_loudness_matches_reference_tone() {
+        // BS.1770: a full-scale 997 Hz sine reads -3.01 LUFS.
+        let samples = sine(3 * RATE as usize, 997.0, 1.0);
+        let loudness = integrated_loudness(&samples, RATE).unwrap();
+        assert!((loudness + 3.01).abs() < 0.3, "loudness {loudness}");
+        assert!(integrated_loudness(&vec![0.0; RATE as usize], RATE).is_none());
+    }
+
+    #[test]
+    fn lufs_normalization_reaches_target() {
+        let mut samples = sine(3 * RATE as usize, 997.0, 0.02);
+        normalize_lufs(&mut samples, RATE);
+        let loudness = integrated_loudness(&samples, RATE).unwrap();
+        assert!((loudness - TARGET_LUFS).abs() < 0.3, "loudness {loudness}");
+    }
+
+    #[test]
+    fn noise_gate_attenuates_noise_and_keeps_tone() {
+        let second = RATE as usize;
+        let background = noise(3 * second, 0.02);
+        let tone = sine(second, 1_000.0, 0.5);
+        let mut samples = background.clone();
+        for (sample, value) in samples[second..2 * second].iter_mut().zip(&tone) {
+            *sample += value;
+        }
+
+        noise_gate(&mut samples);
+
+        let noise_before = rms(&background[..second]);
+        let noise_after = rms(&samples[..second]);
+        assert!(
+            noise_after < noise_before * 0.2,
+            "{noise_after} vs {noise_before}"
+        );
+        let tone_ratio = rms(&samples[second + 1_000..2 * second - 1_000]) / rms(&tone);
+        assert!((tone_ratio - 1.0).abs() < 0.05, "tone ratio {tone_ratio}");
+    }
+
+    #[test]
+    fn parse_accepts_stage_lists() {
+        assert_eq!(
+            PreprocessOptions::parse("off").unwrap(),
+            PreprocessOptions::default()
+        );
+        assert_eq!(
+            PreprocessOptions::parse("on").unwrap(),
+            PreprocessOptions::recommended()
+        );
+        let options = PreprocessOptions::parse("dc, highpass:120,peak").unwrap();
+        assert!(options.dc_removal);
+        assert_eq!(options.high_pass_hz, 120.0);
+        assert_eq!(options.normalize, Normalization::Peak);
+        assert!(!options.noise_gate);
+        assert!(PreprocessOptions::parse("highpass:-5").is_err());
+        assert!(PreprocessOptions::parse("reverb").is_err());
+    }
+}
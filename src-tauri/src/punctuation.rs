@@ -0,0 +1,462 @@
+/// What a spoken command turns into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Command {
+    /// Mark attached to the previous word. Replaces any punctuation the
+    /// model already put there, since that was its guess at the same pause.
+    Mark(&'static str),
+    /// A `Mark` that ends the sentence; the next word is capitalized.
+    EndMark(&'static str),
+    /// Closing mark attached to the previous word as it is.
+    Close(&'static str),
+    /// Opening mark attached to the next word.
+    Open(&'static str),
+    /// Line breaks; the next word starts a sentence.
+    Break(&'static str),
+    /// Upper-cases the next word.
+    AllCaps,
+    /// Types the words of the next command instead of running it.
+    Literal,
+}
+
+/// Spoken commands of one language.
+pub struct CommandTable {
+    /// Words are separated by spaces, so commands match whole words.
+    /// Otherwise they match anywhere in the text.
+    spaced: bool,
+    /// Longer phrases must come before their prefixes.
+    commands: &'static [(&'static str, Command)],
+}
+
+const ENGLISH: CommandTable = CommandTable {
+    spaced: true,
+    commands: &[
+        ("new paragraph", Command::Break("\n\n")),
+        ("new line", Command::Break("\n")),
+        ("newline", Command::Break("\n")),
+        ("comma", Command::Mark(",")),
+        ("period", Command::EndMark(".")),
+        ("full stop", Command::EndMark(".")),
+        ("question mark", Command::EndMark("?")),
+        ("exclamation mark", Command::EndMark("!")),
+        ("exclamation point", Command::EndMark("!")),
+        ("colon", Command::Mark(":")),
+        ("semicolon", Command::Mark(";")),
+        ("ellipsis", Command::Mark("...")),
+        ("open quote", Command::Open("\"")),
+        ("close quote", Command::Close("\"")),
+        ("end quote", Command::Close("\"")),
+        ("unquote", Command::Close("\"")),
+        ("open paren", Command::Open("(")),
+        ("open parenthesis", Command::Open("(")),
+        ("close paren", Command::Close(")")),
+        ("close parenthesis", Command::Close(")")),
+        ("all caps", Command::AllCaps),
+        ("literal", Command::Literal),
+    ],
+};
+
+const CHINESE: CommandTable = CommandTable {
+    spaced: false,
+    commands: &[
+        ("新段落", Command::Break("\n\n")),
+        ("另起一段", Command::Break("\n\n")),
+        ("换行", Command::Break("\n")),
+        ("逗号", Command::Mark("，")),
+        ("顿号", Command::Mark("、")),
+        ("句号", Command::EndMark("。")),
+        ("问号", Command::EndMark("？")),
+        ("感叹号", Command::EndMark("！")),
+        ("冒号", Command::Mark("：")),
+        ("分号", Command::Mark("；")),
+        ("省略号", Command::Mark("……")),
+        ("左引号", Command::Open("“")),
+        ("右引号", Command::Close("”")),
+        ("左括号", Command::Open("（")),
+        ("右括号", Command::Close("）")),
+    ],
+};
+
+/// The commands for a language code such as `en` or `zh-CN`, or `None`
+/// for languages without a table, whose text is left as it is.
+pub fn table(language: &str) -> Option<&'static CommandTable> {
+    let base = language.split(['-', '_']).next().unwrap_or_default();
+    match base.to_ascii_lowercase().as_str() {
+        "en" => Some(&ENGLISH),
+        "zh" => Some(&CHINESE),
+        _ => None,
+    }
+}
+
+/// Punctuation a model may have put around spoken commands.
+fn is_punctuation(c: char) -> bool {
+    matches!(
+        c,
+        ',' | '.' | '?' | '!' | ':' | ';' | '，' | '。' | '？' | '！' | '：' | '；' | '、'
+    )
+}
+
+/// Replaces spoken commands in a transcript with the characters they name.
+pub fn apply(text: &str, table: &CommandTable) -> String {
+    if table.spaced {
+        apply_spaced(text, table)
+    } else {
+        apply_unspaced(text, table)
+    }
+}
+
+/// Builds the output of the spaced transformer word by word.
+struct Writer {
+    out: String,
+    space_before_next: bool,
+    capitalize_next: bool,
+    upper_next: bool,
+}
+
+impl Writer {
+    /// Writes a word after `gap`, the whitespace that preceded it.
+    fn word(&mut self, gap: &str, word: &str) {
+        if self.space_before_next {
+            self.out.push_str(gap);
+        }
+        if self.upper_next {
+            self.out.push_str(&word.to_uppercase());
+        } else if self.capitalize_next {
+            let start = word.find(char::is_alphanumeric).unwrap_or(word.len());
+            let mut rest = word[start..].chars();
+            self.out.push_str(&word[..start]);
+            if let Some(first) = rest.next() {
+                self.out.extend(first.to_uppercase());
+            }
+            self.out.push_str(rest.as_str());
+        } else {
+            self.out.push_str(word);
+        }
+        self.space_before_next = true;
+        self.capitalize_next = false;
+        self.upper_next = false;
+    }
+
+    fn command(&mut self, command: Command) {
+        match command {
+            Command::Mark(mark) | Command::EndMark(mark) => {
+                let kept = self
+                    .out
+                    .trim_end_matches(|c: char| c.is_whitespace() || is_punctuation(c));
+                self.out.truncate(kept.len());
+                self.out.push_str(mark);
+                self.space_before_next = true;
+                self.capitalize_next = matches!(command, Command::EndMark(_));
+            }
+            Command::Close(mark) => {
+                self.out.truncate(self.out.trim_end().len());
+                self.out.push_str(mark);
+                self.space_before_next = true;
+            }
+            Command::Open(mark) => {
+                if self.space_before_next {
+                    self.out.push(' ');
+                }
+                self.out.push_str(mark);
+                self.space_before_next = false;
+            }
+            Command::Break(lines) => {
+                self.out
+                    .truncate(self.out.trim_end_matches([' ', '\t']).len());
+                self.out.push_str(lines);
+                self.space_before_next = false;
+                self.capitalize_next = true;
+            }
+            Command::AllCaps => self.upper_next = true,
+            Command::Literal => {}
+        }
+    }
+}
+
+/// Lower-cased word without the punctuation a model attached to it.
+fn bare_word(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+/// Splits text into words, each with the whitespace before it.
+fn split_words(text: &str) -> Vec<(&str, &str)> {
+    let mut words = Vec::new();
+    let mut rest = text;
+    loop {
+        let start = rest.len() - rest.trim_start().len();
+        if start == rest.len() {
+            return words;
+        }
+        let end = rest[start..]
+            .find(char::is_whitespace)
+            .map_or(rest.len(), |end| start + end);
+        words.push((&rest[..start], &rest[start..end]));
+        rest = &rest[end..];
+    }
+}
+
+/// The command whose phrase starts the words, and how many words it spans.
+fn match_spaced(bare: &[String], table: &CommandTable) -> Option<(Command, usize)> {
+    table.commands.iter().find_map(|(phrase, command)| {
+        let count = phrase.split(' ').count();
+        let matches = bare.len() >= count
+            && phrase
+                .split(' ')
+                .zip(bare)
+                .all(|(expected, word)| expected == word);
+        matches.then_some((*command, count))
+    })
+}
+
+fn apply_spaced(text: &str, table: &CommandTable) -> String {
+    let words = split_words(text);
+    let bare: Vec<String> = words.iter().map(|(_, word)| bare_word(word)).collect();
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let mut writer = Writer {
+        out: leading.to_string(),
+        space_before_next: false,
+        capitalize_next: false,
+        upper_next: false,
+    };
+
+    let mut index = 0;
+    while index < words.len() {
+        let Some((command, count)) = match_spaced(&bare[index..], table) else {
+            writer.word(words[index].0, words[index].1);
+            index += 1;
+            continue;
+        };
+        let next = index + count;
+        match command {
+            // Commands that need a next word are plain words without one.
+            Command::AllCaps if next >= words.len() => {
+                for (gap, word) in &words[index..next] {
+                    writer.word(gap, word);
+                }
+            }
+            Command::Literal => match match_spaced(&bare[next..], table) {
+                // `bare` is only for matching; type the words as dictated.
+                Some((_, escaped)) => {
+                    for (gap, word) in &words[next..next + escaped] {
+                        writer.word(gap, word);
+                    }
+                    index = next + escaped;
+                    continue;
+                }
+                None => writer.word(words[index].0, words[index].1),
+            },
+            command => writer.command(command),
+        }
+        index = next;
+    }
+
+    let trailing = &text[text.trim_end().len()..];
+    if !trailing.is_empty() && writer.space_before_next {
+        writer.out.push_str(trailing);
+    }
+    writer.out
+}
+
+fn apply_unspaced(text: &str, table: &CommandTable) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let found = table
+            .commands
+            .iter()
+            .find(|(phrase, _)| rest.starts_with(phrase));
+        let Some((phrase, command)) = found else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        match *command {
+            Command::Mark(mark) | Command::EndMark(mark) => {
+                let kept = out.trim_end_matches(|c: char| c.is_whitespace() || is_punctuation(c));
+                out.truncate(kept.len());
+                out.push_str(mark);
+            }
+            Command::Close(mark) | Command::Open(mark) => out.push_str(mark),
+            Command::Break(lines) => {
+                out.truncate(out.trim_end_matches([' ', '\t']).len());
+                out.push_str(lines);
+            }
+            Command::AllCaps | Command::Literal => out.push_str(phrase),
+        }
+        // Drop what the model put after the command, as the spaced path does.
+        rest = rest[phrase.len()..]
+            .trim_start_matches(|c: char| is_punctuation(c) || (c.is_whitespace() && c != '\n'));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn en(text: &str) -> String {
+        apply(text, table("en").unwrap())
+    }
+
+    fn zh(text: &str) -> String {
+        apply(text, table("zh").unwrap())
+    }
+
+    #[test]
+    fn picks_tables_by_language() {
+        assert!(!table("zh-CN").unwrap().spaced);
+        assert!(!table("ZH_tw").unwrap().spaced);
+        assert!(table("en-US").unwrap().spaced);
+        assert!(table("de").is_none());
+        assert!(table("").is_none());
+    }
+
+    #[test]
+    fn text_without_commands_is_unchanged() {
+        for text in [
+            "",
+            "Hello world.",
+            "  leading and trailing  ",
+            "Commas, periods. Questions?",
+            "Newlines stay\nas they are",
+        ] {
+            assert_eq!(en(text), text);
+        }
+        assert_eq!(zh("你好，世界。"), "你好，世界。");
+    }
+
+    #[test]
+    fn marks_attach_to_the_previous_word() {
+        assert_eq!(en("hello comma world"), "hello, world");
+        assert_eq!(en("wait colon this semicolon that"), "wait: this; that");
+        assert_eq!(en("and then ellipsis"), "and then...");
+    }
+
+    #[test]
+    fn sentence_ends_capitalize_the_next_word() {
+        assert_eq!(
+            en("hello period how are you question mark"),
+            "hello. How are you?"
+        );
+        assert_eq!(en("stop exclamation point now full stop"), "stop! Now.");
+        assert_eq!(en("wow exclamation mark"), "wow!");
+    }
+
+    #[test]
+    fn replaces_punctuation_the_model_guessed() {
+        assert_eq!(en("Hello, comma, world. Period."), "Hello, world.");
+        assert_eq!(en("Is it? Question mark."), "Is it?");
+        assert_eq!(en("Done. Comma, then more."), "Done, then more.");
+    }
+
+    #[test]
+    fn commands_match_any_case() {
+        assert_eq!(en("One COMMA two New Line three"), "One, two\nThree");
+    }
+
+    #[test]
+    fn line_breaks_drop_surrounding_spaces() {
+        assert_eq!(
+            en("first line new line second line"),
+            "first line\nSecond line"
+        );
+        assert_eq!(
+            en("Dear team, new paragraph. Thanks for the update."),
+            "Dear team,\n\nThanks for the update."
+        );
+        assert_eq!(en("newline at start"), "\nAt start");
+    }
+
+    #[test]
+    fn quotes_and_parentheses_wrap_words() {
+        assert_eq!(
+            en("she said open quote hi there close quote and left"),
+            "she said \"hi there\" and left"
+        );
+        assert_eq!(
+            en("the API open paren beta close paren works"),
+            "the API (beta) works"
+        );
+        assert_eq!(en("open quote yes unquote"), "\"yes\"");
+        assert_eq!(en("open parenthesis a close parenthesis"), "(a)");
+    }
+
+    #[test]
+    fn close_keeps_punctuation_inside_it() {
+        assert_eq!(
+            en("open quote stop period end quote then"),
+            "\"stop.\" Then"
+        );
+    }
+
+    #[test]
+    fn all_caps_upper_cases_one_word() {
+        assert_eq!(en("ask all caps nasa about it"), "ask NASA about it");
+        assert_eq!(en("All caps, slo."), "SLO.");
+        assert_eq!(en("we need all caps"), "we need all caps");
+    }
+
+    #[test]
+    fn literal_types_the_command_words() {
+        assert_eq!(en("type literal comma here"), "type comma here");
+        assert_eq!(en("literal new line please"), "new line please");
+        assert_eq!(en("a literal translation"), "a literal translation");
+        assert_eq!(en("say literal literal"), "say literal");
+    }
+
+    #[test]
+    fn literal_keeps_the_case_and_punctuation_of_the_words() {
+        assert_eq!(en("literal Period."), "Period.");
+        assert_eq!(en("Type literal New Line, then"), "Type New Line, then");
+    }
+
+    #[test]
+    fn commands_inside_words_are_words() {
+        assert_eq!(en("commas and periods"), "commas and periods");
+        assert_eq!(en("the colonel"), "the colonel");
+        assert_eq!(en("newlines"), "newlines");
+    }
+
+    #[test]
+    fn keeps_outer_whitespace_for_streamed_commits() {
+        assert_eq!(en(" and more comma"), " and more,");
+        assert_eq!(en(" comma and more"), ", and more");
+        assert_eq!(en("words "), "words ");
+        assert_eq!(en("words new line "), "words\n");
+    }
+
+    #[test]
+    fn commands_at_the_start_have_no_word_to_attach_to() {
+        assert_eq!(en("comma"), ",");
+        assert_eq!(en("period next"), ". Next");
+        assert_eq!(en("open quote"), "\"");
+    }
+
+    #[test]
+    fn chinese_commands_match_inside_text() {
+        assert_eq!(zh("你好逗号世界句号"), "你好，世界。");
+        assert_eq!(zh("真的吗问号太好了感叹号"), "真的吗？太好了！");
+        assert_eq!(zh("苹果顿号香蕉"), "苹果、香蕉");
+        assert_eq!(zh("注意冒号明天开会分号别迟到"), "注意：明天开会；别迟到");
+    }
+
+    #[test]
+    fn chinese_replaces_guessed_punctuation() {
+        assert_eq!(zh("你好，逗号，世界。句号。"), "你好，世界。");
+        assert_eq!(zh("第一行。换行。第二行"), "第一行。\n第二行");
+    }
+
+    #[test]
+    fn chinese_breaks_quotes_and_brackets() {
+        assert_eq!(zh("标题新段落正文"), "标题\n\n正文");
+        assert_eq!(zh("另起一段开始"), "\n\n开始");
+        assert_eq!(zh("他说左引号你好右引号"), "他说“你好”");
+        assert_eq!(zh("版本左括号测试右括号"), "版本（测试）");
+        assert_eq!(zh("等等省略号"), "等等……");
+    }
+
+    #[test]
+    fn chinese_keeps_english_words() {
+        assert_eq!(zh("部署 Kubernetes 逗号 然后"), "部署 Kubernetes，然后");
+    }
+}
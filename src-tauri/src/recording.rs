@@ -1,130 +1,802 @@
+use crate::audio::{self, f32_to_i16, MonoMixer, PcmSample};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use rtrb::{Consumer, Producer, RingBuffer};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex as StdMutex};
-use tokio::sync::mpsc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InputDeviceInfo {
+    pub name: String,
+    pub is_default: bool,
+    pub default_config: Option<InputFormat>,
+    pub supported_formats: Vec<InputFormatRange>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InputFormat {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub sample_format: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InputFormatRange {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: String,
+}
+
+/// Lists the input devices of the default host with their formats.
+pub fn list_input_devices() -> Result<Vec<InputDeviceInfo>, String> {
+    let host = cpal::default_host();
+    let default_name = host
+        .default_input_device()
+        .and_then(|device| device.name().ok());
+    let devices = host
+        .input_devices()
+        .map_err(|err| format!("Failed to list input devices: {err}"))?;
+
+    let mut result = Vec::new();
+    for device in devices {
+        let Ok(name) = device.name() else {
+            continue;
+        };
+        let default_config = device
+            .default_input_config()
+            .ok()
+            .map(|config| InputFormat {
+                channels: config.channels(),
+                sample_rate: config.sample_rate().0,
+                sample_format: config.sample_format().to_string(),
+            });
+        let supported_formats = device
+            .supported_input_configs()
+            .map(|configs| {
+                configs
+                    .map(|config| InputFormatRange {
+                        channels: config.channels(),
+                        min_sample_rate: config.min_sample_rate().0,
+                        max_sample_rate: config.max_sample_rate().0,
+                        sample_format: config.sample_format().to_string(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        result.push(InputDeviceInfo {
+            is_default: default_name.as_deref() == Some(name.as_str()),
+            name,
+            default_config,
+            supported_formats,
+        });
+    }
+    Ok(result)
+}
+
+/// Finds the input device called `name`, falling back to the default device
+/// when no name is set or the device is gone (e.g. an unplugged headset).
+pub fn input_device(name: Option<&str>) -> Result<cpal::Device, String> {
+    let host = cpal::default_host();
+    if let Some(name) = name.filter(|name| !name.is_empty()) {
+        let found = host.input_devices().ok().and_then(|mut devices| {
+            devices.find(|device| device.name().map(|n| n == name).unwrap_or(false))
+        });
+        match found {
+            Some(device) => return Ok(device),
+            None => {
+                eprintln!("[recording] input device {name:?} not found, using the default device")
+            }
+        }
+    }
+    host.default_input_device()
+        .ok_or_else(|| "No input device available".to_string())
+}
 
 pub struct RecordingSession {
-    stream: cpal::Stream,
-    buffer: Arc<StdMutex<Vec<f32>>>,
+    capture: CaptureSource,
+    /// Microphone chosen in the settings; `None` uses the default.
+    input_device_name: Option<String>,
+    buffer: Arc<StdMutex<RecordingBuffer>>,
     sample_rate: u32,
+    /// Audio captured on devices that failed earlier, with their rates.
+    captured: Vec<(Vec<f32>, u32)>,
+    errors: StreamErrorSender,
+    levels: LevelSender,
+    /// Directory the audio is spooled to while recording, if enabled.
+    spool_dir: Option<PathBuf>,
+    /// Finished spool files from devices that failed earlier.
+    spooled: Vec<PathBuf>,
+}
+
+/// A stopped recording. `spool_files` hold the same audio on disk; the
+/// caller removes them once the recording has been transcribed.
+pub struct FinishedRecording {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub spool_files: Vec<PathBuf>,
+}
+
+impl FinishedRecording {
+    pub fn remove_spool_files(&self) {
+        for path in &self.spool_files {
+            if let Err(err) = std::fs::remove_file(path) {
+                eprintln!("[recording] failed to remove {}: {err}", path.display());
+            }
+        }
+    }
+}
+
+/// Audio of the current device, mirrored to a spool file when enabled.
+#[derive(Default)]
+struct RecordingBuffer {
+    samples: Vec<f32>,
+    spool: Option<WavSpool>,
+}
+
+impl RecordingBuffer {
+    fn extend(&mut self, mono: &[f32]) {
+        self.samples.extend_from_slice(mono);
+        let failed = match &mut self.spool {
+            Some(spool) => spool.write(mono).err(),
+            None => None,
+        };
+        if let Some(err) = failed {
+            eprintln!("[recording] spooling stopped: {err}");
+            self.spool = None;
+        }
+    }
+
+    /// Takes the samples and finishes the spool file, if any.
+    fn take(&mut self) -> (Vec<f32>, Option<PathBuf>) {
+        let samples = std::mem::take(&mut self.samples);
+        let spooled = self.spool.take().and_then(|spool| match spool.finish() {
+            Ok(path) => Some(path),
+            Err(err) => {
+                eprintln!("[recording] {err}");
+                None
+            }
+        });
+        (samples, spooled)
+    }
 }
 
 pub struct StreamingRecordingSession {
-    stream: cpal::Stream,
+    capture: CaptureSource,
+    /// Microphone chosen in the settings; `None` uses the default.
+    input_device_name: Option<String>,
     pub sample_rate: u32,
+    chunks: Arc<StdMutex<ChunkQueue>>,
+    chunk_tx: mpsc::Sender<Vec<i16>>,
+    errors: StreamErrorSender,
+    levels: LevelSender,
 }
 
-// Safety: RecordingSession is always protected by a StdMutex and accessed
-// from one logical owner at a time. cpal::Stream is not Send/Sync due to
-// platform internals, but we never share the stream across threads without
-// synchronisation.
-unsafe impl Send for RecordingSession {}
-unsafe impl Sync for RecordingSession {}
+/// Stream errors reported by cpal, forwarded out of the audio callback so
+/// the owner of the session can react to a lost device.
+pub type StreamErrorSender = mpsc::UnboundedSender<cpal::StreamError>;
+pub type StreamErrorReceiver = mpsc::UnboundedReceiver<cpal::StreamError>;
 
-impl RecordingSession {
-    pub fn start() -> Result<Self, String> {
-        let host = cpal::default_host();
-        let device = host
-            .default_input_device()
-            .ok_or_else(|| "No input device available".to_string())?;
+fn stream_error_handler(
+    label: &'static str,
+    errors: &StreamErrorSender,
+) -> impl FnMut(cpal::StreamError) + Send + 'static {
+    let errors = errors.clone();
+    move |err: cpal::StreamError| {
+        eprintln!("[{label}] stream error: {err}");
+        let _ = errors.send(err);
+    }
+}
+
+/// Input level over one metering window, as linear amplitudes in 0..=1.
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct InputLevel {
+    pub rms: f32,
+    pub peak: f32,
+}
+
+pub type LevelSender = mpsc::UnboundedSender<InputLevel>;
+
+/// Levels are reported every 50 ms, about 20 times a second.
+pub const LEVEL_WINDOW_MS: usize = 50;
+
+/// Accumulates RMS and peak on the capture consumer thread and reports
+/// them once per window.
+struct LevelMeter {
+    levels: LevelSender,
+    window: usize,
+    count: usize,
+    sum_squares: f64,
+    peak: f32,
+}
+
+impl LevelMeter {
+    fn new(levels: &LevelSender, sample_rate: u32) -> Self {
+        Self {
+            levels: levels.clone(),
+            window: (sample_rate as usize * LEVEL_WINDOW_MS / 1000).max(1),
+            count: 0,
+            sum_squares: 0.0,
+            peak: 0.0,
+        }
+    }
+
+    fn push(&mut self, mono: &[f32]) {
+        for &sample in mono {
+            self.sum_squares += (sample as f64) * (sample as f64);
+            self.peak = self.peak.max(sample.abs());
+            self.count += 1;
+            if self.count == self.window {
+                let rms = (self.sum_squares / self.count as f64).sqrt() as f32;
+                let _ = self.levels.send(InputLevel {
+                    rms,
+                    peak: self.peak.min(1.0),
+                });
+                self.count = 0;
+                self.sum_squares = 0.0;
+                self.peak = 0.0;
+            }
+        }
+    }
+}
+
+fn device_name(device: &cpal::Device) -> String {
+    device
+        .name()
+        .unwrap_or_else(|_| "unknown device".to_string())
+}
+
+/// Seconds of interleaved audio the capture ring can hold before the
+/// callback has to drop samples.
+const CAPTURE_RING_SECONDS: usize = 2;
+/// How often the consumer thread drains the capture ring.
+const CAPTURE_POLL: Duration = Duration::from_millis(10);
+/// A running stream that delivers nothing for this long has stalled.
+const CAPTURE_STALL: Duration = Duration::from_millis(200);
+
+static CAPTURE_STATS: CaptureStats = CaptureStats {
+    overruns: AtomicU64::new(0),
+    stalls: AtomicU64::new(0),
+};
+
+struct CaptureStats {
+    overruns: AtomicU64,
+    stalls: AtomicU64,
+}
+
+/// Health of the capture path since the app started.
+#[derive(Serialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureCounters {
+    /// Audio callbacks that found the ring full and dropped their buffer.
+    pub overruns: u64,
+    /// Times a running stream delivered no audio for `CAPTURE_STALL`.
+    pub stalls: u64,
+}
+
+pub fn capture_counters() -> CaptureCounters {
+    CaptureCounters {
+        overruns: CAPTURE_STATS.overruns.load(Ordering::Relaxed),
+        stalls: CAPTURE_STATS.stalls.load(Ordering::Relaxed),
+    }
+}
+
+/// A running input stream. The audio callback only copies samples into a
+/// lock-free ring; a consumer thread mixes them to mono and passes them on,
+/// so nothing on the real-time thread locks or allocates.
+struct Capture {
+    stream: Option<cpal::Stream>,
+    running: Arc<AtomicBool>,
+    consumer: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Capture {
+    /// Opens `device` with its default config and feeds mono samples to
+    /// `sink` on the consumer thread. Returns the capture and its rate.
+    fn start(
+        device: &cpal::Device,
+        err_fn: impl FnMut(cpal::StreamError) + Send + 'static,
+        sink: impl FnMut(&[f32]) + Send + 'static,
+    ) -> Result<(Self, u32), String> {
         let config = device
             .default_input_config()
             .map_err(|err| format!("Failed to get input config: {err}"))?;
         let sample_rate = config.sample_rate().0;
         let channels = config.channels() as usize;
-        let buffer: Arc<StdMutex<Vec<f32>>> = Arc::new(StdMutex::new(Vec::new()));
-        let buf_clone = Arc::clone(&buffer);
-
-        let err_fn = |err: cpal::StreamError| {
-            eprintln!("[recording] stream error: {err}");
-        };
+        let (producer, consumer) =
+            RingBuffer::<f32>::new(sample_rate as usize * channels * CAPTURE_RING_SECONDS);
 
+        let stream_config: cpal::StreamConfig = config.clone().into();
         let stream = match config.sample_format() {
-            cpal::SampleFormat::F32 => device
-                .build_input_stream(
-                    &config.into(),
-                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                        let mut buf = buf_clone.lock().unwrap();
-                        if channels == 1 {
-                            buf.extend_from_slice(data);
-                        } else {
-                            for chunk in data.chunks(channels) {
-                                let sum: f32 = chunk.iter().sum();
-                                buf.push(sum / channels as f32);
-                            }
-                        }
-                    },
-                    err_fn,
-                    None,
-                )
-                .map_err(|err| format!("Failed to build input stream: {err}"))?,
+            cpal::SampleFormat::I8 => {
+                build_capture_stream::<i8>(device, &stream_config, producer, err_fn)
+            }
             cpal::SampleFormat::I16 => {
-                let buf_clone = Arc::clone(&buffer);
-                device
-                    .build_input_stream(
-                        &config.into(),
-                        move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                            let mut buf = buf_clone.lock().unwrap();
-                            if channels == 1 {
-                                for &sample in data {
-                                    buf.push(sample as f32 / i16::MAX as f32);
-                                }
-                            } else {
-                                for chunk in data.chunks(channels) {
-                                    let sum: f32 =
-                                        chunk.iter().map(|&s| s as f32 / i16::MAX as f32).sum();
-                                    buf.push(sum / channels as f32);
-                                }
-                            }
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|err| format!("Failed to build input stream: {err}"))?
+                build_capture_stream::<i16>(device, &stream_config, producer, err_fn)
+            }
+            cpal::SampleFormat::I32 => {
+                build_capture_stream::<i32>(device, &stream_config, producer, err_fn)
+            }
+            cpal::SampleFormat::I64 => {
+                build_capture_stream::<i64>(device, &stream_config, producer, err_fn)
+            }
+            cpal::SampleFormat::U8 => {
+                build_capture_stream::<u8>(device, &stream_config, producer, err_fn)
             }
             cpal::SampleFormat::U16 => {
-                let buf_clone = Arc::clone(&buffer);
-                device
-                    .build_input_stream(
-                        &config.into(),
-                        move |data: &[u16], _: &cpal::InputCallbackInfo| {
-                            let mut buf = buf_clone.lock().unwrap();
-                            if channels == 1 {
-                                for &sample in data {
-                                    buf.push((sample as f32 / u16::MAX as f32) * 2.0 - 1.0);
-                                }
-                            } else {
-                                for chunk in data.chunks(channels) {
-                                    let sum: f32 = chunk
-                                        .iter()
-                                        .map(|&s| (s as f32 / u16::MAX as f32) * 2.0 - 1.0)
-                                        .sum();
-                                    buf.push(sum / channels as f32);
-                                }
-                            }
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|err| format!("Failed to build input stream: {err}"))?
+                build_capture_stream::<u16>(device, &stream_config, producer, err_fn)
+            }
+            cpal::SampleFormat::U32 => {
+                build_capture_stream::<u32>(device, &stream_config, producer, err_fn)
+            }
+            cpal::SampleFormat::U64 => {
+                build_capture_stream::<u64>(device, &stream_config, producer, err_fn)
+            }
+            cpal::SampleFormat::F32 => {
+                build_capture_stream::<f32>(device, &stream_config, producer, err_fn)
+            }
+            cpal::SampleFormat::F64 => {
+                build_capture_stream::<f64>(device, &stream_config, producer, err_fn)
             }
             format => return Err(format!("Unsupported sample format: {format:?}")),
-        };
+        }
+        .map_err(|err| format!("Failed to build input stream: {err}"))?;
 
         stream
             .play()
             .map_err(|err| format!("Failed to start recording: {err}"))?;
 
+        let running = Arc::new(AtomicBool::new(true));
+        let consumer = std::thread::Builder::new()
+            .name("openstt-capture".to_string())
+            .spawn({
+                let running = Arc::clone(&running);
+                move || consume_capture(consumer, channels, &running, sink)
+            })
+            .map_err(|err| format!("Failed to start capture thread: {err}"))?;
+
+        Ok((
+            Self {
+                stream: Some(stream),
+                running,
+                consumer: Some(consumer),
+            },
+            sample_rate,
+        ))
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        // Stop the callback first so the consumer drains everything it wrote.
+        drop(self.stream.take());
+        self.running.store(false, Ordering::Release);
+        if let Some(consumer) = self.consumer.take() {
+            let _ = consumer.join();
+        }
+    }
+}
+
+fn build_capture_stream<T: cpal::SizedSample + PcmSample>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut producer: Producer<f32>,
+    err_fn: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<cpal::Stream, cpal::BuildStreamError> {
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            if !push_capture_buffer(&mut producer, data) {
+                CAPTURE_STATS.overruns.fetch_add(1, Ordering::Relaxed);
+            }
+        },
+        err_fn,
+        None,
+    )
+}
+
+/// Copies one callback buffer into the ring, or drops all of it when it
+/// does not fit. A partial buffer could end mid-frame and pair up channels
+/// of different frames for the rest of the session.
+fn push_capture_buffer<T: PcmSample>(producer: &mut Producer<f32>, data: &[T]) -> bool {
+    match producer.write_chunk_uninit(data.len()) {
+        Ok(chunk) => {
+            chunk.fill_from_iter(data.iter().map(|&sample| sample.to_f32()));
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Mixes everything in the ring down to mono into `mono`. Returns false
+/// when the ring was empty.
+fn drain_capture(consumer: &mut Consumer<f32>, mixer: &mut MonoMixer, mono: &mut Vec<f32>) -> bool {
+    let available = consumer.slots();
+    if available == 0 {
+        return false;
+    }
+    let Ok(chunk) = consumer.read_chunk(available) else {
+        return false;
+    };
+    let (first, second) = chunk.as_slices();
+    mono.clear();
+    mixer.mix(first, mono);
+    mixer.mix(second, mono);
+    chunk.commit_all();
+    true
+}
+
+fn consume_capture(
+    mut consumer: Consumer<f32>,
+    channels: usize,
+    running: &AtomicBool,
+    mut sink: impl FnMut(&[f32]),
+) {
+    let mut mixer = MonoMixer::new(channels);
+    let mut mono: Vec<f32> = Vec::new();
+    let mut last_audio: Option<Instant> = None;
+    let mut stalled = false;
+    loop {
+        // Check the flag before draining so samples written just before
+        // the stream stopped are still delivered.
+        let keep_running = running.load(Ordering::Acquire);
+        if drain_capture(&mut consumer, &mut mixer, &mut mono) {
+            sink(&mono);
+            last_audio = Some(Instant::now());
+            stalled = false;
+        } else if let Some(last) = last_audio {
+            if !stalled && last.elapsed() >= CAPTURE_STALL {
+                CAPTURE_STATS.stalls.fetch_add(1, Ordering::Relaxed);
+                stalled = true;
+            }
+        }
+        if !keep_running {
+            break;
+        }
+        std::thread::sleep(CAPTURE_POLL);
+    }
+}
+
+/// Most recent mono samples from the pre-roll stream, oldest first.
+#[derive(Clone)]
+pub struct PreRollBuffer {
+    shared: Arc<StdMutex<PreRollShared>>,
+    capacity: usize,
+    sample_rate: u32,
+    device: String,
+    /// The stream filling the buffer. Recordings tapping it hold it too, so
+    /// replacing the pre-roll does not cut them off. `None` in the stream's
+    /// own callbacks, and in tests that fill the buffer by hand.
+    _stream: Option<Arc<PreRollStream>>,
+}
+
+#[derive(Default)]
+struct PreRollShared {
+    samples: VecDeque<f32>,
+    /// The recording currently fed from the pre-roll stream, if any.
+    tap: Option<PreRollSink>,
+    /// The device went away; the stream delivers nothing more.
+    lost: bool,
+}
+
+struct PreRollStream {
+    _capture: Capture,
+}
+
+// Safety: Same as RecordingSession
+unsafe impl Send for PreRollStream {}
+unsafe impl Sync for PreRollStream {}
+
+struct PreRollSink {
+    sink: Box<dyn FnMut(&[f32]) + Send>,
+    errors: StreamErrorSender,
+}
+
+impl PreRollBuffer {
+    fn new(device: String, sample_rate: u32, duration_ms: u32) -> Self {
+        Self {
+            shared: Arc::new(StdMutex::new(PreRollShared::default())),
+            capacity: sample_rate as usize * duration_ms as usize / 1000,
+            sample_rate,
+            device,
+            _stream: None,
+        }
+    }
+
+    fn push(&self, mono: &[f32]) {
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(tap) = &mut shared.tap {
+            (tap.sink)(mono);
+        }
+        shared.samples.extend(mono);
+        let excess = shared.samples.len().saturating_sub(self.capacity);
+        shared.samples.drain(..excess);
+    }
+
+    /// Passes an error of the pre-roll stream on to the recording tapping
+    /// it. A lost device ends the stream for good.
+    fn stream_error(&self, err: cpal::StreamError) {
+        let mut shared = self.shared.lock().unwrap();
+        if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+            shared.lost = true;
+        }
+        if let Some(tap) = &shared.tap {
+            let _ = tap.errors.send(err);
+        }
+    }
+
+    fn is_lost(&self) -> bool {
+        self.shared.lock().unwrap().lost
+    }
+
+    /// Whether a recording of the device called `device` at `sample_rate`
+    /// can take its audio from the pre-roll stream.
+    fn serves(&self, device: &str, sample_rate: u32) -> bool {
+        if self.is_lost() {
+            eprintln!("[recording] skipping pre-roll: {} was lost", self.device);
+            return false;
+        }
+        if sample_rate != self.sample_rate || device != self.device {
+            eprintln!(
+                "[recording] skipping pre-roll: captured from {} at {} Hz",
+                self.device, self.sample_rate
+            );
+            return false;
+        }
+        true
+    }
+
+    /// Feeds the buffered audio and then every new buffer of the pre-roll
+    /// stream to `sink` until the tap is dropped. Both happen under one
+    /// lock, so the joined audio has neither a gap nor an overlap.
+    fn tap(
+        &self,
+        errors: &StreamErrorSender,
+        mut sink: impl FnMut(&[f32]) + Send + 'static,
+    ) -> PreRollTap {
+        let mut shared = self.shared.lock().unwrap();
+        let (first, second) = shared.samples.as_slices();
+        sink(first);
+        sink(second);
+        shared.tap = Some(PreRollSink {
+            sink: Box::new(sink),
+            errors: errors.clone(),
+        });
+        PreRollTap {
+            buffer: self.clone(),
+        }
+    }
+}
+
+/// A recording fed from the pre-roll stream. Dropping it detaches the
+/// recording; no audio reaches it afterwards.
+struct PreRollTap {
+    buffer: PreRollBuffer,
+}
+
+impl Drop for PreRollTap {
+    fn drop(&mut self) {
+        self.buffer.shared.lock().unwrap().tap = None;
+    }
+}
+
+/// Where a session's audio comes from: its own input stream, or the
+/// always-on pre-roll stream when that runs on the same device.
+enum CaptureSource {
+    Device { _capture: Capture },
+    PreRoll { _tap: PreRollTap },
+}
+
+impl CaptureSource {
+    fn open(
+        device: &cpal::Device,
+        sample_rate: u32,
+        pre_roll: Option<PreRollBuffer>,
+        err_fn: impl FnMut(cpal::StreamError) + Send + 'static,
+        errors: &StreamErrorSender,
+        sink: impl FnMut(&[f32]) + Send + 'static,
+    ) -> Result<Self, String> {
+        let name = device_name(device);
+        match pre_roll.filter(|pre_roll| pre_roll.serves(&name, sample_rate)) {
+            Some(pre_roll) => Ok(CaptureSource::PreRoll {
+                _tap: pre_roll.tap(errors, sink),
+            }),
+            None => Capture::start(device, err_fn, sink)
+                .map(|(capture, _)| CaptureSource::Device { _capture: capture }),
+        }
+    }
+}
+
+/// Always-on capture that keeps the last `duration_ms` of the input device,
+/// so recordings can start with the audio from just before the hotkey.
+/// Recordings on the same device then read from this stream rather than
+/// opening their own.
+pub struct PreRoll {
+    buffer: PreRollBuffer,
+    input_device_name: Option<String>,
+    duration_ms: u32,
+}
+
+impl PreRoll {
+    pub fn start(input_device_name: Option<&str>, duration_ms: u32) -> Result<Self, String> {
+        let device = input_device(input_device_name)?;
+        let sample_rate = device
+            .default_input_config()
+            .map_err(|err| format!("Failed to get input config: {err}"))?
+            .sample_rate()
+            .0;
+        let mut buffer = PreRollBuffer::new(device_name(&device), sample_rate, duration_ms);
+        let sink = buffer.clone();
+        let errors = buffer.clone();
+        let err_fn = move |err: cpal::StreamError| {
+            eprintln!("[pre-roll] stream error: {err}");
+            errors.stream_error(err);
+        };
+        let (capture, _) = Capture::start(&device, err_fn, move |mono| sink.push(mono))?;
+        buffer._stream = Some(Arc::new(PreRollStream { _capture: capture }));
         Ok(Self {
-            stream,
             buffer,
-            sample_rate,
+            input_device_name: input_device_name.map(str::to_string),
+            duration_ms,
         })
     }
 
-    pub fn stop(self) -> (Vec<f32>, u32) {
-        drop(self.stream);
-        let samples = self.buffer.lock().unwrap().clone();
-        (samples, self.sample_rate)
+    /// Whether the device went away. The stream does not recover by itself;
+    /// `restart` opens a new one once the device is back.
+    pub fn is_lost(&self) -> bool {
+        self.buffer.is_lost()
+    }
+
+    /// Starts a new pre-roll with the same settings.
+    pub fn restart(&self) -> Result<Self, String> {
+        Self::start(self.input_device_name.as_deref(), self.duration_ms)
+    }
+
+    pub fn buffer(&self) -> PreRollBuffer {
+        self.buffer.clone()
+    }
+}
+
+// Safety: RecordingSession is always protected by a StdMutex and accessed
+// from one logical owner at a time. cpal::Stream is not Send/Sync due to
+// platform internals, but we never share the stream across threads without
+// synchronisation.
+unsafe impl Send for RecordingSession {}
+unsafe impl Sync for RecordingSession {}
+
+impl RecordingSession {
+    /// With `pre_roll`, the recording starts with the buffered audio and
+    /// continues on the pre-roll stream, so the two join seamlessly. Input levels
+    /// are reported on `levels` while recording. With `spool_dir`, the audio
+    /// is also written to a WAV file there as it arrives.
+    pub fn start(
+        input_device_name: Option<&str>,
+        pre_roll: Option<PreRollBuffer>,
+        levels: LevelSender,
+        spool_dir: Option<PathBuf>,
+    ) -> Result<(Self, StreamErrorReceiver), String> {
+        let device = input_device(input_device_name)?;
+        let buffer = Arc::new(StdMutex::new(RecordingBuffer::default()));
+        let (errors, error_rx) = mpsc::unbounded_channel();
+        let (capture, sample_rate) = Self::open_capture(
+            &device,
+            &buffer,
+            &errors,
+            &levels,
+            spool_dir.as_deref(),
+            pre_roll,
+        )?;
+        Ok((
+            Self {
+                capture,
+                input_device_name: input_device_name.map(str::to_string),
+                buffer,
+                sample_rate,
+                captured: Vec::new(),
+                errors,
+                levels,
+                spool_dir,
+                spooled: Vec::new(),
+            },
+            error_rx,
+        ))
+    }
+
+    /// Re-opens the stream after the current one failed, on the chosen
+    /// device when it is present and on the default one otherwise. Audio
+    /// captured so far is kept. Returns the new device name.
+    pub fn reopen(&mut self) -> Result<String, String> {
+        let device = input_device(self.input_device_name.as_deref())?;
+        // Move the old device's audio aside before the new stream starts
+        // writing, since the two may run at different rates.
+        let (samples, spooled) = self.buffer.lock().unwrap().take();
+        self.captured.push((samples, self.sample_rate));
+        self.spooled.extend(spooled);
+        let (capture, sample_rate) = Self::open_capture(
+            &device,
+            &self.buffer,
+            &self.errors,
+            &self.levels,
+            self.spool_dir.as_deref(),
+            None,
+        )?;
+        self.capture = capture;
+        self.sample_rate = sample_rate;
+        Ok(device_name(&device))
+    }
+
+    fn open_capture(
+        device: &cpal::Device,
+        buffer: &Arc<StdMutex<RecordingBuffer>>,
+        errors: &StreamErrorSender,
+        levels: &LevelSender,
+        spool_dir: Option<&Path>,
+        pre_roll: Option<PreRollBuffer>,
+    ) -> Result<(CaptureSource, u32), String> {
+        let sample_rate = device
+            .default_input_config()
+            .map_err(|err| format!("Failed to get input config: {err}"))?
+            .sample_rate()
+            .0;
+        if let Some(dir) = spool_dir {
+            match WavSpool::create(dir, sample_rate) {
+                Ok(spool) => buffer.lock().unwrap().spool = Some(spool),
+                Err(err) => eprintln!("[recording] not spooling to disk: {err}"),
+            }
+        }
+        let buf_clone = Arc::clone(buffer);
+        let mut meter = LevelMeter::new(levels, sample_rate);
+        let capture = CaptureSource::open(
+            device,
+            sample_rate,
+            pre_roll,
+            stream_error_handler("recording", errors),
+            errors,
+            move |mono| {
+                meter.push(mono);
+                buf_clone.lock().unwrap().extend(mono);
+            },
+        )?;
+        Ok((capture, sample_rate))
+    }
+
+    pub fn stop(mut self) -> FinishedRecording {
+        // Dropping the capture joins the consumer or detaches from the
+        // pre-roll, so the buffer is complete.
+        drop(self.capture);
+        let (current, spooled) = self.buffer.lock().unwrap().take();
+        self.spooled.extend(spooled);
+        if self.captured.is_empty() {
+            return FinishedRecording {
+                samples: current,
+                sample_rate: self.sample_rate,
+                spool_files: self.spooled,
+            };
+        }
+
+        // Bring audio from earlier devices to the rate of the last one.
+        let mut samples = Vec::new();
+        for (part, rate) in self.captured {
+            match audio::resample(&part, rate, self.sample_rate, audio::ResampleMode::Exact) {
+                Ok(resampled) => samples.extend(resampled),
+                Err(err) => eprintln!("[recording] dropping audio from the lost device: {err}"),
+            }
+        }
+        samples.extend(current);
+        FinishedRecording {
+            samples,
+            sample_rate: self.sample_rate,
+            spool_files: self.spooled,
+        }
     }
 }
 
@@ -133,99 +805,219 @@ unsafe impl Send for StreamingRecordingSession {}
 unsafe impl Sync for StreamingRecordingSession {}
 
 impl StreamingRecordingSession {
-    /// Start a streaming recording session that sends audio chunks via the channel
-    pub fn start(chunk_tx: mpsc::Sender<Vec<i16>>) -> Result<Self, String> {
-        let host = cpal::default_host();
-        let device = host
-            .default_input_device()
-            .ok_or_else(|| "No input device available".to_string())?;
-        let config = device
+    /// Start a streaming recording session that sends audio chunks of
+    /// `chunk_ms` via the channel. Samples that could not be delivered are
+    /// added to `dropped`.
+    pub fn start(
+        input_device_name: Option<&str>,
+        chunk_tx: mpsc::Sender<Vec<i16>>,
+        chunk_ms: u32,
+        pre_roll: Option<PreRollBuffer>,
+        dropped: Arc<AtomicU64>,
+        levels: LevelSender,
+    ) -> Result<(Self, StreamErrorReceiver), String> {
+        let device = input_device(input_device_name)?;
+        let sample_rate = device
             .default_input_config()
-            .map_err(|err| format!("Failed to get input config: {err}"))?;
-        let sample_rate = config.sample_rate().0;
-        let channels = config.channels() as usize;
+            .map_err(|err| format!("Failed to get input config: {err}"))?
+            .sample_rate()
+            .0;
+        let chunks = Arc::new(StdMutex::new(ChunkQueue::new(
+            sample_rate,
+            chunk_ms,
+            dropped,
+        )));
+        let (errors, error_rx) = mpsc::unbounded_channel();
+        let capture = Self::open_capture(
+            &device,
+            sample_rate,
+            &chunks,
+            &chunk_tx,
+            &errors,
+            &levels,
+            pre_roll,
+        )?;
+        Ok((
+            Self {
+                capture,
+                input_device_name: input_device_name.map(str::to_string),
+                sample_rate,
+                chunks,
+                chunk_tx,
+                errors,
+                levels,
+            },
+            error_rx,
+        ))
+    }
 
-        // Buffer to accumulate samples before sending (send every ~100ms)
-        let chunk_size = (sample_rate as usize) / 10; // 100ms of audio
-        let pending: Arc<StdMutex<Vec<i16>>> =
-            Arc::new(StdMutex::new(Vec::with_capacity(chunk_size)));
-        let pending_clone = Arc::clone(&pending);
+    /// Re-opens the stream after the current one failed, on the chosen
+    /// device when it is present and on the default one otherwise. The
+    /// realtime session was negotiated at `sample_rate`, so a device running
+    /// at another rate is refused. Returns the device name.
+    pub fn reopen(&mut self) -> Result<String, String> {
+        let device = input_device(self.input_device_name.as_deref())?;
+        let capture = Self::open_capture(
+            &device,
+            self.sample_rate,
+            &self.chunks,
+            &self.chunk_tx,
+            &self.errors,
+            &self.levels,
+            None,
+        )?;
+        self.capture = capture;
+        Ok(device_name(&device))
+    }
+
+    fn open_capture(
+        device: &cpal::Device,
+        sample_rate: u32,
+        chunks: &Arc<StdMutex<ChunkQueue>>,
+        chunk_tx: &mpsc::Sender<Vec<i16>>,
+        errors: &StreamErrorSender,
+        levels: &LevelSender,
+        pre_roll: Option<PreRollBuffer>,
+    ) -> Result<CaptureSource, String> {
+        let device_rate = device
+            .default_input_config()
+            .map_err(|err| format!("Failed to get input config: {err}"))?
+            .sample_rate()
+            .0;
+        if device_rate != sample_rate {
+            return Err(format!(
+                "{} runs at {device_rate} Hz, the session expects {sample_rate} Hz",
+                device_name(device)
+            ));
+        }
+        let chunks_clone = Arc::clone(chunks);
         let chunk_tx_clone = chunk_tx.clone();
+        let mut meter = LevelMeter::new(levels, sample_rate);
 
-        let err_fn = |err: cpal::StreamError| {
-            eprintln!("[streaming recording] stream error: {err}");
-        };
+        CaptureSource::open(
+            device,
+            sample_rate,
+            pre_roll,
+            stream_error_handler("streaming recording", errors),
+            errors,
+            move |mono| {
+                meter.push(mono);
+                let mut chunks = chunks_clone.lock().unwrap();
+                chunks.push(mono);
+                chunks.send(&chunk_tx_clone);
+            },
+        )
+    }
 
-        let stream = match config.sample_format() {
-            cpal::SampleFormat::F32 => device
-                .build_input_stream(
-                    &config.into(),
-                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                        let mut pending = pending_clone.lock().unwrap();
-                        for chunk in data.chunks(channels) {
-                            let sum: f32 = chunk.iter().sum();
-                            let mono = sum / channels as f32;
-                            let clamped = mono.clamp(-1.0, 1.0);
-                            let int_val = if clamped < 0.0 {
-                                (clamped * 0x8000 as f32) as i16
-                            } else {
-                                (clamped * 0x7FFF as f32) as i16
-                            };
-                            pending.push(int_val);
-                        }
-                        if pending.len() >= chunk_size {
-                            let samples: Vec<i16> = pending.drain(..).collect();
-                            let tx = chunk_tx_clone.clone();
-                            // Fire and forget - don't block the audio callback
-                            let _ = tx.try_send(samples);
-                        }
-                    },
-                    err_fn,
-                    None,
-                )
-                .map_err(|err| format!("Failed to build input stream: {err}"))?,
-            cpal::SampleFormat::I16 => {
-                let pending_clone = Arc::clone(&pending);
-                let chunk_tx_clone = chunk_tx.clone();
-                device
-                    .build_input_stream(
-                        &config.into(),
-                        move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                            let mut pending = pending_clone.lock().unwrap();
-                            if channels == 1 {
-                                pending.extend_from_slice(data);
-                            } else {
-                                for chunk in data.chunks(channels) {
-                                    let sum: i32 = chunk.iter().map(|&s| s as i32).sum();
-                                    pending.push((sum / channels as i32) as i16);
-                                }
-                            }
-                            if pending.len() >= chunk_size {
-                                let samples: Vec<i16> = pending.drain(..).collect();
-                                let tx = chunk_tx_clone.clone();
-                                let _ = tx.try_send(samples);
-                            }
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|err| format!("Failed to build input stream: {err}"))?
+    /// Stops capturing. Audio still queued, including the final partial
+    /// chunk, is delivered in the background.
+    pub fn stop(self) {
+        drop(self.capture);
+        let mut chunks = self.chunks.lock().unwrap();
+        chunks.send(&self.chunk_tx);
+        let remaining = chunks.take_remaining();
+        let dropped = Arc::clone(&chunks.dropped);
+        drop(chunks);
+        if remaining.is_empty() {
+            return;
+        }
+        let chunk_tx = self.chunk_tx.clone();
+        tauri::async_runtime::spawn(async move {
+            for chunk in remaining {
+                let len = chunk.len() as u64;
+                if chunk_tx.send(chunk).await.is_err() {
+                    dropped.fetch_add(len, Ordering::Relaxed);
+                }
             }
-            format => return Err(format!("Unsupported sample format: {format:?}")),
-        };
+        });
+    }
+}
 
-        stream
-            .play()
-            .map_err(|err| format!("Failed to start recording: {err}"))?;
+/// Length of each chunk sent to a realtime provider unless the settings
+/// choose another one.
+pub const DEFAULT_STREAM_CHUNK_MS: u32 = 100;
+/// Chunk lengths outside this range are clamped into it.
+const STREAM_CHUNK_MS_RANGE: (u32, u32) = (10, 1000);
+/// Audio held back while the realtime channel is full, e.g. during a slow
+/// reconnect. Older chunks are dropped beyond this.
+const STREAM_BACKLOG_SECONDS: usize = 30;
 
-        Ok(Self {
-            stream,
-            sample_rate,
-        })
+/// Cuts mono audio into fixed-size chunks for the realtime channel. Chunks
+/// the channel has no room for wait in a backlog instead of being dropped.
+struct ChunkQueue {
+    pending: Vec<i16>,
+    backlog: VecDeque<Vec<i16>>,
+    backlog_samples: usize,
+    chunk_size: usize,
+    max_backlog_samples: usize,
+    dropped: Arc<AtomicU64>,
+}
+
+impl ChunkQueue {
+    fn new(sample_rate: u32, chunk_ms: u32, dropped: Arc<AtomicU64>) -> Self {
+        let (min_ms, max_ms) = STREAM_CHUNK_MS_RANGE;
+        let chunk_ms = chunk_ms.clamp(min_ms, max_ms) as usize;
+        let chunk_size = (sample_rate as usize * chunk_ms / 1000).max(1);
+        Self {
+            pending: Vec::with_capacity(chunk_size),
+            backlog: VecDeque::new(),
+            backlog_samples: 0,
+            chunk_size,
+            max_backlog_samples: sample_rate as usize * STREAM_BACKLOG_SECONDS,
+            dropped,
+        }
     }
 
-    pub fn stop(self) {
-        drop(self.stream);
+    fn push(&mut self, mono: &[f32]) {
+        for &sample in mono {
+            self.pending.push(f32_to_i16(sample));
+            if self.pending.len() == self.chunk_size {
+                let chunk =
+                    std::mem::replace(&mut self.pending, Vec::with_capacity(self.chunk_size));
+                self.enqueue(chunk);
+            }
+        }
+    }
+
+    fn enqueue(&mut self, chunk: Vec<i16>) {
+        self.backlog_samples += chunk.len();
+        self.backlog.push_back(chunk);
+        while self.backlog_samples > self.max_backlog_samples {
+            let Some(oldest) = self.backlog.pop_front() else {
+                break;
+            };
+            self.backlog_samples -= oldest.len();
+            self.dropped
+                .fetch_add(oldest.len() as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Sends queued chunks in order until the channel is full.
+    fn send(&mut self, tx: &mpsc::Sender<Vec<i16>>) {
+        while let Some(chunk) = self.backlog.pop_front() {
+            let len = chunk.len();
+            match tx.try_send(chunk) {
+                Ok(()) => self.backlog_samples -= len,
+                Err(TrySendError::Full(chunk)) => {
+                    self.backlog.push_front(chunk);
+                    return;
+                }
+                Err(TrySendError::Closed(_)) => {
+                    self.backlog_samples -= len;
+                    self.dropped.fetch_add(len as u64, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Everything not yet sent, with the partial chunk last.
+    fn take_remaining(&mut self) -> Vec<Vec<i16>> {
+        let pending = std::mem::take(&mut self.pending);
+        if !pending.is_empty() {
+            self.backlog.push_back(pending);
+        }
+        self.backlog_samples = 0;
+        self.backlog.drain(..).collect()
     }
 }
 
@@ -236,7 +1028,21 @@ pub fn is_too_short(num_samples: usize, sample_rate: u32) -> bool {
 
 pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
     let data_size = (samples.len() * 2) as u32;
-    let mut buffer = Vec::with_capacity(44 + data_size as usize);
+    let mut buffer = wav_header(sample_rate, data_size);
+    buffer.reserve(data_size as usize);
+
+    for &sample in samples {
+        buffer.extend_from_slice(&f32_to_i16(sample).to_le_bytes());
+    }
+
+    buffer
+}
+
+const WAV_HEADER_LEN: u64 = 44;
+
+/// Header of a mono 16-bit PCM WAV file with `data_size` bytes of samples.
+fn wav_header(sample_rate: u32, data_size: u32) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(WAV_HEADER_LEN as usize);
 
     // RIFF header
     buffer.extend_from_slice(b"RIFF");
@@ -256,16 +1062,382 @@ pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
     // data chunk
     buffer.extend_from_slice(b"data");
     buffer.extend_from_slice(&data_size.to_le_bytes());
+    buffer
+}
 
-    for &sample in samples {
-        let clamped = sample.clamp(-1.0, 1.0);
-        let int_val = if clamped < 0.0 {
-            (clamped * 0x8000 as f32) as i16
-        } else {
-            (clamped * 0x7FFF as f32) as i16
+/// Writes the RIFF and data sizes for a file holding `data_size` bytes of
+/// samples after the header.
+fn patch_wav_sizes(file: &mut File, data_size: u32) -> std::io::Result<()> {
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&(36 + data_size).to_le_bytes())?;
+    file.seek(SeekFrom::Start(40))?;
+    file.write_all(&data_size.to_le_bytes())?;
+    file.sync_all()
+}
+
+/// Unflushed audio is limited to about a second, which is what a crash loses.
+const SPOOL_FLUSH_SECONDS: usize = 1;
+
+/// Writes a recording to a WAV file as it is captured, so the audio
+/// survives a crash. The header sizes are written by `finish`; files left
+/// behind by a crash are fixed up by `recover_spools`.
+struct WavSpool {
+    path: PathBuf,
+    file: BufWriter<File>,
+    data_size: u32,
+    unflushed: usize,
+    flush_every: usize,
+}
+
+impl WavSpool {
+    fn create(dir: &Path, sample_rate: u32) -> Result<Self, String> {
+        std::fs::create_dir_all(dir)
+            .map_err(|err| format!("Failed to create {}: {err}", dir.display()))?;
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or(0);
+        let mut attempt = 0;
+        let (path, file) = loop {
+            let path = dir.join(format!("recording-{stamp}-{attempt}.wav"));
+            match File::options().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => attempt += 1,
+                Err(err) => return Err(format!("Failed to create {}: {err}", path.display())),
+            }
         };
-        buffer.extend_from_slice(&int_val.to_le_bytes());
+        let mut file = BufWriter::new(file);
+        file.write_all(&wav_header(sample_rate, 0))
+            .map_err(|err| format!("Failed to write {}: {err}", path.display()))?;
+        Ok(Self {
+            path,
+            file,
+            data_size: 0,
+            unflushed: 0,
+            flush_every: sample_rate as usize * SPOOL_FLUSH_SECONDS,
+        })
     }
 
-    buffer
+    fn write(&mut self, mono: &[f32]) -> std::io::Result<()> {
+        for &sample in mono {
+            self.file.write_all(&f32_to_i16(sample).to_le_bytes())?;
+        }
+        self.data_size = self.data_size.saturating_add(mono.len() as u32 * 2);
+        self.unflushed += mono.len();
+        if self.unflushed >= self.flush_every {
+            self.file.flush()?;
+            self.unflushed = 0;
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<PathBuf, String> {
+        let path = self.path;
+        let fail = |err: std::io::Error| format!("Failed to finish {}: {err}", path.display());
+        let mut file = self
+            .file
+            .into_inner()
+            .map_err(|err| fail(err.into_error()))?;
+        patch_wav_sizes(&mut file, self.data_size).map_err(fail)?;
+        Ok(path)
+    }
+}
+
+/// A spooled recording found on disk at launch.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedRecording {
+    pub name: String,
+    pub modified_at: u64,
+    pub duration_secs: f64,
+}
+
+/// Spool files still in `dir` belong to recordings that were never
+/// transcribed, usually because the app crashed. Fixes their headers from
+/// the file length and lists them, dropping files without audio. Must run
+/// before any recording starts.
+pub fn recover_spools(dir: &Path) -> Vec<OrphanedRecording> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut orphans = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("wav") {
+            continue;
+        }
+        match repair_spool(&path) {
+            Ok(Some(orphan)) => orphans.push(orphan),
+            Ok(None) => {
+                let _ = std::fs::remove_file(&path);
+            }
+            Err(err) => eprintln!("[recording] skipping {}: {err}", path.display()),
+        }
+    }
+    orphans.sort_by_key(|orphan| orphan.modified_at);
+    orphans
+}
+
+fn repair_spool(path: &Path) -> Result<Option<OrphanedRecording>, String> {
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|err| err.to_string())?;
+    let mut header = [0u8; WAV_HEADER_LEN as usize];
+    file.read_exact(&mut header)
+        .map_err(|err| format!("no WAV header: {err}"))?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Err("not a WAV file".to_string());
+    }
+    let sample_rate = u32::from_le_bytes([header[24], header[25], header[26], header[27]]);
+    let metadata = file.metadata().map_err(|err| err.to_string())?;
+    // A crash can cut the last sample in half.
+    let data_size = (metadata.len().saturating_sub(WAV_HEADER_LEN) & !1).min(u32::MAX as u64);
+    if data_size == 0 || sample_rate == 0 {
+        return Ok(None);
+    }
+    patch_wav_sizes(&mut file, data_size as u32).map_err(|err| err.to_string())?;
+
+    let modified_at = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0);
+    Ok(Some(OrphanedRecording {
+        name: path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_string(),
+        modified_at,
+        duration_secs: (data_size / 2) as f64 / sample_rate as f64,
+    }))
+}
+
+/// Rate cloud uploads are resampled to; speech loses nothing below it.
+const UPLOAD_SAMPLE_RATE: u32 = 16_000;
+
+/// Container a finished recording is uploaded in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UploadFormat {
+    /// 16-bit WAV at the capture rate. Local engines decode it fastest.
+    Wav,
+    /// FLAC at 16 kHz, about a sixth of the WAV size for cloud uploads.
+    Flac,
+}
+
+impl UploadFormat {
+    /// FLAC for cloud models when `flac_uploads` is on, WAV otherwise.
+    pub fn for_model(model_id: &str, flac_uploads: bool) -> Self {
+        if crate::is_cloud_model(model_id) && flac_uploads {
+            Self::Flac
+        } else {
+            Self::Wav
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::Flac => "flac",
+        }
+    }
+}
+
+/// Encodes a finished mono recording for upload.
+pub fn encode_upload(samples: &[f32], sample_rate: u32, format: UploadFormat) -> Vec<u8> {
+    match format {
+        UploadFormat::Wav => encode_wav(samples, sample_rate),
+        UploadFormat::Flac => {
+            let rate = sample_rate.min(UPLOAD_SAMPLE_RATE);
+            let resampled =
+                match audio::resample(samples, sample_rate, rate, audio::ResampleMode::Exact) {
+                    Ok(resampled) => resampled,
+                    Err(err) => {
+                        eprintln!("[recording] {err}; uploading at {sample_rate} Hz");
+                        return crate::flac::encode(&to_i16(samples), sample_rate);
+                    }
+                };
+            crate::flac::encode(&to_i16(&resampled), rate)
+        }
+    }
+}
+
+fn to_i16(samples: &[f32]) -> Vec<i16> {
+    samples.iter().map(|&sample| f32_to_i16(sample)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_ring_drops_whole_buffers_and_keeps_frames_aligned() {
+        // Room for two and a half stereo frames.
+        let (mut producer, mut consumer) = RingBuffer::<f32>::new(5);
+        let mut mixer = MonoMixer::new(2);
+        let mut mono = Vec::new();
+        let buffer = [0.5f32, -0.5, 1.0, 0.0];
+
+        assert!(push_capture_buffer(&mut producer, &buffer));
+        // One slot is free; none of the next buffer may go in.
+        assert!(!push_capture_buffer(&mut producer, &buffer));
+        assert_eq!(consumer.slots(), 4);
+
+        assert!(drain_capture(&mut consumer, &mut mixer, &mut mono));
+        assert_eq!(mono, [0.0, 0.5]);
+        assert!(!drain_capture(&mut consumer, &mut mixer, &mut mono));
+
+        // The ring wraps here, so the drain sees two slices.
+        assert!(push_capture_buffer(&mut producer, &buffer));
+        assert!(drain_capture(&mut consumer, &mut mixer, &mut mono));
+        assert_eq!(mono, [0.0, 0.5]);
+    }
+
+    fn chunk_queue(sample_rate: u32, chunk_ms: u32) -> (ChunkQueue, Arc<AtomicU64>) {
+        let dropped = Arc::new(AtomicU64::new(0));
+        (
+            ChunkQueue::new(sample_rate, chunk_ms, dropped.clone()),
+            dropped,
+        )
+    }
+
+    #[test]
+    fn chunk_size_follows_the_clamped_chunk_length() {
+        assert_eq!(chunk_queue(16_000, 100).0.chunk_size, 1600);
+        assert_eq!(chunk_queue(16_000, 1).0.chunk_size, 160);
+        assert_eq!(chunk_queue(16_000, 60_000).0.chunk_size, 16_000);
+    }
+
+    #[test]
+    fn backlog_drops_the_oldest_audio_past_its_cap() {
+        let (mut queue, dropped) = chunk_queue(100, 100);
+        let (tx, mut rx) = mpsc::channel(1);
+        // One chunk fills the channel, then 30 s fill the backlog exactly.
+        queue.push(&[0.0; 10]);
+        queue.send(&tx);
+        queue.push(&vec![0.0; 100 * STREAM_BACKLOG_SECONDS]);
+        queue.send(&tx);
+        assert_eq!(dropped.load(Ordering::Relaxed), 0);
+
+        // Two more chunks push the two oldest waiting ones out.
+        queue.push(&[0.5; 20]);
+        assert_eq!(dropped.load(Ordering::Relaxed), 20);
+        assert_eq!(queue.backlog_samples, 100 * STREAM_BACKLOG_SECONDS);
+        assert_eq!(rx.try_recv().unwrap().len(), 10);
+        queue.send(&tx);
+        assert_eq!(rx.try_recv().unwrap(), vec![0; 10]);
+        let remaining = queue.take_remaining();
+        assert_eq!(remaining.last().unwrap(), &vec![f32_to_i16(0.5); 10]);
+    }
+
+    #[test]
+    fn chunks_sent_to_a_closed_channel_count_as_dropped() {
+        let (mut queue, dropped) = chunk_queue(100, 100);
+        let (tx, rx) = mpsc::channel(4);
+        drop(rx);
+        queue.push(&[0.0; 25]);
+        queue.send(&tx);
+        assert_eq!(dropped.load(Ordering::Relaxed), 20);
+        assert_eq!(queue.backlog_samples, 0);
+        assert_eq!(queue.take_remaining(), vec![vec![0; 5]]);
+    }
+
+    #[test]
+    fn pre_roll_tap_joins_the_buffer_and_live_audio_without_overlap() {
+        // 100 ms at 1 kHz keeps the last 100 samples.
+        let pre_roll = PreRollBuffer::new("mic".to_string(), 1_000, 100);
+        let ramp: Vec<f32> = (0..600).map(|i| i as f32).collect();
+        for chunk in ramp[..300].chunks(64) {
+            pre_roll.push(chunk);
+        }
+
+        let joined = Arc::new(StdMutex::new(Vec::new()));
+        let (errors, _error_rx) = mpsc::unbounded_channel();
+        let tap = pre_roll.tap(&errors, {
+            let joined = Arc::clone(&joined);
+            move |mono| joined.lock().unwrap().extend_from_slice(mono)
+        });
+        for chunk in ramp[300..500].chunks(64) {
+            pre_roll.push(chunk);
+        }
+        drop(tap);
+        pre_roll.push(&ramp[500..]);
+
+        assert_eq!(*joined.lock().unwrap(), ramp[200..500]);
+    }
+
+    #[test]
+    fn lost_pre_roll_is_not_tapped_until_rebuilt() {
+        let pre_roll = PreRollBuffer::new("mic".to_string(), 1_000, 100);
+        assert!(pre_roll.serves("mic", 1_000));
+        let (errors, mut error_rx) = mpsc::unbounded_channel();
+        let tap = pre_roll.tap(&errors, |_| {});
+
+        // The device disappears: the tapping recording hears about it and
+        // the stream is never offered again, even under the same name.
+        pre_roll.stream_error(cpal::StreamError::DeviceNotAvailable);
+        assert!(matches!(
+            error_rx.try_recv(),
+            Ok(cpal::StreamError::DeviceNotAvailable)
+        ));
+        drop(tap);
+        assert!(!pre_roll.serves("mic", 1_000));
+
+        // It comes back and the pre-roll is rebuilt on it.
+        let rebuilt = PreRollBuffer::new("mic".to_string(), 1_000, 100);
+        assert!(rebuilt.serves("mic", 1_000));
+    }
+
+    fn spool_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("openstt-spool-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn finished_spool_has_the_header_sizes() {
+        let dir = spool_dir("finish");
+        let mut spool = WavSpool::create(&dir, 8_000).unwrap();
+        spool.write(&[0.25; 100]).unwrap();
+        spool.write(&[-0.25; 20]).unwrap();
+        let path = spool.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), WAV_HEADER_LEN as usize + 240);
+        assert_eq!(&bytes[..WAV_HEADER_LEN as usize], wav_header(8_000, 240));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn recovery_repairs_cut_spools_and_removes_empty_ones() {
+        let dir = spool_dir("recover");
+        // Dropping a spool without `finish` leaves the sizes at zero, as a
+        // crash does.
+        let mut cut = WavSpool::create(&dir, 8_000).unwrap();
+        cut.write(&[0.5; 400]).unwrap();
+        let cut_path = cut.path.clone();
+        drop(cut);
+        let mut file = File::options().append(true).open(&cut_path).unwrap();
+        file.write_all(&[0x12]).unwrap();
+        drop(file);
+        let empty_path = WavSpool::create(&dir, 8_000).unwrap().path.clone();
+        let other_path = dir.join("notes.txt");
+        std::fs::write(&other_path, b"keep").unwrap();
+
+        let orphans = recover_spools(&dir);
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(
+            orphans[0].name,
+            cut_path.file_name().unwrap().to_str().unwrap()
+        );
+        assert_eq!(orphans[0].duration_secs, 0.05);
+        let bytes = std::fs::read(&cut_path).unwrap();
+        assert_eq!(&bytes[..WAV_HEADER_LEN as usize], wav_header(8_000, 800));
+        assert!(!empty_path.exists());
+        assert!(other_path.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -7,6 +7,8 @@ use tokio_tungstenite::tungstenite::Message;
 
 const SONIOX_WEBSOCKET_URL: &str = "wss://stt-rt.soniox.com/transcribe-websocket";
 const SONIOX_REALTIME_MODEL: &str = "stt-rt-v4";
+/// Rates we send as `pcm_s16le`.
+pub const SAMPLE_RATES: &[u32] = &[8_000, 16_000, 24_000, 44_100, 48_000];
 
 #[derive(Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -18,9 +20,17 @@ struct StartConfig {
     num_channels: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     language_hints: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<StartContext>,
     enable_endpoint_detection: bool,
 }
 
+/// Words the model should expect, such as names and jargon.
+#[derive(Serialize)]
+struct StartContext {
+    terms: Vec<String>,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "snake_case")]
 struct WsToken {
@@ -68,6 +78,7 @@ impl RealtimeSession {
         api_key: &str,
         sample_rate: u32,
         language: Option<String>,
+        terms: Vec<String>,
     ) -> Result<Self, String> {
         eprintln!(
             "[soniox] start, key_len: {}, sample_rate: {}",
@@ -108,16 +119,18 @@ impl RealtimeSession {
             sample_rate,
             num_channels: 1,
             language_hints: language.map(|lang| vec![lang]),
+            context: (!terms.is_empty()).then_some(StartContext { terms }),
             // Push-to-talk: we finalize manually on key release.
             enable_endpoint_detection: false,
         };
         eprintln!(
-            "[soniox] sending config: model={}, audio_format={}, sample_rate={}, channels={}, language_hints={:?}, endpointing={}",
+            "[soniox] sending config: model={}, audio_format={}, sample_rate={}, channels={}, language_hints={:?}, terms={}, endpointing={}",
             config.model,
             config.audio_format,
             config.sample_rate,
             config.num_channels,
             config.language_hints,
+            config.context.as_ref().map_or(0, |context| context.terms.len()),
             config.enable_endpoint_detection
         );
 
@@ -0,0 +1,254 @@
+use std::time::{Duration, Instant};
+
+/// Insertions kept for undo; older ones are forgotten.
+const MAX_ENTRIES: usize = 50;
+/// Undo only reaches back this long after the last insertion. Past it the
+/// cursor has likely moved, so backspaces could hit unrelated text.
+const EXPIRY: Duration = Duration::from_secs(60);
+
+/// Ways to take back dictated text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EditCommand {
+    /// Removes everything the last dictation inserted.
+    UndoLast,
+    /// Removes the last sentence of the last dictation.
+    DeleteLastSentence,
+}
+
+/// Spoken phrases, matched against a whole utterance. Both languages are
+/// always checked since the speech models pick the language themselves.
+const COMMANDS: &[(&str, EditCommand)] = &[
+    ("scratch that", EditCommand::UndoLast),
+    ("undo that", EditCommand::UndoLast),
+    ("undo last dictation", EditCommand::UndoLast),
+    ("delete last sentence", EditCommand::DeleteLastSentence),
+    ("delete the last sentence", EditCommand::DeleteLastSentence),
+    ("删掉刚才的", EditCommand::UndoLast),
+    ("撤销刚才的", EditCommand::UndoLast),
+    ("删除上一句", EditCommand::DeleteLastSentence),
+    ("删掉上一句", EditCommand::DeleteLastSentence),
+];
+
+/// The edit command an utterance consists of, ignoring case and the
+/// punctuation a model adds around it.
+pub fn edit_command(text: &str) -> Option<EditCommand> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect();
+    let spoken = words.join(" ");
+    COMMANDS
+        .iter()
+        .find(|(phrase, _)| *phrase == spoken)
+        .map(|(_, command)| *command)
+}
+
+fn ends_sentence(c: char) -> bool {
+    matches!(c, '.' | '?' | '!' | '\n' | '。' | '？' | '！')
+}
+
+/// Text dictation inserted into other apps, newest last. Undo assumes the
+/// cursor is still where the last insertion ended.
+#[derive(Default)]
+pub struct TypedHistory {
+    entries: Vec<String>,
+    last_insert: Option<Instant>,
+    /// Whether `append` extends the last entry rather than starting one.
+    session_open: bool,
+}
+
+impl TypedHistory {
+    /// Records the whole text of one dictation as its own entry.
+    pub fn push(&mut self, text: &str, now: Instant) {
+        if text.is_empty() {
+            return;
+        }
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.entries.push(text.to_string());
+        self.last_insert = Some(now);
+        self.session_open = false;
+    }
+
+    /// Records text a realtime dictation committed. Every commit of the
+    /// dictation goes into one entry, so undo removes the dictation as a
+    /// whole.
+    pub fn append(&mut self, text: &str, now: Instant) {
+        if text.is_empty() {
+            return;
+        }
+        match self.entries.last_mut() {
+            Some(last) if self.session_open => {
+                last.push_str(text);
+                self.last_insert = Some(now);
+            }
+            _ => {
+                self.push(text, now);
+                self.session_open = true;
+            }
+        }
+    }
+
+    /// Called when a dictation starts. Forgets every insertion once the
+    /// history has expired, so the new dictation cannot undo into text from
+    /// an earlier session. A spoken undo is a dictation of its own, so a
+    /// recent history is kept for it.
+    pub fn start_dictation(&mut self, now: Instant) {
+        if self.is_expired(now) {
+            self.entries.clear();
+            self.last_insert = None;
+        }
+        self.session_open = false;
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        self.last_insert.map_or(true, |last_insert| {
+            now.saturating_duration_since(last_insert) > EXPIRY
+        })
+    }
+
+    /// Forgets the text the command removes and returns how many characters
+    /// to delete for it; 0 when there is nothing left to remove or the
+    /// history has expired.
+    pub fn take(&mut self, command: EditCommand, now: Instant) -> usize {
+        if self.is_expired(now) {
+            return 0;
+        }
+        let Some(last) = self.entries.last_mut() else {
+            return 0;
+        };
+        let keep = match command {
+            EditCommand::UndoLast => 0,
+            EditCommand::DeleteLastSentence => {
+                let body = last
+                    .trim_end()
+                    .trim_end_matches(|c: char| ends_sentence(c) && c != '\n');
+                body.char_indices()
+                    .rev()
+                    .find(|(_, c)| ends_sentence(*c))
+                    .map_or(0, |(index, c)| index + c.len_utf8())
+            }
+        };
+        let removed = last[keep..].chars().count();
+        if keep == 0 {
+            self.entries.pop();
+            self.session_open = false;
+        } else {
+            last.truncate(keep);
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_whole_utterances_only() {
+        assert_eq!(edit_command("Scratch that."), Some(EditCommand::UndoLast));
+        assert_eq!(edit_command(" undo that "), Some(EditCommand::UndoLast));
+        assert_eq!(
+            edit_command("Delete the last sentence!"),
+            Some(EditCommand::DeleteLastSentence)
+        );
+        assert_eq!(
+            edit_command("删除上一句。"),
+            Some(EditCommand::DeleteLastSentence)
+        );
+        assert_eq!(edit_command("We should scratch that idea."), None);
+        assert_eq!(edit_command(""), None);
+    }
+
+    #[test]
+    fn undo_removes_insertions_newest_first() {
+        let now = Instant::now();
+        let mut history = TypedHistory::default();
+        history.push("Hello.", now);
+        history.push(" How are you?", now);
+        assert_eq!(history.take(EditCommand::UndoLast, now), 13);
+        assert_eq!(history.take(EditCommand::UndoLast, now), 6);
+        assert_eq!(history.take(EditCommand::UndoLast, now), 0);
+    }
+
+    #[test]
+    fn undo_removes_every_commit_of_a_realtime_dictation() {
+        let now = Instant::now();
+        let mut history = TypedHistory::default();
+        history.push("Earlier.", now);
+        history.start_dictation(now);
+        history.append("First phrase.", now);
+        history.append(" Second phrase.", now);
+        history.append(" Third.", now);
+        assert_eq!(history.entries.len(), 2);
+        assert_eq!(history.take(EditCommand::UndoLast, now), 35);
+        assert_eq!(history.entries, ["Earlier."]);
+
+        // Commits after the undo, and the next dictation, start new entries.
+        history.append("Again.", now);
+        history.start_dictation(now);
+        history.append("Next.", now);
+        assert_eq!(history.entries, ["Earlier.", "Again.", "Next."]);
+    }
+
+    #[test]
+    fn delete_last_sentence_keeps_earlier_sentences() {
+        let now = Instant::now();
+        let mut history = TypedHistory::default();
+        history.push("First one. Second one? Third one. ", now);
+        assert_eq!(history.take(EditCommand::DeleteLastSentence, now), 12);
+        assert_eq!(history.entries, ["First one. Second one?"]);
+        assert_eq!(history.take(EditCommand::DeleteLastSentence, now), 12);
+        assert_eq!(history.take(EditCommand::DeleteLastSentence, now), 10);
+        assert!(history.entries.is_empty());
+    }
+
+    #[test]
+    fn delete_last_sentence_stops_at_line_breaks_and_cjk_marks() {
+        let now = Instant::now();
+        let mut history = TypedHistory::default();
+        history.push("Title\nBody text", now);
+        assert_eq!(history.take(EditCommand::DeleteLastSentence, now), 9);
+        history.push("你好。再见。", now);
+        assert_eq!(history.take(EditCommand::DeleteLastSentence, now), 3);
+        assert_eq!(history.entries, ["Title\n", "你好。"]);
+    }
+
+    #[test]
+    fn keeps_a_bounded_number_of_insertions() {
+        let now = Instant::now();
+        let mut history = TypedHistory::default();
+        for index in 0..MAX_ENTRIES + 5 {
+            history.push(&index.to_string(), now);
+        }
+        history.push("", now);
+        assert_eq!(history.entries.len(), MAX_ENTRIES);
+        assert_eq!(history.entries[0], "5");
+    }
+
+    #[test]
+    fn expires_a_minute_after_the_last_insertion() {
+        let start = Instant::now();
+        let mut history = TypedHistory::default();
+        history.push("Hello.", start);
+        history.push(" Again.", start + Duration::from_secs(30));
+
+        let late = start + Duration::from_secs(30) + EXPIRY + Duration::from_secs(1);
+        assert_eq!(history.take(EditCommand::UndoLast, late), 0);
+        assert_eq!(history.entries.len(), 2);
+
+        history.start_dictation(start + EXPIRY);
+        assert_eq!(history.entries.len(), 2);
+        assert_eq!(history.take(EditCommand::UndoLast, start + EXPIRY), 7);
+
+        history.start_dictation(late);
+        assert!(history.entries.is_empty());
+        history.push("New.", late);
+        assert_eq!(history.take(EditCommand::UndoLast, late), 4);
+    }
+}
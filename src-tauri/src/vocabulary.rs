@@ -0,0 +1,192 @@
+use regex::{NoExpand, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// Whisper keeps only the last 224 tokens of its prompt, so the term list is
+/// cut well before that.
+const MAX_PROMPT_CHARS: usize = 600;
+
+/// Words the speech models should expect, and fixes applied to dictated text.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Vocabulary {
+    /// Product names, acronyms and jargon, one per entry.
+    pub terms: Vec<String>,
+    /// Applied in order to every final dictation transcript.
+    pub replacements: Vec<ReplacementRule>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReplacementRule {
+    pub find: String,
+    pub replace: String,
+    /// `find` is a regular expression and `replace` may use `$1` groups.
+    /// Literal rules only match whole words.
+    pub regex: bool,
+    pub case_sensitive: bool,
+}
+
+impl Vocabulary {
+    /// The terms, trimmed and without blanks or duplicates.
+    pub fn terms(&self) -> Vec<String> {
+        let mut terms: Vec<String> = Vec::new();
+        for term in &self.terms {
+            let term = term.trim();
+            if !term.is_empty() && !terms.iter().any(|known| known == term) {
+                terms.push(term.to_string());
+            }
+        }
+        terms
+    }
+}
+
+/// Whisper's initial prompt: the request prompt followed by as many terms as
+/// fit. Seeing the spellings in the prompt makes whisper reuse them.
+pub fn whisper_prompt(prompt: Option<&str>, terms: &[String]) -> Option<String> {
+    let mut listed = String::new();
+    for term in terms {
+        if listed.len() + term.len() + 2 > MAX_PROMPT_CHARS {
+            break;
+        }
+        if !listed.is_empty() {
+            listed.push_str(", ");
+        }
+        listed.push_str(term);
+    }
+    let prompt = prompt.map(str::trim).filter(|prompt| !prompt.is_empty());
+    match (prompt, listed.is_empty()) {
+        (prompt, true) => prompt.map(str::to_string),
+        (None, false) => Some(format!("{listed}.")),
+        (Some(prompt), false) => Some(format!("{prompt} {listed}.")),
+    }
+}
+
+/// Compiled replacement rules.
+#[derive(Default)]
+pub struct Replacer {
+    rules: Vec<(Regex, String, bool)>,
+}
+
+impl Replacer {
+    pub fn new(rules: &[ReplacementRule]) -> Result<Self, String> {
+        let mut compiled = Vec::with_capacity(rules.len());
+        for rule in rules {
+            if rule.find.trim().is_empty() {
+                continue;
+            }
+            let pattern = if rule.regex {
+                rule.find.clone()
+            } else {
+                literal_pattern(rule.find.trim())
+            };
+            let regex = RegexBuilder::new(&pattern)
+                .case_insensitive(!rule.case_sensitive)
+                .build()
+                .map_err(|err| format!("Invalid replacement pattern {:?}: {err}", rule.find))?;
+            compiled.push((regex, rule.replace.clone(), rule.regex));
+        }
+        Ok(Self { rules: compiled })
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (regex, replace, expand) in &self.rules {
+            let replaced = if *expand {
+                regex.replace_all(&text, replace.as_str())
+            } else {
+                regex.replace_all(&text, NoExpand(replace))
+            };
+            text = replaced.into_owned();
+        }
+        text
+    }
+}
+
+/// Escapes `find`, anchored at word boundaries where it starts or ends with
+/// a word character, so "SLO" does not match inside "SLOW".
+fn literal_pattern(find: &str) -> String {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let mut pattern = regex::escape(find);
+    if is_word(find.chars().next()) {
+        pattern.insert_str(0, r"\b");
+    }
+    if is_word(find.chars().last()) {
+        pattern.push_str(r"\b");
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(find: &str, replace: &str, regex: bool) -> ReplacementRule {
+        ReplacementRule {
+            find: find.to_string(),
+            replace: replace.to_string(),
+            regex,
+            case_sensitive: false,
+        }
+    }
+
+    #[test]
+    fn literal_rules_match_whole_words_in_any_case() {
+        let replacer = Replacer::new(&[
+            rule("open s t t", "OpenSTT", false),
+            rule("slo", "SLO", false),
+            rule("$5", "five dollars", false),
+        ])
+        .unwrap();
+        assert_eq!(
+            replacer.apply("Open S T T tracks the slo, slowly, for $5."),
+            "OpenSTT tracks the SLO, slowly, for five dollars."
+        );
+    }
+
+    #[test]
+    fn regex_rules_expand_groups_in_order() {
+        let replacer = Replacer::new(&[
+            rule(r"cube (control|cuddle)", "kube$1", true),
+            rule("kubecuddle", "kubectl", false),
+        ])
+        .unwrap();
+        assert_eq!(replacer.apply("Run cube cuddle apply"), "Run kubectl apply");
+    }
+
+    #[test]
+    fn invalid_regex_is_reported() {
+        let err = Replacer::new(&[rule("(unclosed", "", true)]).err().unwrap();
+        assert!(err.contains("(unclosed"), "{err}");
+    }
+
+    #[test]
+    fn prompt_lists_unique_terms_after_the_request_prompt() {
+        let vocabulary = Vocabulary {
+            terms: vec![
+                " Kubernetes".into(),
+                "OpenSTT".into(),
+                "".into(),
+                "Kubernetes".into(),
+            ],
+            replacements: Vec::new(),
+        };
+        let terms = vocabulary.terms();
+        assert_eq!(
+            whisper_prompt(None, &terms).as_deref(),
+            Some("Kubernetes, OpenSTT.")
+        );
+        assert_eq!(
+            whisper_prompt(Some("Meeting notes."), &terms).as_deref(),
+            Some("Meeting notes. Kubernetes, OpenSTT.")
+        );
+        assert_eq!(whisper_prompt(Some(" "), &[]), None);
+    }
+
+    #[test]
+    fn prompt_stops_before_the_length_limit() {
+        let terms: Vec<String> = (0..500).map(|i| format!("term{i}")).collect();
+        let prompt = whisper_prompt(None, &terms).unwrap();
+        assert!(prompt.len() <= MAX_PROMPT_CHARS + 1);
+        assert!(prompt.starts_with("term0, term1,"));
+    }
+}
@@ -27,27 +27,87 @@ type ServerStatus = {
   requests: number;
 };
 
+type AudioPreprocess = {
+  dcRemoval: boolean;
+  highPassHz: number;
+  normalize: "off" | "peak" | "lufs";
+  noiseGate: boolean;
+};
+
+const audioPreprocessOff: AudioPreprocess = {
+  dcRemoval: false,
+  highPassHz: 0,
+  normalize: "off",
+  noiseGate: false,
+};
+
+const audioPreprocessRecommended: AudioPreprocess = {
+  dcRemoval: true,
+  highPassHz: 80,
+  normalize: "lufs",
+  noiseGate: true,
+};
+
+const isAudioPreprocessOn = (value: AudioPreprocess) =>
+  value.dcRemoval ||
+  value.highPassHz > 0 ||
+  value.normalize !== "off" ||
+  value.noiseGate;
+
 type UiSettings = {
   reducedTransparency: boolean;
   language: "en" | "zh";
   dictationShortcut: DictationShortcut;
+  undoShortcut: DictationShortcut | null;
   dictationAutoPaste: boolean;
   elevenlabsApiKey: string;
   sonioxApiKey: string;
   sonioxWarmConnection: boolean;
   sonioxWarmConnectionMinutes: number;
   sonioxWarmConnectionForever: boolean;
+  dictationVad: boolean;
+  audioPreprocess: AudioPreprocess;
+  transcriptCache: boolean;
+  inputDevice: string;
+  preRollMs: number;
+  streamChunkMs: number;
+  autoStopSilenceSecs: number;
+  maxRecordingSecs: number;
+  spoolRecordings: boolean;
+  flacUploads: boolean;
+  localLiveDictation: boolean;
+  vocabulary: Vocabulary;
+  spokenPunctuation: boolean;
+  historyEnabled: boolean;
+  historyKeepAudio: boolean;
+  historyGateway: boolean;
+};
+
+type ReplacementRule = {
+  find: string;
+  replace: string;
+  regex: boolean;
+  caseSensitive: boolean;
+};
+
+type Vocabulary = {
+  terms: string[];
+  replacements: ReplacementRule[];
 };
 
 type SonioxRealtimeStatus = {
   connected: boolean;
   warmUntilMs: number | null;
   warmForever: boolean;
+  droppedSamples: number;
 };
 
+type ShortcutMode = "hold" | "toggle" | "doubleTap";
+
 type DictationShortcut = {
   key: string;
   modifiers: string[];
+  mode?: ShortcutMode;
 };
 
 type ModelInfo = {
@@ -66,6 +126,26 @@ type DictationStateEvent = {
   queueLen: number;
 };
 
+type RecordingErrorEvent = {
+  message: string;
+  fallbackDevice: string | null;
+};
+
+type OrphanedRecording = {
+  name: string;
+  modifiedAt: number;
+  durationSecs: number;
+};
+
+type InputLevelEvent = {
+  rms: number;
+  peak: number;
+};
+
+// Maps an RMS amplitude onto 0..1 over a -60..0 dBFS scale.
+const levelToMeter = (rms: number) =>
+  rms > 0 ? Math.min(1, Math.max(0, (20 * Math.log10(rms) + 60) / 60)) : 0;
+
 type PlaygroundTranscriptionResult = {
   text: string;
   error: string | null;
@@ -93,6 +173,11 @@ type LegacyModelsInfo = {
   sizeBytes: number;
 };
 
+type InputDevice = {
+  name: string;
+  isDefault: boolean;
+};
+
 type PermissionStatus = {
   accessibility: boolean;
   microphone: string;
@@ -132,6 +217,7 @@ const detectSystemLanguage = (): "en" | "zh" => {
 const defaultDictationShortcut: DictationShortcut = {
   key: "AltLeft",
   modifiers: [],
+  mode: "hold",
 };
 
 const isModifierKey = (code: string) =>
@@ -150,7 +236,7 @@ const normalizeDictationShortcut = (
   shortcut: DictationShortcut,
 ): DictationShortcut => {
   if (isModifierKey(shortcut.key)) {
-    return { key: shortcut.key, modifiers: [] };
+    return { key: shortcut.key, modifiers: [], mode: shortcut.mode };
   }
   const normalized = new Set(
     shortcut.modifiers
@@ -160,7 +246,7 @@ const normalizeDictationShortcut = (
   const ordered = ["command", "control", "alt", "shift"].filter((value) =>
     normalized.has(value),
   );
-  return { key: shortcut.key, modifiers: ordered };
+  return { key: shortcut.key, modifiers: ordered, mode: shortcut.mode };
 };
 
 const formatShortcutLabel = (shortcut: DictationShortcut) => {
@@ -337,13 +423,81 @@ const translations = {
     languageHint: "Display language",
     dictationTitle: "Dictation",
     dictationDesc: "Hold the shortcut to record, release to transcribe",
-    dictationShortcutLabel: "Hold shortcut",
+    dictationShortcutLabel: "Dictation shortcut",
     dictationShortcutHint: "Hold to record, release to transcribe",
+    dictationShortcutToggleHint: "Press to record, press again to transcribe",
+    dictationShortcutDoubleTapHint:
+      "Hold to record, or double-tap to record until the next press",
+    shortcutMode: "Shortcut mode",
+    shortcutModeHint: "How the shortcut starts and stops dictation",
+    shortcutModeHold: "Hold to talk",
+    shortcutModeToggle: "Toggle",
+    shortcutModeDoubleTap: "Double-tap to lock",
     dictationShortcutCaptureHint: "Press new shortcut (Esc to cancel)",
     dictationShortcutChange: "Change",
     dictationShortcutListening: "Listening...",
+    undoShortcutLabel: "Undo last dictation",
+    undoShortcutHint:
+      "Deletes the text of the last dictation. Saying \"scratch that\" or \"delete last sentence\" also works",
+    undoShortcutNone: "Not set",
+    undoShortcutClear: "Clear",
     dictationAutoPaste: "Auto paste",
     dictationAutoPasteHint: "Paste transcript to the active app after copying",
+    dictationVad: "Skip silence",
+    dictationVadHint: "Cut silent stretches before local transcription",
+    audioPreprocess: "Clean up microphone audio",
+    audioPreprocessHint: "Remove hum and noise and even out the volume",
+    transcriptCache: "Cache transcripts",
+    transcriptCacheHint: "Reuse results when the same file is transcribed again",
+    inputDevice: "Microphone",
+    inputDeviceHint: "Falls back to the system default when unplugged",
+    inputDeviceDefault: "System default",
+    preRoll: "Pre-roll",
+    preRollHint: "Keep the microphone open so words spoken with the shortcut are not cut off",
+    preRollOff: "Off",
+    streamChunk: "Live audio chunk",
+    streamChunkHint: "How much audio is sent to live transcription at a time; smaller chunks show words sooner",
+    autoStopSilence: "Stop on silence",
+    autoStopSilenceHint: "End dictation after a pause of this length",
+    maxRecording: "Maximum length",
+    maxRecordingHint: "End dictation that runs longer than this",
+    limitOff: "Off",
+    seconds: "{count} s",
+    minutes: "{count} min",
+    spoolRecordings: "Save recordings while dictating",
+    spoolRecordingsHint: "Write audio to disk as it is captured so a crash does not lose it",
+    flacUploads: "Compress cloud uploads",
+    flacUploadsHint: "Send dictations to cloud models as FLAC, about a sixth of the WAV size",
+    localLiveDictation: "Live dictation with local models",
+    localLiveDictationHint: "Type each phrase when you pause instead of after releasing the shortcut",
+    spokenPunctuation: "Spoken punctuation",
+    spokenPunctuationHint:
+      "Say \"comma\", \"new line\", \"open quote\" or \"all caps\" to format the text",
+    historyEnabled: "Transcription history",
+    historyEnabledHint: "Save dictation and playground transcripts on this computer",
+    historyKeepAudio: "Keep audio in history",
+    historyKeepAudioHint: "Store each recording so it can be transcribed again with another model",
+    historyGateway: "Include gateway requests",
+    historyGatewayHint: "Also save transcripts other apps request through the local gateway",
+    vocabulary: "Vocabulary",
+    vocabularyHint: "Names and terms the models should expect, one per line",
+    vocabularyPlaceholder: "Kubernetes\nOpenSTT\nSLO",
+    replacementRules: "Replacements",
+    replacementRulesHint:
+      "Fix words that still come out wrong; literal text matches whole words",
+    replacementFind: "Find",
+    replacementReplace: "Replace with",
+    replacementRegex: "Regex",
+    replacementRemove: "Remove",
+    replacementAdd: "Add rule",
+    recoveredRecordings: "Unfinished recordings from a previous session",
+    recoveredRecording: "{date}, {duration}",
+    recoveredTranscribe: "Transcribe",
+    recoveredDiscard: "Discard",
+    recoveredTranscribed: "Recovered recording added to the Playground",
+    recordingDeviceLost: "Microphone disconnected, recording continues on {device}",
+    recordingError: "Recording error: {message}",
+    inputSilent: "No sound from the microphone for a few seconds. Is it muted?",
     dictationStatus: "Status",
     dictationStatusHint: "Listening and transcription state",
     statusLabel: "Status",
@@ -549,13 +703,77 @@ const translations = {
     languageHint: "界面显示语言",
     dictationTitle: "语音输入",
     dictationDesc: "按住快捷键录音，松开开始转写",
-    dictationShortcutLabel: "按住快捷键",
+    dictationShortcutLabel: "听写快捷键",
     dictationShortcutHint: "按住录音，松开转写",
+    dictationShortcutToggleHint: "按一下开始录音，再按一下转写",
+    dictationShortcutDoubleTapHint: "按住录音，或双击后持续录音直到再次按下",
+    shortcutMode: "快捷键模式",
+    shortcutModeHint: "快捷键开始和结束听写的方式",
+    shortcutModeHold: "按住说话",
+    shortcutModeToggle: "切换",
+    shortcutModeDoubleTap: "双击锁定",
     dictationShortcutCaptureHint: "请按下新的快捷键（Esc 取消）",
     dictationShortcutChange: "修改",
     dictationShortcutListening: "监听中...",
+    undoShortcutLabel: "撤销上次听写",
+    undoShortcutHint: "删除上次听写输入的文字。也可以说“删掉刚才的”或“删除上一句”",
+    undoShortcutNone: "未设置",
+    undoShortcutClear: "清除",
     dictationAutoPaste: "自动粘贴",
     dictationAutoPasteHint: "复制后尝试粘贴到当前应用",
+    dictationVad: "跳过静音",
+    dictationVadHint: "本地转写前剪掉静音片段",
+    audioPreprocess: "麦克风音频优化",
+    audioPreprocessHint: "去除嗡嗡声和噪声，并统一音量",
+    transcriptCache: "缓存转写结果",
+    transcriptCacheHint: "同一文件再次转写时直接复用结果",
+    inputDevice: "麦克风",
+    inputDeviceHint: "设备拔出时回退到系统默认设备",
+    inputDeviceDefault: "系统默认",
+    preRoll: "预录",
+    preRollHint: "保持麦克风常开，按下快捷键时说出的第一个字不会被截断",
+    preRollOff: "关闭",
+    streamChunk: "实时音频分段",
+    streamChunkHint: "每次发送给实时转写的音频长度；分段越短，文字出现越快",
+    autoStopSilence: "静音自动停止",
+    autoStopSilenceHint: "停顿达到该时长后结束听写",
+    maxRecording: "最长时长",
+    maxRecordingHint: "听写超过该时长后自动结束",
+    limitOff: "关闭",
+    seconds: "{count} 秒",
+    minutes: "{count} 分钟",
+    spoolRecordings: "听写时保存录音",
+    spoolRecordingsHint: "录音时同步写入磁盘，应用崩溃也不会丢失",
+    flacUploads: "压缩云端上传",
+    flacUploadsHint: "以 FLAC 格式将听写发送给云端模型，体积约为 WAV 的六分之一",
+    localLiveDictation: "本地模型实时听写",
+    localLiveDictationHint: "每次停顿时输入该句，而不是松开快捷键后再输入",
+    spokenPunctuation: "语音标点",
+    spokenPunctuationHint: "说“逗号”“句号”“换行”“左引号”等插入对应的标点和格式",
+    historyEnabled: "转写历史",
+    historyEnabledHint: "在本机保存听写和试用的转写结果",
+    historyKeepAudio: "在历史中保留音频",
+    historyKeepAudioHint: "保存每段录音，以便之后用其他模型重新转写",
+    historyGateway: "包含网关请求",
+    historyGatewayHint: "同时保存其他应用通过本地网关请求的转写结果",
+    vocabulary: "词汇表",
+    vocabularyHint: "模型应识别的名称和术语，每行一个",
+    vocabularyPlaceholder: "Kubernetes\nOpenSTT\nSLO",
+    replacementRules: "替换规则",
+    replacementRulesHint: "修正仍然识别错误的词；普通文本按整词匹配",
+    replacementFind: "查找",
+    replacementReplace: "替换为",
+    replacementRegex: "正则",
+    replacementRemove: "删除",
+    replacementAdd: "添加规则",
+    recoveredRecordings: "上次未完成的录音",
+    recoveredRecording: "{date}，{duration}",
+    recoveredTranscribe: "转写",
+    recoveredDiscard: "丢弃",
+    recoveredTranscribed: "恢复的录音已添加到 Playground",
+    recordingDeviceLost: "麦克风已断开，录音已切换到 {device}",
+    recordingError: "录音出错：{message}",
+    inputSilent: "麦克风几秒内没有声音，是否已静音？",
     dictationStatus: "状态",
     dictationStatusHint: "录音与转写状态",
     statusLabel: "状态",
@@ -658,18 +876,43 @@ function App() {
     reducedTransparency: false,
     language: detectSystemLanguage(),
     dictationShortcut: defaultDictationShortcut,
+    undoShortcut: null,
     dictationAutoPaste: true,
     elevenlabsApiKey: "",
     sonioxApiKey: "",
     sonioxWarmConnection: false,
     sonioxWarmConnectionMinutes: 5,
     sonioxWarmConnectionForever: false,
+    dictationVad: false,
+    audioPreprocess: audioPreprocessOff,
+    transcriptCache: false,
+    inputDevice: "",
+    preRollMs: 0,
+    streamChunkMs: 100,
+    autoStopSilenceSecs: 0,
+    maxRecordingSecs: 0,
+    spoolRecordings: false,
+    flacUploads: true,
+    localLiveDictation: false,
+    vocabulary: { terms: [], replacements: [] },
+    spokenPunctuation: false,
+    historyEnabled: false,
+    historyKeepAudio: false,
+    historyGateway: false,
   });
   const [dictationCapture, setDictationCapture] = useState(false);
+  const [undoCapture, setUndoCapture] = useState(false);
   const [dictationState, setDictationState] = useState<
     "idle" | "listening" | "processing"
   >("idle");
   const [, setDictationQueueCount] = useState(0);
+  const [inputLevel, setInputLevel] = useState(0);
+  const [orphanedRecordings, setOrphanedRecordings] = useState<
+    OrphanedRecording[]
+  >([]);
+  const [recoveringRecording, setRecoveringRecording] = useState<
+    string | null
+  >(null);
   const [playgroundStatus, setPlaygroundStatus] = useState<
     "idle" | "recording" | "transcribing"
   >("idle");
@@ -697,6 +940,7 @@ function App() {
   const [legacyAction, setLegacyAction] = useState<"cleaning" | null>(null);
   const [deleteConfirmId, setDeleteConfirmId] = useState<string | null>(null);
   const [permissionStatus, setPermissionStatus] = useState<PermissionStatus | null>(null);
+  const [inputDevices, setInputDevices] = useState<InputDevice[]>([]);
   const [showOnboarding, setShowOnboarding] = useState(false);
   const [appVersion, setAppVersion] = useState("");
   const [updateChecking, setUpdateChecking] = useState(false);
@@ -922,6 +1166,10 @@ function App() {
     void refreshModels();
     void refreshMlxDeps();
     void invoke<LegacyModelsInfo>("check_legacy_models").then(setLegacyModels).catch(() => {});
+    void invoke<InputDevice[]>("list_input_devices").then(setInputDevices).catch(() => {});
+    void invoke<OrphanedRecording[]>("list_orphaned_recordings")
+      .then(setOrphanedRecordings)
+      .catch(() => {});
     void invoke<PermissionStatus>("check_all_permissions").then((status) => {
       setPermissionStatus(status);
       if (!status.inputMonitoring || status.microphone !== "granted" || !status.accessibility) {
@@ -938,6 +1186,7 @@ function App() {
           language: settings.language ?? detectSystemLanguage(),
           dictationShortcut:
             settings.dictationShortcut ?? defaultDictationShortcut,
+          undoShortcut: settings.undoShortcut ?? null,
           dictationAutoPaste: settings.dictationAutoPaste ?? true,
           sonioxWarmConnection: settings.sonioxWarmConnection ?? false,
           sonioxWarmConnectionMinutes:
@@ -947,6 +1196,22 @@ function App() {
             ),
           sonioxWarmConnectionForever:
             settings.sonioxWarmConnectionForever ?? false,
+          dictationVad: settings.dictationVad ?? false,
+          audioPreprocess: settings.audioPreprocess ?? audioPreprocessOff,
+          transcriptCache: settings.transcriptCache ?? false,
+          inputDevice: settings.inputDevice ?? "",
+          preRollMs: settings.preRollMs ?? 0,
+          streamChunkMs: settings.streamChunkMs ?? 100,
+          autoStopSilenceSecs: settings.autoStopSilenceSecs ?? 0,
+          maxRecordingSecs: settings.maxRecordingSecs ?? 0,
+          spoolRecordings: settings.spoolRecordings ?? false,
+          flacUploads: settings.flacUploads ?? true,
+          localLiveDictation: settings.localLiveDictation ?? false,
+          vocabulary: settings.vocabulary ?? { terms: [], replacements: [] },
+          spokenPunctuation: settings.spokenPunctuation ?? false,
+          historyEnabled: settings.historyEnabled ?? false,
+          historyKeepAudio: settings.historyKeepAudio ?? false,
+          historyGateway: settings.historyGateway ?? false,
         });
       } catch (err) {
         setError(String(err));
@@ -1106,7 +1371,10 @@ function App() {
       const nextShortcut = buildDictationShortcut(event);
       void persistSettings({
         ...uiSettings,
-        dictationShortcut: nextShortcut,
+        dictationShortcut: {
+          ...nextShortcut,
+          mode: uiSettings.dictationShortcut.mode,
+        },
       });
       setDictationCapture(false);
     };
@@ -1114,6 +1382,28 @@ function App() {
     return () => window.removeEventListener("keydown", handler, true);
   }, [dictationCapture, uiSettings]);
 
+  useEffect(() => {
+    if (!undoCapture) {
+      return;
+    }
+    const handler = (event: KeyboardEvent) => {
+      event.preventDefault();
+      event.stopPropagation();
+      if (event.key === "Escape") {
+        setUndoCapture(false);
+        return;
+      }
+      const nextShortcut = buildDictationShortcut(event);
+      if (isModifierKey(nextShortcut.key)) {
+        return;
+      }
+      void persistSettings({ ...uiSettings, undoShortcut: nextShortcut });
+      setUndoCapture(false);
+    };
+    window.addEventListener("keydown", handler, true);
+    return () => window.removeEventListener("keydown", handler, true);
+  }, [undoCapture, uiSettings]);
+
   useEffect(() => {
     let unlisten: (() => void) | null = null;
     void (async () => {
@@ -1156,6 +1446,62 @@ function App() {
     };
   }, []);
 
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    void (async () => {
+      unlisten = await listen<RecordingErrorEvent>(
+        "recording-error",
+        (event) => {
+          const { message, fallbackDevice } = event.payload;
+          setError(
+            fallbackDevice
+              ? t("recordingDeviceLost", { device: fallbackDevice })
+              : t("recordingError", { message }),
+          );
+        },
+      );
+    })();
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, [language]);
+
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    void (async () => {
+      unlisten = await listen<InputLevelEvent>("input-level", (event) => {
+        setInputLevel(levelToMeter(event.payload.rms));
+      });
+    })();
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, []);
+
+  useEffect(() => {
+    if (dictationState !== "listening") {
+      setInputLevel(0);
+    }
+  }, [dictationState]);
+
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    void (async () => {
+      unlisten = await listen("input-silence", () => {
+        setError(t("inputSilent"));
+      });
+    })();
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, [language]);
+
   useEffect(() => {
     let unlisten: (() => void) | null = null;
     void (async () => {
@@ -1319,6 +1665,47 @@ function App() {
     }
   };
 
+  const updateReplacement = (
+    index: number,
+    change: Partial<ReplacementRule>,
+  ) => {
+    const next = {
+      ...uiSettings,
+      vocabulary: {
+        ...uiSettings.vocabulary,
+        replacements: uiSettings.vocabulary.replacements.map((rule, other) =>
+          other === index ? { ...rule, ...change } : rule,
+        ),
+      },
+    };
+    setUiSettings(next);
+    return next;
+  };
+
+  const recoverRecording = async (name: string, transcribe: boolean) => {
+    setRecoveringRecording(name);
+    try {
+      if (transcribe) {
+        const text = await invoke<string>("transcribe_orphaned_recording", {
+          name,
+        });
+        if (text) {
+          setPlaygroundText((prev) => (prev ? prev + "\n" + text : text));
+        }
+        setSuccessMessage(t("recoveredTranscribed"));
+      } else {
+        await invoke("discard_orphaned_recording", { name });
+      }
+      setOrphanedRecordings((prev) =>
+        prev.filter((recording) => recording.name !== name),
+      );
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setRecoveringRecording(null);
+    }
+  };
+
   const handleToggleTransparency = async (next: boolean) => {
     await persistSettings({ ...uiSettings, reducedTransparency: next });
   };
@@ -1660,6 +2047,43 @@ function App() {
                 </button>
               </div>
             )}
+            {orphanedRecordings.length > 0 && (
+              <div className="recovery-banner">
+                <span>{t("recoveredRecordings")}</span>
+                {orphanedRecordings.map((recording) => (
+                  <div key={recording.name} className="recovery-item">
+                    <span>
+                      {t("recoveredRecording", {
+                        date: new Date(recording.modifiedAt).toLocaleString(),
+                        duration: t("seconds", {
+                          count: Math.round(recording.durationSecs),
+                        }),
+                      })}
+                    </span>
+                    <div className="recovery-actions">
+                      <button
+                        className="button tiny primary"
+                        disabled={recoveringRecording !== null}
+                        onClick={() =>
+                          void recoverRecording(recording.name, true)
+                        }
+                      >
+                        {t("recoveredTranscribe")}
+                      </button>
+                      <button
+                        className="button tiny ghost"
+                        disabled={recoveringRecording !== null}
+                        onClick={() =>
+                          void recoverRecording(recording.name, false)
+                        }
+                      >
+                        {t("recoveredDiscard")}
+                      </button>
+                    </div>
+                  </div>
+                ))}
+              </div>
+            )}
             {activePage === "overview" && (
               <>
                 {/* MLX Runtime */}
@@ -2453,7 +2877,11 @@ function App() {
                         <div className="settings-hint">
                           {dictationCapture
                             ? t("dictationShortcutCaptureHint")
-                            : t("dictationShortcutHint")}
+                            : uiSettings.dictationShortcut.mode === "toggle"
+                              ? t("dictationShortcutToggleHint")
+                              : uiSettings.dictationShortcut.mode === "doubleTap"
+                                ? t("dictationShortcutDoubleTapHint")
+                                : t("dictationShortcutHint")}
                         </div>
                       </div>
                       <div className="shortcut-control">
@@ -2477,6 +2905,79 @@ function App() {
                         </button>
                       </div>
                     </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">{t("shortcutMode")}</div>
+                        <div className="settings-hint">
+                          {t("shortcutModeHint")}
+                        </div>
+                      </div>
+                      <select
+                        value={uiSettings.dictationShortcut.mode ?? "hold"}
+                        onChange={(event) =>
+                          persistSettings({
+                            ...uiSettings,
+                            dictationShortcut: {
+                              ...uiSettings.dictationShortcut,
+                              mode: event.target.value as ShortcutMode,
+                            },
+                          })
+                        }
+                      >
+                        <option value="hold">{t("shortcutModeHold")}</option>
+                        <option value="toggle">{t("shortcutModeToggle")}</option>
+                        <option value="doubleTap">
+                          {t("shortcutModeDoubleTap")}
+                        </option>
+                      </select>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("undoShortcutLabel")}
+                        </div>
+                        <div className="settings-hint">
+                          {undoCapture
+                            ? t("dictationShortcutCaptureHint")
+                            : t("undoShortcutHint")}
+                        </div>
+                      </div>
+                      <div className="shortcut-control">
+                        <div
+                          className={`shortcut-display ${
+                            undoCapture ? "is-capturing" : ""
+                          }`}
+                        >
+                          {undoCapture
+                            ? t("dictationShortcutListening")
+                            : uiSettings.undoShortcut
+                              ? formatShortcutLabel(uiSettings.undoShortcut)
+                              : t("undoShortcutNone")}
+                        </div>
+                        <button
+                          className="button tiny"
+                          onClick={() => setUndoCapture(true)}
+                          disabled={undoCapture}
+                        >
+                          {undoCapture
+                            ? t("dictationShortcutListening")
+                            : t("dictationShortcutChange")}
+                        </button>
+                        {uiSettings.undoShortcut && !undoCapture && (
+                          <button
+                            className="button tiny"
+                            onClick={() =>
+                              persistSettings({
+                                ...uiSettings,
+                                undoShortcut: null,
+                              })
+                            }
+                          >
+                            {t("undoShortcutClear")}
+                          </button>
+                        )}
+                      </div>
+                    </div>
                     <div className="settings-row">
                       <div>
                         <div className="settings-label">
@@ -2501,6 +3002,499 @@ function App() {
                         <span className="switch-thumb" />
                       </button>
                     </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">{t("inputDevice")}</div>
+                        <div className="settings-hint">{t("inputDeviceHint")}</div>
+                      </div>
+                      <select
+                        value={uiSettings.inputDevice}
+                        onFocus={() =>
+                          void invoke<InputDevice[]>("list_input_devices")
+                            .then(setInputDevices)
+                            .catch(() => {})
+                        }
+                        onChange={(event) =>
+                          persistSettings({
+                            ...uiSettings,
+                            inputDevice: event.target.value,
+                          })
+                        }
+                      >
+                        <option value="">{t("inputDeviceDefault")}</option>
+                        {inputDevices.map((device) => (
+                          <option key={device.name} value={device.name}>
+                            {device.name}
+                          </option>
+                        ))}
+                        {uiSettings.inputDevice &&
+                          !inputDevices.some(
+                            (device) => device.name === uiSettings.inputDevice,
+                          ) && (
+                            <option value={uiSettings.inputDevice}>
+                              {uiSettings.inputDevice}
+                            </option>
+                          )}
+                      </select>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">{t("preRoll")}</div>
+                        <div className="settings-hint">{t("preRollHint")}</div>
+                      </div>
+                      <select
+                        value={uiSettings.preRollMs}
+                        onChange={(event) =>
+                          persistSettings({
+                            ...uiSettings,
+                            preRollMs: Number(event.target.value),
+                          })
+                        }
+                      >
+                        <option value={0}>{t("preRollOff")}</option>
+                        {[200, 300, 500, 1000].map((ms) => (
+                          <option key={ms} value={ms}>
+                            {ms} ms
+                          </option>
+                        ))}
+                      </select>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">{t("streamChunk")}</div>
+                        <div className="settings-hint">
+                          {t("streamChunkHint")}
+                        </div>
+                      </div>
+                      <select
+                        value={uiSettings.streamChunkMs}
+                        onChange={(event) =>
+                          persistSettings({
+                            ...uiSettings,
+                            streamChunkMs: Number(event.target.value),
+                          })
+                        }
+                      >
+                        {[50, 100, 200, 500].map((ms) => (
+                          <option key={ms} value={ms}>
+                            {ms} ms
+                          </option>
+                        ))}
+                      </select>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("autoStopSilence")}
+                        </div>
+                        <div className="settings-hint">
+                          {t("autoStopSilenceHint")}
+                        </div>
+                      </div>
+                      <select
+                        value={uiSettings.autoStopSilenceSecs}
+                        onChange={(event) =>
+                          persistSettings({
+                            ...uiSettings,
+                            autoStopSilenceSecs: Number(event.target.value),
+                          })
+                        }
+                      >
+                        <option value={0}>{t("limitOff")}</option>
+                        {[2, 3, 5, 10].map((count) => (
+                          <option key={count} value={count}>
+                            {t("seconds", { count: String(count) })}
+                          </option>
+                        ))}
+                      </select>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">{t("maxRecording")}</div>
+                        <div className="settings-hint">
+                          {t("maxRecordingHint")}
+                        </div>
+                      </div>
+                      <select
+                        value={uiSettings.maxRecordingSecs}
+                        onChange={(event) =>
+                          persistSettings({
+                            ...uiSettings,
+                            maxRecordingSecs: Number(event.target.value),
+                          })
+                        }
+                      >
+                        <option value={0}>{t("limitOff")}</option>
+                        {[1, 2, 5, 10, 30].map((count) => (
+                          <option key={count} value={count * 60}>
+                            {t("minutes", { count: String(count) })}
+                          </option>
+                        ))}
+                      </select>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("localLiveDictation")}
+                        </div>
+                        <div className="settings-hint">
+                          {t("localLiveDictationHint")}
+                        </div>
+                      </div>
+                      <button
+                        className={`switch ${
+                          uiSettings.localLiveDictation ? "is-on" : ""
+                        }`}
+                        onClick={() =>
+                          persistSettings({
+                            ...uiSettings,
+                            localLiveDictation: !uiSettings.localLiveDictation,
+                          })
+                        }
+                        aria-pressed={uiSettings.localLiveDictation}
+                      >
+                        <span className="switch-thumb" />
+                      </button>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("spokenPunctuation")}
+                        </div>
+                        <div className="settings-hint">
+                          {t("spokenPunctuationHint")}
+                        </div>
+                      </div>
+                      <button
+                        className={`switch ${
+                          uiSettings.spokenPunctuation ? "is-on" : ""
+                        }`}
+                        onClick={() =>
+                          persistSettings({
+                            ...uiSettings,
+                            spokenPunctuation: !uiSettings.spokenPunctuation,
+                          })
+                        }
+                        aria-pressed={uiSettings.spokenPunctuation}
+                      >
+                        <span className="switch-thumb" />
+                      </button>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("historyEnabled")}
+                        </div>
+                        <div className="settings-hint">
+                          {t("historyEnabledHint")}
+                        </div>
+                      </div>
+                      <button
+                        className={`switch ${
+                          uiSettings.historyEnabled ? "is-on" : ""
+                        }`}
+                        onClick={() =>
+                          persistSettings({
+                            ...uiSettings,
+                            historyEnabled: !uiSettings.historyEnabled,
+                          })
+                        }
+                        aria-pressed={uiSettings.historyEnabled}
+                      >
+                        <span className="switch-thumb" />
+                      </button>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("historyKeepAudio")}
+                        </div>
+                        <div className="settings-hint">
+                          {t("historyKeepAudioHint")}
+                        </div>
+                      </div>
+                      <button
+                        className={`switch ${
+                          uiSettings.historyKeepAudio ? "is-on" : ""
+                        }`}
+                        onClick={() =>
+                          persistSettings({
+                            ...uiSettings,
+                            historyKeepAudio: !uiSettings.historyKeepAudio,
+                          })
+                        }
+                        aria-pressed={uiSettings.historyKeepAudio}
+                        disabled={!uiSettings.historyEnabled}
+                      >
+                        <span className="switch-thumb" />
+                      </button>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("historyGateway")}
+                        </div>
+                        <div className="settings-hint">
+                          {t("historyGatewayHint")}
+                        </div>
+                      </div>
+                      <button
+                        className={`switch ${
+                          uiSettings.historyGateway ? "is-on" : ""
+                        }`}
+                        onClick={() =>
+                          persistSettings({
+                            ...uiSettings,
+                            historyGateway: !uiSettings.historyGateway,
+                          })
+                        }
+                        aria-pressed={uiSettings.historyGateway}
+                        disabled={!uiSettings.historyEnabled}
+                      >
+                        <span className="switch-thumb" />
+                      </button>
+                    </div>
+                    <div className="settings-row vocabulary-row">
+                      <div>
+                        <div className="settings-label">{t("vocabulary")}</div>
+                        <div className="settings-hint">
+                          {t("vocabularyHint")}
+                        </div>
+                      </div>
+                      <textarea
+                        className="vocabulary-terms"
+                        rows={4}
+                        placeholder={t("vocabularyPlaceholder")}
+                        value={uiSettings.vocabulary.terms.join("\n")}
+                        onChange={(event) =>
+                          setUiSettings({
+                            ...uiSettings,
+                            vocabulary: {
+                              ...uiSettings.vocabulary,
+                              terms: event.target.value.split("\n"),
+                            },
+                          })
+                        }
+                        onBlur={() => void persistSettings(uiSettings)}
+                      />
+                    </div>
+                    <div className="settings-row vocabulary-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("replacementRules")}
+                        </div>
+                        <div className="settings-hint">
+                          {t("replacementRulesHint")}
+                        </div>
+                      </div>
+                      <div className="replacement-rules">
+                        {uiSettings.vocabulary.replacements.map((rule, index) => (
+                          <div className="replacement-rule" key={index}>
+                            <input
+                              value={rule.find}
+                              placeholder={t("replacementFind")}
+                              onChange={(event) =>
+                                updateReplacement(index, {
+                                  find: event.target.value,
+                                })
+                              }
+                              onBlur={() => void persistSettings(uiSettings)}
+                            />
+                            <input
+                              value={rule.replace}
+                              placeholder={t("replacementReplace")}
+                              onChange={(event) =>
+                                updateReplacement(index, {
+                                  replace: event.target.value,
+                                })
+                              }
+                              onBlur={() => void persistSettings(uiSettings)}
+                            />
+                            <label className="replacement-regex">
+                              <input
+                                type="checkbox"
+                                checked={rule.regex}
+                                onChange={(event) =>
+                                  void persistSettings(
+                                    updateReplacement(index, {
+                                      regex: event.target.checked,
+                                    }),
+                                  )
+                                }
+                              />
+                              {t("replacementRegex")}
+                            </label>
+                            <button
+                              className="button tiny"
+                              onClick={() =>
+                                void persistSettings({
+                                  ...uiSettings,
+                                  vocabulary: {
+                                    ...uiSettings.vocabulary,
+                                    replacements:
+                                      uiSettings.vocabulary.replacements.filter(
+                                        (_, other) => other !== index,
+                                      ),
+                                  },
+                                })
+                              }
+                            >
+                              {t("replacementRemove")}
+                            </button>
+                          </div>
+                        ))}
+                        <button
+                          className="button tiny"
+                          onClick={() =>
+                            void persistSettings({
+                              ...uiSettings,
+                              vocabulary: {
+                                ...uiSettings.vocabulary,
+                                replacements: [
+                                  ...uiSettings.vocabulary.replacements,
+                                  {
+                                    find: "",
+                                    replace: "",
+                                    regex: false,
+                                    caseSensitive: false,
+                                  },
+                                ],
+                              },
+                            })
+                          }
+                        >
+                          {t("replacementAdd")}
+                        </button>
+                      </div>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("spoolRecordings")}
+                        </div>
+                        <div className="settings-hint">
+                          {t("spoolRecordingsHint")}
+                        </div>
+                      </div>
+                      <button
+                        className={`switch ${
+                          uiSettings.spoolRecordings ? "is-on" : ""
+                        }`}
+                        onClick={() =>
+                          persistSettings({
+                            ...uiSettings,
+                            spoolRecordings: !uiSettings.spoolRecordings,
+                          })
+                        }
+                        aria-pressed={uiSettings.spoolRecordings}
+                      >
+                        <span className="switch-thumb" />
+                      </button>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("flacUploads")}
+                        </div>
+                        <div className="settings-hint">
+                          {t("flacUploadsHint")}
+                        </div>
+                      </div>
+                      <button
+                        className={`switch ${
+                          uiSettings.flacUploads ? "is-on" : ""
+                        }`}
+                        onClick={() =>
+                          persistSettings({
+                            ...uiSettings,
+                            flacUploads: !uiSettings.flacUploads,
+                          })
+                        }
+                        aria-pressed={uiSettings.flacUploads}
+                      >
+                        <span className="switch-thumb" />
+                      </button>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("dictationVad")}
+                        </div>
+                        <div className="settings-hint">
+                          {t("dictationVadHint")}
+                        </div>
+                      </div>
+                      <button
+                        className={`switch ${
+                          uiSettings.dictationVad ? "is-on" : ""
+                        }`}
+                        onClick={() =>
+                          persistSettings({
+                            ...uiSettings,
+                            dictationVad: !uiSettings.dictationVad,
+                          })
+                        }
+                        aria-pressed={uiSettings.dictationVad}
+                      >
+                        <span className="switch-thumb" />
+                      </button>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("audioPreprocess")}
+                        </div>
+                        <div className="settings-hint">
+                          {t("audioPreprocessHint")}
+                        </div>
+                      </div>
+                      <button
+                        className={`switch ${
+                          isAudioPreprocessOn(uiSettings.audioPreprocess)
+                            ? "is-on"
+                            : ""
+                        }`}
+                        onClick={() =>
+                          persistSettings({
+                            ...uiSettings,
+                            audioPreprocess: isAudioPreprocessOn(
+                              uiSettings.audioPreprocess,
+                            )
+                              ? audioPreprocessOff
+                              : audioPreprocessRecommended,
+                          })
+                        }
+                        aria-pressed={isAudioPreprocessOn(
+                          uiSettings.audioPreprocess,
+                        )}
+                      >
+                        <span className="switch-thumb" />
+                      </button>
+                    </div>
+                    <div className="settings-row">
+                      <div>
+                        <div className="settings-label">
+                          {t("transcriptCache")}
+                        </div>
+                        <div className="settings-hint">
+                          {t("transcriptCacheHint")}
+                        </div>
+                      </div>
+                      <button
+                        className={`switch ${
+                          uiSettings.transcriptCache ? "is-on" : ""
+                        }`}
+                        onClick={() =>
+                          persistSettings({
+                            ...uiSettings,
+                            transcriptCache: !uiSettings.transcriptCache,
+                          })
+                        }
+                        aria-pressed={uiSettings.transcriptCache}
+                      >
+                        <span className="switch-thumb" />
+                      </button>
+                    </div>
 
                     <div className="settings-row">
                       <div>
@@ -2944,6 +3938,14 @@ function App() {
               : dictationState === "listening"
                 ? t("dictationListening")
                 : t("dictationProcessing")}
+            {dictationState === "listening" && (
+              <span className="level-meter" aria-hidden="true">
+                <span
+                  className="level-meter-fill"
+                  style={{ width: `${Math.round(inputLevel * 100)}%` }}
+                />
+              </span>
+            )}
             {dictationState !== "idle" && (
               <button
                 className="stop-button-inline"